@@ -0,0 +1,54 @@
+//! Start and remove fixtures shared by a test run, see `test_utilities::docker::setup`.
+
+use std::error::Error;
+use std::fs::OpenOptions;
+use std::process::ExitCode;
+
+use test_utilities::docker::setup;
+
+const USAGE: &str = "usage: test-utilities-setupd up <fixtures.json> <descriptors.json>
+       test-utilities-setupd down <descriptors.json>";
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let result = match args.as_slice() {
+        ["up", config, descriptors] => up(config, descriptors).await,
+        ["down", descriptors] => down(descriptors),
+        _ => {
+            eprintln!("{USAGE}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("test-utilities-setupd: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn up(config: &str, descriptors: &str) -> Result<(), Box<dyn Error>> {
+    let config = setup::SetupConfig::from_file(config)?;
+    let fixtures = setup::start(&config.fixtures).await?;
+    setup::write_descriptors(descriptors, &fixtures)?;
+
+    // set when run as a nextest setup script
+    if let Some(path) = std::env::var_os("NEXTEST_ENV") {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        setup::write_nextest_env(&fixtures, file)?;
+    }
+    for fixture in &fixtures {
+        println!("started {} ({})", fixture.name, fixture.container_id);
+    }
+    Ok(())
+}
+
+fn down(descriptors: &str) -> Result<(), Box<dyn Error>> {
+    let fixtures = setup::read_descriptors(descriptors)?;
+    setup::teardown(&fixtures)?;
+    Ok(())
+}
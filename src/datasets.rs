@@ -0,0 +1,185 @@
+//! Optional datasets making the fakers produce statistically realistic corpora, e.g. for
+//! search and indexing tests, each behind its own `dataset-*` feature.
+//!
+//! The frequencies are rough shares observed in public corpora, not exact figures.
+
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+/// Common account names, by how often they occur in leaked username lists.
+#[cfg(feature = "dataset-usernames")]
+pub const USERNAMES: &[(&str, u32)] = &[
+    ("admin", 120),
+    ("user", 80),
+    ("test", 70),
+    ("info", 60),
+    ("john", 45),
+    ("david", 40),
+    ("michael", 40),
+    ("alex", 35),
+    ("chris", 35),
+    ("daniel", 30),
+    ("maria", 30),
+    ("anna", 28),
+    ("james", 28),
+    ("robert", 26),
+    ("sarah", 25),
+    ("mark", 24),
+    ("paul", 22),
+    ("laura", 22),
+    ("kevin", 20),
+    ("jessica", 20),
+    ("thomas", 20),
+    ("emma", 18),
+    ("peter", 18),
+    ("lisa", 18),
+    ("support", 16),
+    ("sales", 15),
+    ("contact", 15),
+    ("web", 12),
+    ("dev", 12),
+    ("root", 12),
+    ("guest", 10),
+    ("service", 10),
+    ("office", 9),
+    ("backup", 8),
+    ("oracle", 6),
+    ("postgres", 6),
+    ("ubuntu", 6),
+    ("jenkins", 5),
+    ("git", 5),
+    ("deploy", 5),
+];
+
+/// Email domains, by their share of the addresses of a typical consumer service.
+#[cfg(feature = "dataset-domains")]
+pub const DOMAINS: &[(&str, u32)] = &[
+    ("gmail.com", 400),
+    ("yahoo.com", 120),
+    ("hotmail.com", 90),
+    ("outlook.com", 60),
+    ("icloud.com", 40),
+    ("aol.com", 20),
+    ("qq.com", 20),
+    ("163.com", 15),
+    ("gmx.de", 12),
+    ("web.de", 12),
+    ("mail.ru", 12),
+    ("yandex.ru", 10),
+    ("live.com", 10),
+    ("msn.com", 8),
+    ("protonmail.com", 8),
+    ("comcast.net", 6),
+    ("orange.fr", 6),
+    ("free.fr", 5),
+    ("libero.it", 5),
+    ("naver.com", 5),
+    ("yahoo.co.jp", 5),
+    ("btinternet.com", 4),
+    ("t-online.de", 4),
+    ("rediffmail.com", 3),
+    ("example.com", 2),
+];
+
+/// File extensions, by their share of the files of a typical user home directory.
+#[cfg(feature = "dataset-extensions")]
+pub const EXTENSIONS: &[(&str, u32)] = &[
+    ("jpg", 220),
+    ("png", 90),
+    ("pdf", 70),
+    ("txt", 60),
+    ("docx", 45),
+    ("js", 40),
+    ("json", 35),
+    ("html", 30),
+    ("xlsx", 25),
+    ("mp3", 25),
+    ("py", 20),
+    ("xml", 20),
+    ("csv", 18),
+    ("gif", 18),
+    ("zip", 15),
+    ("mp4", 15),
+    ("md", 12),
+    ("log", 12),
+    ("css", 12),
+    ("svg", 10),
+    ("pptx", 10),
+    ("doc", 10),
+    ("rs", 8),
+    ("java", 8),
+    ("c", 8),
+    ("h", 8),
+    ("yaml", 6),
+    ("tar.gz", 5),
+    ("mov", 5),
+    ("exe", 4),
+    ("iso", 2),
+];
+
+/// Pick an entry of `dataset` according to its frequencies.
+pub fn choose_weighted<R: Rng + ?Sized>(
+    dataset: &[(&'static str, u32)],
+    rng: &mut R,
+) -> &'static str {
+    dataset
+        .choose_weighted(rng, |(_, weight)| *weight)
+        .map(|(entry, _)| *entry)
+        .expect("datasets are not empty")
+}
+
+/// A username drawn from `USERNAMES`, suffixed with digits half of the time, like `john84`.
+#[cfg(feature = "dataset-usernames")]
+pub fn fake_username_with_rng<R: Rng + ?Sized>(rng: &mut R) -> String {
+    let name = choose_weighted(USERNAMES, rng);
+    if rng.gen_bool(0.5) {
+        format!("{name}{}", rng.gen_range(1..100))
+    } else {
+        name.to_string()
+    }
+}
+
+/// An email address of a username of `USERNAMES` at a domain of `DOMAINS`.
+#[cfg(all(feature = "dataset-usernames", feature = "dataset-domains"))]
+pub fn fake_email_with_rng<R: Rng + ?Sized>(rng: &mut R) -> String {
+    let username = fake_username_with_rng(rng);
+    format!("{username}@{}", choose_weighted(DOMAINS, rng))
+}
+
+#[cfg(all(
+    test,
+    feature = "dataset-usernames",
+    feature = "dataset-domains",
+    feature = "dataset-extensions"
+))]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+
+    #[test]
+    fn test_choose_weighted() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let picks: Vec<_> = (0..2000)
+            .map(|_| choose_weighted(EXTENSIONS, &mut rng))
+            .collect();
+        let count = |ext: &str| picks.iter().filter(|pick| **pick == ext).count();
+
+        // jpg is about a hundred times as frequent as iso
+        assert!(count("jpg") > 10 * count("iso"));
+        assert!(picks
+            .iter()
+            .all(|pick| EXTENSIONS.iter().any(|(ext, _)| ext == pick)));
+    }
+
+    #[test]
+    fn test_fake_email() {
+        let mut rng = StdRng::seed_from_u64(7);
+        for _ in 0..100 {
+            let email = fake_email_with_rng(&mut rng);
+            let (_, domain) = email.split_once('@').unwrap();
+            assert!(DOMAINS.iter().any(|(known, _)| *known == domain));
+        }
+    }
+}
@@ -1,59 +1,515 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-use bollard::models::{ContainerInspectResponse, PortBinding};
-use bollard::{
-    container::{CreateContainerOptions, StartContainerOptions},
-    service::HostConfig,
-};
+use bollard::auth::DockerCredentials;
+use bollard::models::{ContainerInspectResponse, EndpointSettings, HealthConfig, PortBinding};
+use bollard::{container::CreateContainerOptions, service::HostConfig};
+use futures::future::BoxFuture;
+use futures::stream::BoxStream;
+use futures::StreamExt;
+
+use crate::env::EnvGuard;
+use crate::fixture::Fixture;
+
+pub(crate) use backend::block_on;
+pub use backend::{Backend, BackendResult, ExecOutput};
+pub use backoff::{connect_with_backoff, BackoffPolicy};
+pub use changes::{ChangeKind, FsChange};
+pub use digest::{resolve_digest, resolve_digest_with};
+pub use error::{Error, Stage, UrlError, ValidationError, WaitError};
+pub use host::{connect, DOCKER_HOST_ENV, DOCKER_TLS_VERIFY_ENV};
+#[cfg(feature = "http")]
+pub use http::{HttpClient, DEFAULT_WARMUP};
+pub use lazy::LazyHandle;
+pub use logs::{LogLine, LogSource};
+pub use name::unique_name;
+pub use network::NetworkHandle;
+pub use port::{ContainerPort, HostPort, ParsePortError, Protocol};
+pub use reaper::SESSION_LABEL;
+pub use reuse::REUSE_LABEL;
+pub use stack::{Stack, StackHandle};
+pub use status::{status_report, status_report_with, FixtureStatus, Health, CRATE_LABEL};
+pub use teardown::TeardownReport;
+pub use throttle::{set_max_concurrent_containers, MAX_CONTAINERS_ENV};
+pub use volume::BindOpts;
+pub use wait::{WaitStrategy, DEFAULT_WAIT_TIMEOUT};
+
+mod archive;
+mod backend;
+mod backoff;
+mod changes;
+pub mod creds;
+mod digest;
+mod error;
+mod graph;
+#[cfg(feature = "tonic")]
+mod grpc;
+mod host;
+#[cfg(feature = "http")]
+mod http;
+mod lazy;
+mod logs;
+pub mod mock;
+mod name;
+mod network;
+mod port;
+pub mod presets;
+pub mod reaper;
+mod reuse;
+#[cfg(feature = "setupd")]
+pub mod setup;
+#[cfg(feature = "tracing")]
+mod span;
+mod stack;
+mod status;
+pub mod teardown;
+mod throttle;
+pub mod timing;
+mod volume;
+mod wait;
+pub mod watchdog;
 
 pub struct ContainerHandle {
     pub container_id: String,
     pub name: Option<String>,
     pub host_ip: String,
-    pub default_host_port: Option<String>,
+    pub default_host_port: Option<HostPort>,
     pub protocol: Option<String>,
-    docker: bollard::Docker,
+    /// Container port `url` points to
+    default_port: Option<ContainerPort>,
+    backend: Arc<dyn Backend>,
+    /// Inspect response captured right after the container started
+    info: ContainerInspectResponse,
+    /// Whether the container has to be removed explicitly when the handle is dropped
+    remove_on_drop: bool,
+    /// Whether the container outlives the handle, see `detach`
+    detached: bool,
+    /// Whether to print the output of the container when the handle is dropped
+    tee_logs: bool,
+    /// Digest reference the image was pinned to, see `Builder::pin_digest`
+    digest: Option<String>,
+    /// When the container got started, for its teardown report
+    started: Instant,
+    credentials: Option<creds::Credentials>,
+    /// Slot of the container under `set_max_concurrent_containers`, released after disposal
+    _permit: Option<tokio::sync::OwnedSemaphorePermit>,
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
 }
 
 impl ContainerHandle {
-    pub fn url(&self) -> String {
-        let protocol = self.protocol.as_ref().unwrap();
-        match self.default_host_port.as_ref() {
+    /// Environment variables the container was started with.
+    pub fn env(&self) -> HashMap<String, String> {
+        self.info
+            .config
+            .as_ref()
+            .and_then(|config| config.env.as_ref())
+            .map(|env| {
+                env.iter()
+                    .map(|entry| match entry.split_once('=') {
+                        Some((key, value)) => (key.to_string(), value.to_string()),
+                        None => (entry.clone(), String::new()),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Image the container was created from, as requested at creation.
+    pub fn image(&self) -> Option<&str> {
+        self.info
+            .config
+            .as_ref()
+            .and_then(|config| config.image.as_deref())
+    }
+
+    /// Labels the container was created with, including those set by the builder.
+    pub fn labels(&self) -> HashMap<String, String> {
+        self.info
+            .config
+            .as_ref()
+            .and_then(|config| config.labels.clone())
+            .unwrap_or_default()
+    }
+
+    /// Export the coordinates of the service as `<PREFIX>_URL`, `<PREFIX>_HOST` and
+    /// `<PREFIX>_PORT`, plus `<PREFIX>_USERNAME`, `<PREFIX>_PASSWORD` and `<PREFIX>_DATABASE`
+    /// if the handle has credentials, so that processes spawned by the test can find it.
+    ///
+    /// Variables which cannot be resolved, such as the url of a container without protocol,
+    /// are left out. They are all restored when the returned guard is dropped.
+    pub fn export_env<S: AsRef<str>>(&self, prefix: S) -> EnvGuard {
+        self.env_vars(prefix)
+            .into_iter()
+            .fold(EnvGuard::new(), |guard, (key, value)| guard.set(key, value))
+    }
+
+    /// The variables `export_env` sets, as name and value pairs.
+    pub fn env_vars<S: AsRef<str>>(&self, prefix: S) -> Vec<(String, String)> {
+        let prefix = prefix.as_ref();
+        let mut vars = vec![(format!("{prefix}_HOST"), self.host_ip.clone())];
+        if let Ok(url) = self.url() {
+            vars.push((format!("{prefix}_URL"), url));
+        }
+        if let Some(port) = self.default_host_port {
+            vars.push((format!("{prefix}_PORT"), port.to_string()));
+        }
+        if let Some(credentials) = self.credentials.as_ref() {
+            vars.push((format!("{prefix}_USERNAME"), credentials.username.clone()));
+            vars.push((format!("{prefix}_PASSWORD"), credentials.password.clone()));
+            vars.push((format!("{prefix}_DATABASE"), credentials.database.clone()));
+        }
+        vars
+    }
+
+    /// Keep the container running after the handle is dropped, e.g. to share it with other
+    /// processes, returning its id. Removing it is then up to the caller.
+    pub fn detach(mut self) -> String {
+        self.detached = true;
+        self.container_id.clone()
+    }
+
+    /// Output of the container line by line, following it until the container stops. The
+    /// stream ends early if the daemon fails to serve it.
+    pub fn logs(&self) -> BoxStream<'_, LogLine> {
+        let id = self.container_id.as_str();
+        self.backend
+            .log_stream(id, true)
+            .take_while(move |line| {
+                if let Err(err) = line {
+                    log::warn!("failed to stream logs of container {id}: {err}");
+                }
+                futures::future::ready(line.is_ok())
+            })
+            .filter_map(|line| futures::future::ready(line.ok()))
+            .boxed()
+    }
+
+    /// Output of the container so far, e.g. to dump it when a test fails.
+    pub async fn logs_to_string(&self) -> Result<String, Error> {
+        self.backend
+            .logs(&self.container_id)
+            .await
+            .map_err(|err| self.error(Stage::ReadLogs, err))
+    }
+
+    /// Copy the file or directory at `local_path` to `container_path`, e.g. to seed a config
+    /// file. The parent directory has to exist in the container.
+    pub async fn copy_in<P: AsRef<Path>>(
+        &self,
+        local_path: P,
+        container_path: &str,
+    ) -> Result<(), Error> {
+        let (dir, name) = archive::split_path(container_path);
+        let archive =
+            archive::pack(local_path.as_ref(), name).map_err(|err| self.error(Stage::Copy, err))?;
+        self.backend
+            .upload_archive(&self.container_id, dir, archive)
+            .await
+            .map_err(|err| self.error(Stage::Copy, err))
+    }
+
+    /// Content of the file at `container_path`, e.g. an artifact generated by the container.
+    pub async fn copy_out(&self, container_path: &str) -> Result<Vec<u8>, Error> {
+        let archive = self
+            .backend
+            .download_archive(&self.container_id, container_path)
+            .await
+            .map_err(|err| self.error(Stage::Copy, err))?;
+        archive::unpack_file(&archive).map_err(|err| self.error(Stage::Copy, err))
+    }
+
+    /// Run `cmd` in the container until it exits, e.g. a CLI shipped in the image to set up
+    /// the service. Commands exiting with a non-zero code are not an error.
+    pub async fn exec<I, S>(&self, cmd: I) -> Result<ExecOutput, Error>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let cmd = cmd.into_iter().map(Into::into).collect();
+        self.backend
+            .exec(&self.container_id, cmd)
+            .await
+            .map_err(|err| self.error(Stage::Exec, err))
+    }
+
+    /// Paths the container changed relative to its image, parent directories of changed paths
+    /// included as modified.
+    pub async fn fs_changes(&self) -> Result<Vec<FsChange>, Error> {
+        self.backend
+            .container_changes(&self.container_id)
+            .await
+            .map_err(|err| self.error(Stage::Inspect, err))
+    }
+
+    /// Changes of the container outside of `dirs`, e.g. to check that a service configured
+    /// with a read-only root filesystem in mind only writes to its data directories.
+    pub async fn fs_changes_outside(&self, dirs: &[&str]) -> Result<Vec<FsChange>, Error> {
+        let mut changes = self.fs_changes().await?;
+        changes.retain(|change| !change.is_within(dirs));
+        Ok(changes)
+    }
+
+    /// A channel to the gRPC server on the default port, connected in plaintext.
+    #[cfg(feature = "tonic")]
+    pub async fn grpc_channel(&self) -> Result<tonic::transport::Channel, Error> {
+        let endpoint = grpc::endpoint(self).map_err(|err| self.error(Stage::ResolveUrl, err))?;
+        endpoint
+            .connect()
+            .await
+            .map_err(|err| self.error(Stage::Connect, err))
+    }
+
+    /// Wait until the healthcheck of the container passes, failing if it turns unhealthy, the
+    /// container exits, or `timeout` elapses.
+    pub async fn wait_healthy(&self, timeout: Duration) -> Result<(), Error> {
+        wait::wait_until_ready(self, &WaitStrategy::Healthy, timeout)
+            .await
+            .map_err(|err| Error::new(Stage::WaitReady, err))
+    }
+
+    /// Stop the container, and remove it unless the daemon does, awaiting it rather than
+    /// blocking in drop. The report tells which state the container was found in.
+    pub async fn stop(mut self) -> Result<TeardownReport, Error> {
+        // the handle no longer owns the container, whatever the outcome
+        self.detached = true;
+        let stop = async {
+            let found = self
+                .backend
+                .inspect_container(&self.container_id)
+                .await
+                .ok();
+            self.backend
+                .stop_container(&self.container_id, self.remove_on_drop)
+                .await
+                .map_err(|err| self.error(Stage::Stop, err))?;
+            let report = self.teardown_report(found.as_ref());
+            teardown::record(report.clone());
+            Ok(report)
+        };
+        #[cfg(feature = "tracing")]
+        let stop = tracing::Instrument::instrument(stop, self.span.clone());
+        stop.await
+    }
+
+    /// Credentials the service in the container was set up with, if any.
+    pub fn credentials(&self) -> Option<&creds::Credentials> {
+        self.credentials.as_ref()
+    }
+
+    /// The digest reference, e.g. `mongo@sha256:...`, the container was created from if the
+    /// builder pinned the image.
+    pub fn pinned_digest(&self) -> Option<&str> {
+        self.digest.as_deref()
+    }
+
+    /// The docker client the container was created with, for calling APIs this crate does not
+    /// wrap. It is `None` when the container lives on a non-docker backend such as the mock.
+    pub fn docker(&self) -> Option<&bollard::Docker> {
+        self.backend.as_docker()
+    }
+
+    /// Span of the container, with its name, id, image and published ports as fields.
+    ///
+    /// The handle enters it while waiting for, stopping and disposing of the container.
+    /// Instrumenting the code of a test with it attributes the events emitted meanwhile to the
+    /// container, those of the `log` crate included once bridged by `tracing-log`.
+    #[cfg(feature = "tracing")]
+    pub fn span(&self) -> &tracing::Span {
+        &self.span
+    }
+
+    pub(crate) fn backend(&self) -> Arc<dyn Backend> {
+        self.backend.clone()
+    }
+
+    /// Host port `port` of the container was published on when it started.
+    pub fn host_port<P: Into<ContainerPort>>(&self, port: P) -> Option<HostPort> {
+        self.info.get_host_port(Some(host::BIND_IP), port.into())
+    }
+
+    /// A `reqwest` client for the service on the default port, over https if that is the
+    /// protocol of the container and http otherwise.
+    #[cfg(feature = "http")]
+    pub fn http(&self) -> HttpClient {
+        let scheme = match self.protocol.as_deref() {
+            Some("https") => "https",
+            _ => "http",
+        };
+        HttpClient::new(match self.default_host_port.as_ref() {
+            Some(port) => format!("{scheme}://{}:{port}", self.host_ip),
+            None => format!("{scheme}://{}", self.host_ip),
+        })
+    }
+
+    pub fn url(&self) -> Result<String, Error> {
+        let protocol = self.protocol()?;
+        Ok(match self.default_host_port.as_ref() {
             Some(port) => format!("{protocol}://{host}:{port}/", host = self.host_ip.as_str()),
             None => format!("{protocol}://{host}/", host = self.host_ip.as_str()),
-        }
+        })
     }
 
-    pub async fn url_by<S: AsRef<str>>(&self, port: S) -> Option<String> {
-        let protocol = self.protocol.as_ref().unwrap();
-        let port = port.as_ref();
+    pub async fn url_by<P: Into<ContainerPort>>(&self, port: P) -> Result<String, Error> {
+        let protocol = self.protocol()?;
+        let port = port.into();
 
         let info = self
-            .docker
-            .inspect_container(&self.container_id, None)
+            .backend
+            .inspect_container(&self.container_id)
             .await
-            .unwrap();
-        let host_port = info.get_host_port(Some(self.host_ip.as_str()), port);
-        host_port.map(|host_port| {
-            format!(
-                "{protocol}://{host}:{host_port}",
-                host = self.host_ip.as_str()
-            )
+            .map_err(|err| self.error(Stage::Inspect, err))?;
+        let host_port = info
+            .get_host_port(Some(host::BIND_IP), port)
+            .ok_or_else(|| self.error(Stage::ResolveUrl, UrlError::UnboundPort(port)))?;
+        Ok(format!(
+            "{protocol}://{host}:{host_port}",
+            host = self.host_ip.as_str()
+        ))
+    }
+
+    /// Names the container is reachable by from the other containers on `network`, the
+    /// aliases given with `Builder::network_alias` first. It is empty if the container is not
+    /// attached to `network`.
+    pub fn network_aliases(&self, network: &str) -> Vec<String> {
+        let endpoint = self
+            .info
+            .network_settings
+            .as_ref()
+            .and_then(|settings| settings.networks.as_ref())
+            .and_then(|networks| networks.get(network));
+        let Some(endpoint) = endpoint else {
+            return Vec::new();
+        };
+        let mut aliases = endpoint.aliases.clone().unwrap_or_default();
+        if let Some(name) = &self.name {
+            if !aliases.contains(name) {
+                aliases.push(name.clone());
+            }
+        }
+        aliases
+    }
+
+    /// Url of the default port as seen from the other containers on `network`, e.g.
+    /// `mongodb://mongo:27017/` for an app container to connect to.
+    pub fn network_url(&self, network: &str) -> Result<String, Error> {
+        let protocol = self.protocol()?;
+        let alias = self
+            .network_aliases(network)
+            .into_iter()
+            .next()
+            .ok_or_else(|| {
+                self.error(
+                    Stage::ResolveUrl,
+                    UrlError::NotOnNetwork(network.to_string()),
+                )
+            })?;
+        Ok(match self.default_port {
+            Some(port) => format!("{protocol}://{alias}:{}/", port.port()),
+            None => format!("{protocol}://{alias}/"),
         })
     }
+
+    fn protocol(&self) -> Result<&str, Error> {
+        self.protocol
+            .as_deref()
+            .ok_or_else(|| self.error(Stage::ResolveUrl, UrlError::MissingProtocol))
+    }
+
+    fn error<E>(&self, stage: Stage, source: E) -> Error
+    where
+        E: Into<Box<dyn std::error::Error + Send + Sync>>,
+    {
+        Error::new(stage, source).with_container(self.image(), self.name.as_deref())
+    }
+}
+
+impl ContainerHandle {
+    /// Report of the teardown of the container, `found` being how it was inspected right before.
+    fn teardown_report(&self, found: Option<&ContainerInspectResponse>) -> TeardownReport {
+        let state = found.and_then(|info| info.state.as_ref());
+        let auto_remove = self
+            .info
+            .host_config
+            .as_ref()
+            .and_then(|host_config| host_config.auto_remove)
+            .unwrap_or(false);
+        TeardownReport {
+            container_id: self.container_id.clone(),
+            name: self.name.clone(),
+            image: self.image().map(str::to_string),
+            state: state
+                .and_then(|state| state.status)
+                .map(|status| status.to_string()),
+            exit_code: state
+                .filter(|state| state.running != Some(true))
+                .and_then(|state| state.exit_code),
+            duration: self.started.elapsed(),
+            volumes_removed: if self.remove_on_drop || auto_remove {
+                teardown::anonymous_volumes(found.unwrap_or(&self.info))
+            } else {
+                Vec::new()
+            },
+        }
+    }
+
+    fn print_logs(&self) {
+        let backend = self.backend.clone();
+        let id = self.container_id.clone();
+        match backend::block_on(async move { backend.logs(&id).await }) {
+            Ok(Ok(logs)) => eprintln!("output of container {}:\n{logs}", self.container_id),
+            Ok(Err(err)) => log::warn!("failed to read logs of {}: {err}", self.container_id),
+            Err(err) => log::warn!("failed to read logs of {}: {err}", self.container_id),
+        }
+    }
 }
 
 impl Drop for ContainerHandle {
     fn drop(&mut self) {
-        std::process::Command::new("docker")
-            .arg("stop")
-            .arg(self.container_id.trim())
-            .output()
-            .unwrap();
+        #[cfg(feature = "tracing")]
+        let _entered = self.span.clone().entered();
+        if let Some(digest) = self.digest.as_ref().filter(|_| std::thread::panicking()) {
+            eprintln!("container {} ran image {digest}", self.container_id);
+        }
+        // before disposing, which may remove the container along with its logs
+        if self.tee_logs {
+            self.print_logs();
+        }
+        if self.detached {
+            return;
+        }
+        if teardown::is_enabled() {
+            let backend = self.backend.clone();
+            let id = self.container_id.clone();
+            let found = backend::block_on(async move { backend.inspect_container(&id).await })
+                .ok()
+                .and_then(Result::ok);
+            self.backend
+                .dispose(&self.container_id, self.remove_on_drop);
+            teardown::record(self.teardown_report(found.as_ref()));
+        } else {
+            self.backend
+                .dispose(&self.container_id, self.remove_on_drop);
+        }
+    }
+}
+
+impl Fixture for ContainerHandle {
+    /// Stop the container, returning a failure to the caller instead of only logging it as
+    /// `Drop` does. Containers left running for reuse are only released.
+    fn teardown(self: Box<Self>) -> BoxFuture<'static, crate::Result<()>> {
+        Box::pin(async move {
+            if !self.detached {
+                (*self).stop().await?;
+            }
+            Ok(())
+        })
     }
 }
 
-#[derive(Default)]
+#[derive(Clone, Default)]
 pub struct Builder {
     /// Container config
     config: bollard::container::Config<String>,
@@ -62,9 +518,59 @@ pub struct Builder {
     /// Default accessing protocol
     protocol: Option<String>,
     /// Default accessing port
-    default_port: Option<String>,
+    default_port: Option<ContainerPort>,
+    /// Clock seen by the processes in the container
+    fake_time: Option<FakeTime>,
+    /// Host path of the libfaketime shared library
+    faketime_lib: Option<String>,
+    /// Daemon the container is created on, the one of `connect` by default
+    backend: Option<Arc<dyn Backend>>,
+    /// Host the published ports are reached on, derived from `DOCKER_HOST` by default
+    host_ip: Option<String>,
+    /// Whether the daemon removes the container once stopped, detected when not specified
+    auto_remove: Option<bool>,
+    pin_digest: bool,
+    /// Whether to pull the image if it is missing on the daemon
+    pull: bool,
+    registry_auth: Option<DockerCredentials>,
+    credentials: Option<creds::Credentials>,
+    tee_logs: bool,
+    /// Whether to reuse a running container configured the same way, see `reuse`
+    reuse: bool,
+    /// Whether to label the container with the session of the process, see `reaper`
+    session: bool,
+    /// Mounts rejected by `mount`, reported by `validate`
+    mount_errors: Vec<ValidationError>,
+    /// Names the container is reachable by on its network, besides its own
+    network_aliases: Vec<String>,
+    /// How `build_disposable` tells that the container is ready
+    wait: Option<WaitStrategy>,
+    wait_timeout: Option<Duration>,
+}
+
+/// Clock skew applied inside a container through libfaketime.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FakeTime {
+    /// Shift the clock by the given number of seconds, which can be negative
+    Offset(i64),
+    /// Start the clock at an absolute time formatted as `YYYY-MM-DD hh:mm:ss`
+    Absolute(String),
 }
 
+impl FakeTime {
+    fn to_faketime_spec(&self) -> String {
+        match self {
+            FakeTime::Offset(secs) => format!("{secs:+}"),
+            FakeTime::Absolute(time) => format!("@{time}"),
+        }
+    }
+}
+
+const DEFAULT_FAKETIME_LIB: &str = "/usr/lib/x86_64-linux-gnu/faketime/libfaketime.so.1";
+const CONTAINER_FAKETIME_LIB: &str = "/usr/local/lib/faketime/libfaketime.so.1";
+/// Scheduler period `Builder::cpu_quota` is a share of, the default of the daemon
+const CPU_PERIOD_MICROS: i64 = 100_000;
+
 impl Builder {
     pub fn new<S: Into<String>>(image: S) -> Self {
         let image = image.into();
@@ -76,6 +582,10 @@ impl Builder {
         Builder {
             config: bollard::container::Config {
                 image: Some(image),
+                labels: Some(HashMap::from([(
+                    CRATE_LABEL.to_string(),
+                    env!("CARGO_PKG_VERSION").to_string(),
+                )])),
                 host_config: Some(HostConfig {
                     auto_remove: Some(true),
                     ..Default::default()
@@ -85,21 +595,38 @@ impl Builder {
             create_options: None,
             protocol,
             default_port: None,
+            fake_time: None,
+            faketime_lib: None,
+            backend: None,
+            host_ip: None,
+            auto_remove: None,
+            pin_digest: false,
+            pull: true,
+            registry_auth: None,
+            credentials: None,
+            tee_logs: false,
+            reuse: false,
+            session: true,
+            mount_errors: Vec::new(),
+            network_aliases: Vec::new(),
+            wait: None,
+            wait_timeout: None,
         }
     }
 
-    pub fn bind_port<S, T>(mut self, host_port: Option<S>, port: T) -> Self
+    /// Publish container `port` on `host_port`, or on the same port number if `None`.
+    pub fn bind_port<H, P>(mut self, host_port: Option<H>, port: P) -> Self
     where
-        S: Into<String>,
-        T: Into<String>,
+        H: Into<HostPort>,
+        P: Into<ContainerPort>,
     {
-        let port = canonicalize_port(port.into());
-        let host_ip = "localhost".to_string();
-        let host_port = host_port.map(Into::into).unwrap_or(port.clone());
+        let port = port.into();
+        let host_port = host_port.map(Into::into).unwrap_or(HostPort(port.port()));
         let binding = PortBinding {
-            host_ip: Some(host_ip),
-            host_port: Some(host_port),
+            host_ip: Some(host::BIND_IP.to_string()),
+            host_port: Some(host_port.to_string()),
         };
+        let port = port.to_string();
 
         let host_config = self.host_config();
         if host_config.port_bindings.is_none() {
@@ -114,42 +641,340 @@ impl Builder {
         self
     }
 
-    pub fn bind_port_as_default<S, T>(mut self, host_port: Option<S>, port: T) -> Self
+    pub fn bind_port_as_default<H, P>(mut self, host_port: Option<H>, port: P) -> Self
     where
-        S: Into<String>,
-        T: Into<String>,
+        H: Into<HostPort>,
+        P: Into<ContainerPort>,
     {
-        let port = canonicalize_port(port.into());
-        self.default_port = Some(port.clone());
-        self.bind_port(host_port, port.as_str())
+        let port = port.into();
+        self.default_port = Some(port);
+        self.bind_port(host_port, port)
     }
 
     #[deprecated(since = "0.2.0", note = "please use `bind_port`")]
     pub fn port_mapping(self, host_port: u16, port: Option<u16>) -> Self {
-        let port = port.unwrap_or(host_port).to_string();
-        let host_port = host_port.to_string();
-        self.bind_port(Some(host_port), port)
+        self.bind_port(Some(host_port), port.unwrap_or(host_port))
     }
 
     pub fn bind_volume<S: Into<String>>(mut self, bind: S) -> Self {
-        let host_config = self.host_config();
-        if host_config.binds.is_none() {
-            host_config.binds = Some(Vec::new());
-        }
-        host_config.binds.as_mut().unwrap().push(bind.into());
+        self.push_bind(bind.into());
         self
     }
 
+    /// Bind-mount `host:container` like `bind_volume`, with mount options which make it work
+    /// on SELinux hosts and rootless runners.
+    pub fn bind_volume_opts<S: AsRef<str>>(self, bind: S, opts: BindOpts) -> Self {
+        let bind = bind.as_ref();
+        if opts.open_permissions {
+            if let Some(host_path) = volume::bind_source(bind) {
+                if let Err(err) = volume::open_up_permissions(Path::new(host_path)) {
+                    log::warn!("failed to open up permissions of {host_path}: {err}");
+                }
+            }
+        }
+        self.bind_volume(opts.apply(bind))
+    }
+
+    /// Bind-mount the host path `host` at `container_path`, read-only if `read_only`.
+    ///
+    /// Unlike with `bind_volume`, `host` may be relative and is resolved now, so that a path
+    /// which does not exist fails `validate` rather than being created by the daemon.
+    pub fn mount<P: AsRef<Path>>(mut self, host: P, container_path: &str, read_only: bool) -> Self {
+        if !container_path.starts_with('/') {
+            let err = ValidationError::RelativeMountTarget(container_path.to_string());
+            self.mount_errors.push(err);
+            return self;
+        }
+        match host.as_ref().canonicalize() {
+            Ok(host) => {
+                let bind = format!("{}:{container_path}", volume::bind_host_path(&host));
+                let opts = BindOpts {
+                    read_only,
+                    ..Default::default()
+                };
+                self.bind_volume(opts.apply(&bind))
+            }
+            Err(err) => {
+                log::debug!("failed to resolve {}: {err}", host.as_ref().display());
+                let err = ValidationError::UnresolvedMountSource(host.as_ref().to_path_buf());
+                self.mount_errors.push(err);
+                self
+            }
+        }
+    }
+
+    /// Bind-mount a fresh temporary host directory at `container_path`, so that files written
+    /// by the container can be inspected. The directory is removed when the returned `TempDir`
+    /// is dropped.
+    #[cfg(feature = "fs")]
+    pub fn bind_temp_dir<S: AsRef<str>>(self, container_path: S) -> (Self, tempfile::TempDir) {
+        let dir = crate::fs::temp_dir();
+        let bind = format!("{}:{}", dir.path().display(), container_path.as_ref());
+        let opts = BindOpts {
+            open_permissions: true,
+            ..Default::default()
+        };
+        (self.bind_volume_opts(bind, opts), dir)
+    }
+
     pub fn name<S: Into<String>>(mut self, name: S) -> Self {
         self.create_options().name = name.into();
         self
     }
 
+    /// Attach the container to the user-defined network `network`, e.g. of a `NetworkHandle`,
+    /// instead of the default bridge network.
+    pub fn network<S: Into<String>>(mut self, network: S) -> Self {
+        self.host_config().network_mode = Some(network.into());
+        self
+    }
+
+    /// Make the container reachable by `alias` from the other containers on its network, in
+    /// addition to its name.
+    pub fn network_alias<S: Into<String>>(mut self, alias: S) -> Self {
+        self.network_aliases.push(alias.into());
+        self
+    }
+
     pub fn protocol<S: Into<String>>(mut self, protocol: S) -> Self {
         self.protocol = Some(protocol.into());
         self
     }
 
+    /// Set the timezone of the processes in the container, e.g. `UTC` or `Asia/Shanghai`.
+    pub fn timezone<S: AsRef<str>>(mut self, timezone: S) -> Self {
+        self.push_env("TZ", timezone.as_ref());
+        self
+    }
+
+    /// Set an environment variable of the container, replacing any previous value.
+    pub fn env<K: AsRef<str>, V: AsRef<str>>(mut self, key: K, value: V) -> Self {
+        self.push_env(key.as_ref(), value.as_ref());
+        self
+    }
+
+    pub fn envs<I, K, V>(mut self, vars: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: AsRef<str>,
+        V: AsRef<str>,
+    {
+        for (key, value) in vars {
+            self.push_env(key.as_ref(), value.as_ref());
+        }
+        self
+    }
+
+    /// Override the command of the image, e.g. `["redis-server", "--appendonly", "yes"]`.
+    pub fn cmd<S: Into<String>>(mut self, cmd: Vec<S>) -> Self {
+        self.config.cmd = Some(cmd.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Override the entrypoint of the image, an empty one clearing it.
+    pub fn entrypoint<S: Into<String>>(mut self, entrypoint: Vec<S>) -> Self {
+        self.config.entrypoint = Some(entrypoint.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Limit the memory of the container to `bytes`, beyond which it is killed.
+    pub fn memory_limit(mut self, bytes: u64) -> Self {
+        self.host_config().memory = Some(bytes as i64);
+        self
+    }
+
+    /// Limit the container to `cpus` CPUs worth of time, e.g. `0.5`.
+    pub fn cpu_quota(mut self, cpus: f64) -> Self {
+        let host_config = self.host_config();
+        host_config.cpu_period = Some(CPU_PERIOD_MICROS);
+        host_config.cpu_quota = Some((cpus * CPU_PERIOD_MICROS as f64) as i64);
+        self
+    }
+
+    /// Size of `/dev/shm` in bytes, which defaults to 64MB, too little for e.g. browsers.
+    pub fn shm_size(mut self, bytes: u64) -> Self {
+        self.host_config().shm_size = Some(bytes as i64);
+        self
+    }
+
+    /// Mount a memory-backed filesystem at `container_path`, with `options` such as
+    /// `rw,size=512m`, e.g. on the data directory of a database to spare the disk.
+    pub fn tmpfs<P: Into<String>, O: Into<String>>(
+        mut self,
+        container_path: P,
+        options: O,
+    ) -> Self {
+        self.host_config()
+            .tmpfs
+            .get_or_insert_with(Default::default)
+            .insert(container_path.into(), options.into());
+        self
+    }
+
+    /// Set the locale of the processes in the container, e.g. `C.UTF-8`.
+    pub fn locale<S: AsRef<str>>(mut self, locale: S) -> Self {
+        self.push_env("LANG", locale.as_ref());
+        self.push_env("LC_ALL", locale.as_ref());
+        self
+    }
+
+    /// Run the container under a skewed clock by preloading libfaketime.
+    ///
+    /// The library is bind-mounted from the host, see `faketime_lib` for its location.
+    pub fn fake_time(mut self, fake_time: FakeTime) -> Self {
+        self.fake_time = Some(fake_time);
+        self
+    }
+
+    pub fn faketime_lib<S: Into<String>>(mut self, host_path: S) -> Self {
+        self.faketime_lib = Some(host_path.into());
+        self
+    }
+
+    /// Create the container on `backend` rather than on the local docker daemon.
+    pub fn backend<B: Backend + 'static>(mut self, backend: B) -> Self {
+        self.backend = Some(Arc::new(backend));
+        self
+    }
+
+    /// Create the container through `docker`, e.g. connected to a remote daemon over TLS.
+    ///
+    /// The client does not tell the address of the daemon, so the published ports are reached
+    /// on `localhost` unless `host_ip` gives that of a remote one.
+    pub fn docker(self, docker: bollard::Docker) -> Self {
+        self.backend(docker)
+    }
+
+    /// Host the published ports of the container are reached on, e.g. the address of a remote
+    /// daemon. It defaults to the one the backend tells, the host of `DOCKER_HOST` for the
+    /// daemon of `connect`, or `localhost`.
+    pub fn host_ip<S: Into<String>>(mut self, host_ip: S) -> Self {
+        self.host_ip = Some(host_ip.into());
+        self
+    }
+
+    /// Let the daemon remove the container once stopped, or remove it explicitly on drop.
+    ///
+    /// By default the daemon is asked to remove the container, falling back to removing it on
+    /// drop if the daemon rejects that, as some rootless setups do.
+    pub fn auto_remove(mut self, auto_remove: bool) -> Self {
+        self.auto_remove = Some(auto_remove);
+        self
+    }
+
+    /// Resolve the image tag to the digest it currently points to and create the container
+    /// from that digest, so that a mutated upstream tag can be diagnosed and pinned.
+    ///
+    /// The digest is logged, kept on the handle, and printed if the handle is dropped while
+    /// panicking.
+    pub fn pin_digest(mut self, pin_digest: bool) -> Self {
+        self.pin_digest = pin_digest;
+        self
+    }
+
+    /// Whether to pull the image before creating the container if the daemon does not have it,
+    /// which is the default.
+    pub fn pull(mut self, pull: bool) -> Self {
+        self.pull = pull;
+        self
+    }
+
+    /// Credentials for pulling the image from the private registry `server`, e.g. `ghcr.io`
+    /// with a personal access token, or the ECR registry of an account with the password
+    /// `aws ecr get-login-password` prints for the username `AWS`.
+    pub fn registry_auth<U: Into<String>, P: Into<String>, S: Into<String>>(
+        self,
+        username: U,
+        password: P,
+        server: S,
+    ) -> Self {
+        self.registry_credentials(DockerCredentials {
+            username: Some(username.into()),
+            password: Some(password.into()),
+            serveraddress: Some(server.into()),
+            ..Default::default()
+        })
+    }
+
+    /// Credentials for pulling the image from a private registry, e.g. an identity token.
+    pub fn registry_credentials(mut self, auth: DockerCredentials) -> Self {
+        self.registry_auth = Some(auth);
+        self
+    }
+
+    /// Record the credentials the service is set up with, to be exposed on the handle.
+    ///
+    /// Presets use `creds::random()` rather than well-known defaults.
+    pub fn credentials(mut self, credentials: creds::Credentials) -> Self {
+        self.credentials = Some(credentials);
+        self
+    }
+
+    /// Print the output of the container to stderr when its handle is dropped. The test
+    /// harness captures it, showing it only for failed tests.
+    pub fn tee_logs_on_drop(mut self, tee_logs: bool) -> Self {
+        self.tee_logs = tee_logs;
+        self
+    }
+
+    /// Return a handle to a running container created by a builder configured the same way,
+    /// in this process or an earlier one, instead of creating a new container. Such containers
+    /// are left running when their handles are dropped, for the next tests to reuse.
+    pub fn reuse(mut self, reuse: bool) -> Self {
+        self.reuse = reuse;
+        self
+    }
+
+    /// Run `cmd` in the container every `interval` to tell whether it is healthy, unhealthy
+    /// after `retries` consecutive failures, for images defining no healthcheck of their own.
+    /// See `WaitStrategy::Healthy` and `ContainerHandle::wait_healthy`.
+    pub fn healthcheck<S: Into<String>>(
+        mut self,
+        cmd: Vec<S>,
+        interval: Duration,
+        retries: u32,
+    ) -> Self {
+        let test = std::iter::once("CMD".to_string())
+            .chain(cmd.into_iter().map(Into::into))
+            .collect();
+        self.config.healthcheck = Some(HealthConfig {
+            test: Some(test),
+            interval: Some(interval.as_nanos() as i64),
+            retries: Some(retries as i64),
+            ..Default::default()
+        });
+        self
+    }
+
+    /// Make `build_disposable` return only once the container is ready according to
+    /// `strategy`, failing if it is not within the wait timeout.
+    pub fn wait_for(mut self, strategy: WaitStrategy) -> Self {
+        self.wait = Some(strategy);
+        self
+    }
+
+    /// Make `build_disposable` return only once the gRPC health service on the default port
+    /// reports `service` serving, or the whole server for an empty name.
+    #[cfg(feature = "tonic")]
+    pub fn wait_for_grpc_health<S: Into<String>>(self, service: S) -> Self {
+        self.wait_for(WaitStrategy::GrpcHealth(service.into()))
+    }
+
+    /// Time to wait for the container to be ready, `DEFAULT_WAIT_TIMEOUT` by default.
+    pub fn wait_timeout(mut self, timeout: Duration) -> Self {
+        self.wait_timeout = Some(timeout);
+        self
+    }
+
+    /// Tweak the raw container config for settings the builder does not cover.
+    pub fn configure<F>(mut self, f: F) -> Self
+    where
+        F: FnOnce(&mut bollard::container::Config<String>),
+    {
+        f(&mut self.config);
+        self
+    }
+
     pub fn host_config(&mut self) -> &mut HostConfig {
         self.config.host_config.as_mut().unwrap()
     }
@@ -161,61 +986,383 @@ impl Builder {
         self.create_options.as_mut().unwrap()
     }
 
+    fn push_env(&mut self, key: &str, value: &str) {
+        let env = self.config.env.get_or_insert_with(Vec::new);
+        let prefix = format!("{key}=");
+        env.retain(|entry| !entry.starts_with(prefix.as_str()));
+        env.push(format!("{key}={value}"));
+    }
+
+    fn push_bind(&mut self, bind: String) {
+        let host_config = self.host_config();
+        host_config.binds.get_or_insert_with(Vec::new).push(bind);
+    }
+
+    fn apply_fake_time(&mut self) {
+        if let Some(fake_time) = self.fake_time.take() {
+            let lib = self
+                .faketime_lib
+                .take()
+                .unwrap_or_else(|| DEFAULT_FAKETIME_LIB.to_string());
+            self.push_bind(format!("{lib}:{CONTAINER_FAKETIME_LIB}:ro"));
+            self.push_env("LD_PRELOAD", CONTAINER_FAKETIME_LIB);
+            self.push_env("FAKETIME", fake_time.to_faketime_spec().as_str());
+            self.push_env("DONT_FAKE_MONOTONIC", "1");
+        }
+    }
+
+    fn apply_network_aliases(&mut self) {
+        let network = self
+            .config
+            .host_config
+            .as_ref()
+            .and_then(|host_config| host_config.network_mode.clone());
+        if let Some(network) = network.filter(|_| !self.network_aliases.is_empty()) {
+            let endpoint = EndpointSettings {
+                aliases: Some(std::mem::take(&mut self.network_aliases)),
+                ..Default::default()
+            };
+            self.config.networking_config = Some(bollard::container::NetworkingConfig {
+                endpoints_config: HashMap::from([(network, endpoint)]),
+            });
+        }
+    }
+
+    /// Check the configuration for contradictions, which `build_disposable` does before
+    /// contacting the daemon.
+    pub fn validate(&self) -> Result<(), Error> {
+        let invalid = |err: ValidationError| Err(Error::new(Stage::Validate, err));
+
+        if self.config.image.as_deref().unwrap_or_default().is_empty() {
+            return invalid(ValidationError::EmptyImage);
+        }
+
+        let host_config = self.config.host_config.as_ref();
+        let port_bindings = host_config.and_then(|host_config| host_config.port_bindings.as_ref());
+        if let Some(port) = self.default_port {
+            let bound = port_bindings.is_some_and(|bindings| {
+                matches!(bindings.get(&port.to_string()), Some(Some(bindings)) if !bindings.is_empty())
+            });
+            if !bound {
+                return invalid(ValidationError::DefaultPortNotBound(port));
+            }
+        }
+
+        let mut host_ports = HashSet::new();
+        for binding in port_bindings
+            .into_iter()
+            .flat_map(|bindings| bindings.values().flatten().flatten())
+        {
+            let host_port = binding
+                .host_port
+                .as_deref()
+                .and_then(|port| port.parse().ok());
+            match host_port {
+                // ephemeral ports never collide
+                None | Some(HostPort::ANY) => {}
+                Some(host_port) => {
+                    let host_ip = binding.host_ip.as_deref().unwrap_or_default();
+                    if !host_ports.insert((host_ip, host_port)) {
+                        return invalid(ValidationError::DuplicateHostPort(host_port));
+                    }
+                }
+            }
+        }
+
+        if let Some(err) = self.mount_errors.first() {
+            return invalid(err.clone());
+        }
+        let binds = host_config.and_then(|host_config| host_config.binds.as_ref());
+        for bind in binds.into_iter().flatten() {
+            let host_path = bind.split_once(':').map_or(bind.as_str(), |(host, _)| host);
+            // anything else than a path is the name of a volume
+            let is_path = host_path.contains('/') || host_path.starts_with('.');
+            // paths starting with `/` are absolute to the daemon, even on Windows
+            let is_absolute = host_path.starts_with('/') || Path::new(host_path).is_absolute();
+            if is_path && !is_absolute {
+                return invalid(ValidationError::RelativeBindPath(bind.clone()));
+            }
+        }
+
+        let network_mode = host_config.and_then(|host_config| host_config.network_mode.as_ref());
+        if network_mode.is_none() {
+            if let Some(alias) = self.network_aliases.first() {
+                return invalid(ValidationError::AliasWithoutNetwork(alias.clone()));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// A handle which creates and starts the container on first use instead, waiting for it
+    /// as `try_build_disposable` does, e.g. for a service only some tests of a suite need.
+    pub fn lazy(self) -> LazyHandle {
+        LazyHandle::new(self)
+    }
+
+    /// Create and start the container, panicking if any step fails.
     pub async fn build_disposable(self) -> ContainerHandle {
-        let host_ip = "localhost".to_string();
-        // should be consistent with host_ip
-        let docker = bollard::Docker::connect_with_local_defaults().unwrap();
-        let container_handle = docker
-            .create_container(self.create_options, self.config)
-            .await
-            .unwrap();
-        docker
-            .start_container(&container_handle.id, None::<StartContainerOptions<String>>)
+        self.try_build_disposable()
             .await
-            .unwrap();
-        let container_info = docker
-            .inspect_container(&container_handle.id, None)
-            .await
-            .unwrap();
+            .unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    /// Like `build_disposable`, but returning the failure instead, e.g. to skip tests when no
+    /// docker daemon is available.
+    pub async fn try_build_disposable(mut self) -> Result<ContainerHandle, Error> {
+        self.apply_fake_time();
+        let image = self.config.image.clone();
+        let name = self
+            .create_options
+            .as_ref()
+            .map(|options| options.name.clone());
+        let context = |err: Error| err.with_container(image.as_deref(), name.as_deref());
+        self.validate().map_err(context)?;
+        self.apply_network_aliases();
+
+        // the reaper only watches the daemon `connect` talks to
+        let default_backend = self.backend.is_none();
+        let backend = match self.backend.take() {
+            Some(backend) => backend,
+            None => {
+                Arc::new(host::connect().map_err(|err| context(Error::new(Stage::Connect, err)))?)
+            }
+        };
+        // `DOCKER_HOST` only tells where the daemon of `connect` is
+        let host_ip = match self.host_ip.take() {
+            Some(host_ip) => host_ip,
+            None if default_backend => host::default_host_ip(),
+            None => backend
+                .host_ip()
+                .unwrap_or_else(|| host::BIND_IP.to_string()),
+        };
+        let fixture = image.clone().unwrap_or_default();
+        if self.pull {
+            pull_if_missing(backend.as_ref(), &fixture, self.registry_auth.take())
+                .await
+                .map_err(context)?;
+        }
+        let (image, digest) = if self.pin_digest {
+            let digest = resolve_digest_with(backend.as_ref(), &fixture)
+                .await
+                .map_err(context)?;
+            log::info!("pinned image {fixture} to {digest}");
+            self.config.image = Some(digest.clone());
+            (Some(digest.clone()), Some(digest))
+        } else {
+            (image, None)
+        };
+        // errors from now on report the pinned digest, if any
+        let context = |err: Error| err.with_container(image.as_deref(), name.as_deref());
+
+        let reused = if self.reuse {
+            let key = reuse::key(&self.config);
+            let reused = reuse::find(backend.as_ref(), &key)
+                .await
+                .map_err(|err| context(Error::new(Stage::Create, err)))?;
+            self.config
+                .labels
+                .get_or_insert_with(HashMap::new)
+                .insert(REUSE_LABEL.to_string(), key);
+            reused
+        } else {
+            // containers left for reuse outlive the session
+            if self.session {
+                self.config
+                    .labels
+                    .get_or_insert_with(HashMap::new)
+                    .insert(SESSION_LABEL.to_string(), reaper::session_id().to_string());
+                if reaper::is_enabled() && default_backend {
+                    reaper::ensure_started(&host_ip).await.map_err(context)?;
+                }
+            }
+            None
+        };
+
+        let started = Instant::now();
+        let mut remove_on_drop = false;
+        let created = reused.is_none();
+        let (container_id, permit) = match reused {
+            Some(container_id) => {
+                log::info!("reusing container {container_id}");
+                (container_id, None)
+            }
+            None => {
+                let permit = throttle::acquire().await;
+                let auto_remove = self.auto_remove.unwrap_or(true);
+                self.host_config().auto_remove = Some(auto_remove);
+                remove_on_drop = !auto_remove;
+                let created = backend
+                    .create_container(self.create_options.clone(), self.config.clone())
+                    .await;
+                let container_id = match created {
+                    Err(err) if self.auto_remove.is_none() && is_auto_remove_unsupported(&err) => {
+                        log::warn!(
+                            "auto remove is rejected by the daemon ({err}), removing on drop instead"
+                        );
+                        self.host_config().auto_remove = Some(false);
+                        remove_on_drop = true;
+                        backend
+                            .create_container(self.create_options, self.config)
+                            .await
+                    }
+                    created => created,
+                }
+                .map_err(|err| context(Error::new(Stage::Create, err)))?;
+                crate::leakcheck::record(crate::leakcheck::Leak::Container {
+                    id: container_id.clone(),
+                    name: name.clone().unwrap_or_else(|| container_id.clone()),
+                });
+                if let Err(err) = backend.start_container(&container_id).await {
+                    remove_failed(backend.as_ref(), &container_id).await;
+                    return Err(context(Error::new(Stage::Start, err)));
+                }
+                timing::record(fixture.as_str(), timing::Phase::Start, started.elapsed());
+                (container_id, permit)
+            }
+        };
+        let container_info = match backend.inspect_container(&container_id).await {
+            Ok(info) => info,
+            Err(err) => {
+                if created {
+                    remove_failed(backend.as_ref(), &container_id).await;
+                }
+                return Err(context(Error::new(Stage::Inspect, err)));
+            }
+        };
 
         let default_host_port = self
             .default_port
-            .and_then(|port| container_info.get_host_port(Some(host_ip.as_str()), port.as_str()));
+            .and_then(|port| container_info.get_host_port(Some(host::BIND_IP), port));
 
-        ContainerHandle {
-            container_id: container_handle.id,
+        #[cfg(feature = "tracing")]
+        let span = span::container_span(&container_id, &container_info);
+        let handle = ContainerHandle {
+            container_id,
             name: container_info.get_name(),
             host_ip,
             protocol: self.protocol,
+            default_port: self.default_port,
             default_host_port,
-            docker,
+            backend,
+            info: container_info,
+            remove_on_drop,
+            detached: self.reuse,
+            tee_logs: self.tee_logs,
+            digest,
+            started,
+            credentials: self.credentials,
+            _permit: permit,
+            #[cfg(feature = "tracing")]
+            span,
+        };
+
+        if let Some(strategy) = self.wait.as_ref() {
+            let started = Instant::now();
+            let timeout = self.wait_timeout.unwrap_or(DEFAULT_WAIT_TIMEOUT);
+            let ready = wait::wait_until_ready(&handle, strategy, timeout);
+            #[cfg(feature = "tracing")]
+            let ready = tracing::Instrument::instrument(ready, handle.span.clone());
+            if let Err(err) = ready.await {
+                let mut handle = handle;
+                // removed now rather than on drop, even if meant to be left for reuse
+                if created {
+                    if handle.tee_logs {
+                        handle.print_logs();
+                        handle.tee_logs = false;
+                    }
+                    handle.detached = true;
+                    remove_failed(handle.backend.as_ref(), &handle.container_id).await;
+                }
+                return Err(context(Error::new(Stage::WaitReady, err)));
+            }
+            timing::record(fixture, timing::Phase::Ready, started.elapsed());
         }
+        Ok(handle)
     }
 }
 
-fn canonicalize_port<S: Into<String>>(port: S) -> String {
-    let port = port.into();
-    if port.contains('/') {
-        port
-    } else {
-        port + "/tcp"
+/// Force-remove the container `id` of a build which failed after creating it, so that it
+/// neither leaks nor keeps its name taken.
+async fn remove_failed(backend: &dyn Backend, id: &str) {
+    if let Err(err) = backend.stop_container(id, true).await {
+        log::warn!("failed to remove container {id} of a failed build: {err}");
+    }
+}
+
+/// Pull `images` in parallel on the local docker daemon, e.g. from a CI warmup step, so that
+/// pulling does not count towards the timing of the tests.
+pub async fn prefetch_images<S: AsRef<str>>(images: &[S]) -> Result<(), Error> {
+    let docker = host::connect().map_err(|err| Error::new(Stage::Connect, err))?;
+    prefetch_images_with(&docker, images).await
+}
+
+pub async fn prefetch_images_with<B, S>(backend: &B, images: &[S]) -> Result<(), Error>
+where
+    B: Backend + ?Sized,
+    S: AsRef<str>,
+{
+    futures::future::try_join_all(images.iter().map(|image| async move {
+        let image = image.as_ref();
+        timing::measure(image, timing::Phase::Pull, backend.pull_image(image, None))
+            .await
+            .map_err(|err| Error::new(Stage::Pull, err).with_container(Some(image), None))?;
+        log::info!("pulled image {image}");
+        Ok::<_, Error>(())
+    }))
+    .await?;
+    Ok(())
+}
+
+/// Pull `image` unless the daemon has it already.
+async fn pull_if_missing<B: Backend + ?Sized>(
+    backend: &B,
+    image: &str,
+    credentials: Option<DockerCredentials>,
+) -> Result<(), Error> {
+    match backend.inspect_image(image).await {
+        Err(bollard::errors::Error::DockerResponseServerError {
+            status_code: 404, ..
+        }) => {
+            timing::measure(
+                image,
+                timing::Phase::Pull,
+                backend.pull_image(image, credentials),
+            )
+            .await
+            .map_err(|err| Error::new(Stage::Pull, err))?;
+            log::info!("pulled image {image}");
+            Ok(())
+        }
+        // anything else is left to the creation of the container to report
+        _ => Ok(()),
+    }
+}
+
+fn is_auto_remove_unsupported(err: &bollard::errors::Error) -> bool {
+    match err {
+        bollard::errors::Error::DockerResponseServerError { message, .. } => {
+            let message = message.to_lowercase();
+            message.contains("autoremove") || message.contains("auto-remove")
+        }
+        _ => false,
     }
 }
 
 trait ContainerInspectResponseExt {
-    fn get_host_port<S: AsRef<str>>(&self, host_ip: Option<S>, port: S) -> Option<String>;
+    fn get_host_port(&self, host_ip: Option<&str>, port: ContainerPort) -> Option<HostPort>;
     fn get_name(&self) -> Option<String>;
 }
 
 impl ContainerInspectResponseExt for ContainerInspectResponse {
-    fn get_host_port<S: AsRef<str>>(&self, host_ip: Option<S>, port: S) -> Option<String> {
-        let port = canonicalize_port(port.as_ref().to_string());
+    fn get_host_port(&self, host_ip: Option<&str>, port: ContainerPort) -> Option<HostPort> {
+        let port = port.to_string();
         // the ip of localhost/127.0.0.1 will be canonicalized as 0.0.0.0 by docker
-        let host_ip = host_ip.as_ref().map(|ip| {
-            if ip.as_ref() == "localhost" || ip.as_ref() == "127.0.0.1" {
+        let host_ip = host_ip.map(|ip| {
+            if ip == "localhost" || ip == "127.0.0.1" {
                 "0.0.0.0"
             } else {
-                ip.as_ref()
+                ip
             }
         });
 
@@ -229,7 +1376,7 @@ impl ContainerInspectResponseExt for ContainerInspectResponse {
                     if let Some(bindings) = bindings {
                         for binding in bindings {
                             if binding.host_ip.as_ref().map(String::as_str) == host_ip {
-                                return binding.host_port.clone();
+                                return binding.host_port.as_ref()?.parse().ok();
                             }
                         }
                     }
@@ -251,17 +1398,724 @@ impl ContainerInspectResponseExt for ContainerInspectResponse {
 #[cfg(test)]
 mod tests {
     use bollard::container::InspectContainerOptions;
-    use fake::Fake;
 
     use super::*;
 
+    #[test]
+    fn test_fake_time_preloads_libfaketime() {
+        let mut builder = Builder::new("redis")
+            .fake_time(FakeTime::Offset(-3600))
+            .faketime_lib("/opt/libfaketime.so.1");
+        builder.apply_fake_time();
+
+        let env = builder.config.env.unwrap();
+        assert!(env.contains(&format!("LD_PRELOAD={CONTAINER_FAKETIME_LIB}")));
+        assert!(env.contains(&"FAKETIME=-3600".to_string()));
+
+        let binds = builder.config.host_config.unwrap().binds.unwrap();
+        assert_eq!(
+            binds,
+            vec![format!("/opt/libfaketime.so.1:{CONTAINER_FAKETIME_LIB}:ro")]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_build_with_mock_backend() {
+        let docker = mock::MockDocker::new();
+        let credentials = creds::random();
+        let container_id;
+        {
+            let handle = Builder::new("mongo")
+                .bind_port_as_default(Some(HostPort::ANY), 27017)
+                .name("brisk-otter")
+                .credentials(credentials.clone())
+                .backend(docker.clone())
+                .build_disposable()
+                .await;
+            container_id = handle.container_id.clone();
+
+            assert!(docker.is_running(&container_id));
+            assert_eq!(handle.name.as_deref(), Some("brisk-otter"));
+            assert_eq!(handle.image(), Some("mongo"));
+            assert_eq!(handle.credentials(), Some(&credentials));
+            let host_port = handle.default_host_port.unwrap();
+            assert_eq!(
+                handle.url().unwrap(),
+                format!("mongodb://localhost:{host_port}/")
+            );
+            assert_eq!(
+                handle.url_by(27017).await.unwrap(),
+                format!("mongodb://localhost:{host_port}")
+            );
+            assert_eq!(
+                handle.url_by(6379).await.unwrap_err().stage(),
+                Stage::ResolveUrl
+            );
+        }
+        // auto removed once the handle is dropped
+        assert!(!docker.containers().contains(&container_id));
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(Builder::new("mongo")
+            .bind_port_as_default(Some(HostPort::ANY), 27017)
+            .bind_port(Some(HostPort::ANY), 27018)
+            .bind_volume("data:/data")
+            .bind_volume("/tmp/seed:/seed:ro")
+            .validate()
+            .is_ok());
+
+        let validation_error = |builder: Builder| {
+            let err = builder.validate().unwrap_err();
+            assert_eq!(err.stage(), Stage::Validate);
+            std::error::Error::source(&err)
+                .and_then(|source| source.downcast_ref::<ValidationError>())
+                .cloned()
+                .unwrap()
+        };
+        assert_eq!(
+            validation_error(Builder::new("")),
+            ValidationError::EmptyImage
+        );
+        assert_eq!(
+            validation_error(
+                Builder::new("mongo")
+                    .bind_port_as_default(Some(28017), 27017)
+                    .bind_port(Some(28017), 27018)
+            ),
+            ValidationError::DuplicateHostPort(HostPort(28017))
+        );
+        assert_eq!(
+            validation_error(
+                Builder::new("mongo")
+                    .bind_port_as_default(None::<HostPort>, 27017)
+                    .configure(|config| {
+                        config.host_config.as_mut().unwrap().port_bindings = None
+                    })
+            ),
+            ValidationError::DefaultPortNotBound(ContainerPort::tcp(27017))
+        );
+        assert_eq!(
+            validation_error(Builder::new("mongo").bind_volume("./seed:/seed")),
+            ValidationError::RelativeBindPath("./seed:/seed".to_string())
+        );
+        assert_eq!(
+            validation_error(Builder::new("mongo").mount("./missing", "/seed", true)),
+            ValidationError::UnresolvedMountSource("./missing".into())
+        );
+        assert_eq!(
+            validation_error(Builder::new("mongo").mount(".", "seed", true)),
+            ValidationError::RelativeMountTarget("seed".to_string())
+        );
+        assert_eq!(
+            validation_error(Builder::new("mongo").network_alias("db")),
+            ValidationError::AliasWithoutNetwork("db".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_pin_digest() {
+        let docker = mock::MockDocker::new();
+        let handle = Builder::new("redis:7")
+            .pin_digest(true)
+            .backend(docker.clone())
+            .build_disposable()
+            .await;
+
+        let digest = handle.pinned_digest().unwrap();
+        assert!(digest.starts_with("redis@sha256:"));
+        assert_eq!(handle.image(), Some(digest));
+        assert!(docker.pulled_images().contains("redis:7"));
+    }
+
+    #[tokio::test]
+    async fn test_export_env() {
+        let docker = mock::MockDocker::new();
+        let handle = Builder::new("redis")
+            .bind_port_as_default(Some(HostPort::ANY), 6379)
+            .backend(docker)
+            .build_disposable()
+            .await;
+
+        {
+            let _guard = handle.export_env("TEST_EXPORT_ENV_REDIS");
+            let port = handle.default_host_port.unwrap().to_string();
+            assert_eq!(std::env::var("TEST_EXPORT_ENV_REDIS_PORT").unwrap(), port);
+            assert_eq!(
+                std::env::var("TEST_EXPORT_ENV_REDIS_URL").unwrap(),
+                handle.url().unwrap()
+            );
+            assert!(std::env::var_os("TEST_EXPORT_ENV_REDIS_PASSWORD").is_none());
+        }
+        assert!(std::env::var_os("TEST_EXPORT_ENV_REDIS_URL").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_build_with_conflicting_name_fails_at_create() {
+        let docker = mock::MockDocker::new();
+        let _handle = Builder::new("redis")
+            .name("brisk-otter")
+            .backend(docker.clone())
+            .build_disposable()
+            .await;
+
+        let err = Builder::new("redis")
+            .name("brisk-otter")
+            .backend(docker.clone())
+            .try_build_disposable()
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(err.stage(), Stage::Create);
+        assert_eq!(err.name(), Some("brisk-otter"));
+    }
+
+    #[tokio::test]
+    async fn test_configure_raw_config() {
+        let docker = mock::MockDocker::new();
+        let handle = Builder::new("redis")
+            .configure(|config| config.hostname = Some("cache".to_string()))
+            .configure(|config| config.env = Some(vec!["REDIS_ARGS=--save 60 1".to_string()]))
+            .backend(docker.clone())
+            .build_disposable()
+            .await;
+
+        let config = docker.config(&handle.container_id).unwrap();
+        assert_eq!(config.hostname.as_deref(), Some("cache"));
+        assert_eq!(handle.env()["REDIS_ARGS"], "--save 60 1");
+        assert!(handle.docker().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_fall_back_to_remove_on_drop() {
+        let docker = mock::MockDocker::new().reject_auto_remove();
+        let container_id;
+        {
+            let handle = Builder::new("redis")
+                .backend(docker.clone())
+                .build_disposable()
+                .await;
+            container_id = handle.container_id.clone();
+
+            let config = docker.config(&container_id).unwrap();
+            assert_eq!(config.host_config.unwrap().auto_remove, Some(false));
+        }
+        assert!(!docker.containers().contains(&container_id));
+    }
+
+    #[tokio::test]
+    async fn test_logs() {
+        let docker = mock::MockDocker::new()
+            .startup_log("mongo", "Starting up")
+            .startup_log("mongo", "Waiting for connections");
+        let handle = Builder::new("mongo")
+            .tee_logs_on_drop(true)
+            .backend(docker)
+            .build_disposable()
+            .await;
+
+        let lines = handle.logs().collect::<Vec<_>>().await;
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[1].source, LogSource::Stdout);
+        assert_eq!(lines[1].message, "Waiting for connections");
+        assert!(handle
+            .logs_to_string()
+            .await
+            .unwrap()
+            .contains("Starting up"));
+    }
+
+    #[cfg(feature = "fs")]
+    #[tokio::test]
+    async fn test_copy_in_and_out() {
+        let docker = mock::MockDocker::new();
+        let handle = Builder::new("redis")
+            .backend(docker)
+            .build_disposable()
+            .await;
+        let dir = crate::fs::temp_dir();
+        let local_path = dir.path().join("redis.conf");
+        std::fs::write(&local_path, "appendonly yes\n").unwrap();
+
+        handle
+            .copy_in(&local_path, "/usr/local/etc/redis/redis.conf")
+            .await
+            .unwrap();
+        let content = handle
+            .copy_out("/usr/local/etc/redis/redis.conf")
+            .await
+            .unwrap();
+        assert_eq!(content, b"appendonly yes\n");
+
+        let err = handle.copy_out("/missing").await.unwrap_err();
+        assert_eq!(err.stage(), Stage::Copy);
+    }
+
+    #[cfg(feature = "fs")]
+    #[tokio::test]
+    async fn test_fs_changes() {
+        let docker = mock::MockDocker::new();
+        let handle = Builder::new("redis")
+            .backend(docker)
+            .build_disposable()
+            .await;
+        let dir = crate::fs::temp_dir();
+        let local_path = dir.path().join("dump.rdb");
+        std::fs::write(&local_path, "REDIS").unwrap();
+        handle.copy_in(&local_path, "/data/dump.rdb").await.unwrap();
+
+        let changes = handle.fs_changes().await.unwrap();
+        assert_eq!(
+            changes.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            ["C /data", "A /data/dump.rdb"]
+        );
+        assert!(handle
+            .fs_changes_outside(&["/data"])
+            .await
+            .unwrap()
+            .is_empty());
+        assert_eq!(handle.fs_changes_outside(&["/tmp"]).await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_stop() {
+        let docker = mock::MockDocker::new().reject_auto_remove();
+        let handle = Builder::new("redis")
+            .backend(docker.clone())
+            .build_disposable()
+            .await;
+        let container_id = handle.container_id.clone();
+
+        let report = handle.stop().await.unwrap();
+        assert!(!docker.containers().contains(&container_id));
+        assert_eq!(report.state.as_deref(), Some("running"));
+        assert!(!report.crashed());
+    }
+
+    #[tokio::test]
+    async fn test_host_ip() {
+        let handle = Builder::new("mongo")
+            .bind_port_as_default(Some(HostPort::ANY), 27017)
+            .host_ip("10.0.0.5")
+            .backend(mock::MockDocker::new())
+            .build_disposable()
+            .await;
+
+        let host_port = handle.default_host_port.unwrap();
+        assert_eq!(handle.host_ip, "10.0.0.5");
+        assert_eq!(
+            handle.url().unwrap(),
+            format!("mongodb://10.0.0.5:{host_port}/")
+        );
+
+        // derived from the backend rather than from `DOCKER_HOST`
+        let remote = Builder::new("mongo")
+            .backend(mock::MockDocker::new().host_ip("10.0.0.6"))
+            .build_disposable()
+            .await;
+        assert_eq!(remote.host_ip, "10.0.0.6");
+        let local = Builder::new("mongo")
+            .backend(mock::MockDocker::new())
+            .build_disposable()
+            .await;
+        assert_eq!(local.host_ip, "localhost");
+    }
+
+    #[cfg(feature = "http")]
+    #[tokio::test]
+    async fn test_http() {
+        let handle = Builder::new("nginx")
+            .bind_port_as_default(Some(HostPort::ANY), 443)
+            .protocol("https")
+            .host_ip("10.0.0.5")
+            .backend(mock::MockDocker::new())
+            .build_disposable()
+            .await;
+
+        let host_port = handle.default_host_port.unwrap();
+        assert_eq!(
+            handle.http().base_url(),
+            format!("https://10.0.0.5:{host_port}")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_reuse() {
+        let docker = mock::MockDocker::new();
+        let builder = || {
+            Builder::new("mongo")
+                .env("MONGO_INITDB_DATABASE", "test")
+                .reuse(true)
+                .backend(docker.clone())
+        };
+
+        let first = builder().build_disposable().await;
+        let container_id = first.container_id.clone();
+        drop(first);
+        assert!(docker.is_running(&container_id));
+
+        let second = builder().build_disposable().await;
+        assert_eq!(second.container_id, container_id);
+        let other = builder()
+            .env("MONGO_INITDB_DATABASE", "other")
+            .build_disposable()
+            .await;
+        assert_ne!(other.container_id, container_id);
+        assert!(!other.labels().contains_key(SESSION_LABEL));
+    }
+
+    #[tokio::test]
+    async fn test_session_label() {
+        let docker = mock::MockDocker::new();
+        let handle = Builder::new("mongo")
+            .backend(docker.clone())
+            .build_disposable()
+            .await;
+
+        assert_eq!(handle.labels()[SESSION_LABEL], reaper::session_id());
+    }
+
+    #[tokio::test]
+    async fn test_teardown_report_of_crash() {
+        let scope = teardown::scope();
+        let docker = mock::MockDocker::new().reject_auto_remove();
+        let handle = Builder::new("redis")
+            .backend(docker.clone())
+            .build_disposable()
+            .await;
+        let container_id = handle.container_id.clone();
+
+        docker.exit(&container_id, 137);
+        drop(handle);
+        let [report] = &scope.crashed()[..] else {
+            panic!("expected a single crash");
+        };
+        assert_eq!(report.container_id, container_id);
+        assert_eq!(report.state.as_deref(), Some("exited"));
+        assert_eq!(report.exit_code, Some(137));
+    }
+
+    #[tokio::test]
+    async fn test_teardown_report_of_vanished_container() {
+        let scope = teardown::scope();
+        let docker = mock::MockDocker::new();
+        let handle = Builder::new("redis")
+            .backend(docker.clone())
+            .build_disposable()
+            .await;
+        docker.remove(&handle.container_id);
+        drop(handle);
+
+        let [report] = &scope.reports()[..] else {
+            panic!("expected a single report");
+        };
+        assert!(report.is_unknown());
+        assert!(!report.crashed());
+        scope.assert_no_crashes();
+    }
+
+    #[tokio::test]
+    async fn test_forced_auto_remove_does_not_fall_back() {
+        let docker = mock::MockDocker::new().reject_auto_remove();
+        let err = Builder::new("redis")
+            .auto_remove(true)
+            .backend(docker)
+            .try_build_disposable()
+            .await
+            .err()
+            .unwrap();
+
+        assert_eq!(err.stage(), Stage::Create);
+    }
+
+    #[cfg(feature = "fs")]
+    #[tokio::test]
+    async fn test_bind_temp_dir() {
+        let docker = mock::MockDocker::new();
+        let (builder, dir) = Builder::new("mongo").bind_temp_dir("/data/db");
+        let handle = builder.backend(docker.clone()).build_disposable().await;
+
+        let binds = docker
+            .config(&handle.container_id)
+            .and_then(|config| config.host_config)
+            .and_then(|host_config| host_config.binds)
+            .unwrap();
+        assert_eq!(binds, vec![format!("{}:/data/db", dir.path().display())]);
+        assert!(dir.path().is_dir());
+    }
+
+    #[tokio::test]
+    async fn test_mount() {
+        let docker = mock::MockDocker::new();
+        let handle = Builder::new("mongo")
+            .mount(".", "/src", true)
+            .backend(docker.clone())
+            .build_disposable()
+            .await;
+
+        let binds = docker
+            .config(&handle.container_id)
+            .and_then(|config| config.host_config)
+            .and_then(|host_config| host_config.binds)
+            .unwrap();
+        let cwd = std::env::current_dir().unwrap().canonicalize().unwrap();
+        assert_eq!(binds, vec![format!("{}:/src:ro", cwd.display())]);
+    }
+
+    #[tokio::test]
+    async fn test_timezone_and_locale() {
+        let handle = Builder::new("postgres")
+            .timezone("Asia/Shanghai")
+            .timezone("UTC")
+            .locale("C.UTF-8")
+            .backend(mock::MockDocker::new())
+            .build_disposable()
+            .await;
+
+        let env = handle.env();
+        assert_eq!(env["TZ"], "UTC");
+        assert_eq!(env["LANG"], "C.UTF-8");
+        assert_eq!(env["LC_ALL"], "C.UTF-8");
+    }
+
+    #[tokio::test]
+    async fn test_env() {
+        let handle = Builder::new("postgres")
+            .env("POSTGRES_PASSWORD", "secret")
+            .envs([("POSTGRES_USER", "alice"), ("POSTGRES_PASSWORD", "hunter2")])
+            .backend(mock::MockDocker::new())
+            .build_disposable()
+            .await;
+
+        let env = handle.env();
+        assert_eq!(env["POSTGRES_USER"], "alice");
+        assert_eq!(env["POSTGRES_PASSWORD"], "hunter2");
+    }
+
+    #[tokio::test]
+    async fn test_cmd_and_entrypoint() {
+        let docker = mock::MockDocker::new();
+        let handle = Builder::new("redis")
+            .entrypoint(vec!["docker-entrypoint.sh"])
+            .cmd(vec!["redis-server", "--appendonly", "yes"])
+            .backend(docker.clone())
+            .build_disposable()
+            .await;
+
+        let config = docker.config(&handle.container_id).unwrap();
+        assert_eq!(config.entrypoint.unwrap(), ["docker-entrypoint.sh"]);
+        assert_eq!(config.cmd.unwrap(), ["redis-server", "--appendonly", "yes"]);
+    }
+
+    #[tokio::test]
+    async fn test_resource_limits() {
+        let docker = mock::MockDocker::new();
+        let handle = Builder::new("redis")
+            .memory_limit(256 << 20)
+            .cpu_quota(0.5)
+            .shm_size(1 << 30)
+            .backend(docker.clone())
+            .build_disposable()
+            .await;
+
+        let host_config = docker
+            .config(&handle.container_id)
+            .unwrap()
+            .host_config
+            .unwrap();
+        assert_eq!(host_config.memory, Some(256 << 20));
+        assert_eq!(host_config.cpu_period, Some(100_000));
+        assert_eq!(host_config.cpu_quota, Some(50_000));
+        assert_eq!(host_config.shm_size, Some(1 << 30));
+    }
+
+    #[tokio::test]
+    async fn test_tmpfs() {
+        let docker = mock::MockDocker::new();
+        let handle = Builder::new("postgres")
+            .tmpfs("/var/lib/postgresql/data", "rw,size=512m")
+            .tmpfs("/tmp", "")
+            .backend(docker.clone())
+            .build_disposable()
+            .await;
+
+        let host_config = docker
+            .config(&handle.container_id)
+            .unwrap()
+            .host_config
+            .unwrap();
+        assert_eq!(
+            host_config.tmpfs,
+            Some(HashMap::from([
+                (
+                    "/var/lib/postgresql/data".to_string(),
+                    "rw,size=512m".to_string()
+                ),
+                ("/tmp".to_string(), String::new()),
+            ]))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_remove_container_failing_to_start() {
+        let docker = mock::MockDocker::new();
+        let _nginx = Builder::new("nginx")
+            .bind_port(Some(8080), 80)
+            .backend(docker.clone())
+            .build_disposable()
+            .await;
+
+        let build = || {
+            Builder::new("nginx")
+                .name("web")
+                .bind_port(Some(8080), 80)
+                .backend(docker.clone())
+                .try_build_disposable()
+        };
+        let err = build().await.err().unwrap();
+        assert_eq!(err.stage(), Stage::Start);
+        assert_eq!(docker.containers().len(), 1);
+        // the name is free again
+        assert_eq!(build().await.err().unwrap().stage(), Stage::Start);
+    }
+
+    #[tokio::test]
+    async fn test_try_build_disposable() {
+        let err = Builder::new("")
+            .backend(mock::MockDocker::new())
+            .try_build_disposable()
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(err.stage(), Stage::Validate);
+
+        let err: crate::Error = err.into();
+        assert!(matches!(err, crate::Error::Docker(_)));
+        assert!(err.to_string().ends_with(": no image is specified"));
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_readiness() {
+        let docker = mock::MockDocker::new().startup_log("mongo", "Waiting for connections");
+        let handle = Builder::new("mongo")
+            .wait_for(WaitStrategy::LogLine("Waiting for connections".to_string()))
+            .backend(docker.clone())
+            .try_build_disposable()
+            .await
+            .unwrap();
+        drop(handle);
+
+        let err = Builder::new("redis")
+            .wait_for(WaitStrategy::LogLine(
+                "Ready to accept connections".to_string(),
+            ))
+            .wait_timeout(Duration::from_millis(300))
+            .backend(docker.clone())
+            .try_build_disposable()
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(err.stage(), Stage::WaitReady);
+        assert!(err.to_string().ends_with("not ready after 300ms"));
+        // the container which never got ready is gone
+        assert!(docker.containers().is_empty());
+
+        let err = Builder::new("redis")
+            .wait_for(WaitStrategy::Healthy)
+            .backend(docker)
+            .try_build_disposable()
+            .await
+            .err()
+            .unwrap();
+        let source = std::error::Error::source(&err).unwrap();
+        assert_eq!(
+            source.downcast_ref::<WaitError>(),
+            Some(&WaitError::NoHealthcheck)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_healthcheck() {
+        let docker = mock::MockDocker::new();
+        let handle = Builder::new("redis")
+            .healthcheck(vec!["redis-cli", "ping"], Duration::from_secs(1), 30)
+            .backend(docker.clone())
+            .build_disposable()
+            .await;
+
+        let healthcheck = docker
+            .config(&handle.container_id)
+            .unwrap()
+            .healthcheck
+            .unwrap();
+        assert_eq!(healthcheck.test.unwrap(), ["CMD", "redis-cli", "ping"]);
+        assert_eq!(healthcheck.interval, Some(1_000_000_000));
+        assert_eq!(healthcheck.retries, Some(30));
+        handle.wait_healthy(Duration::from_secs(1)).await.unwrap();
+
+        let handle = Builder::new("redis")
+            .backend(docker)
+            .build_disposable()
+            .await;
+        let err = handle
+            .wait_healthy(Duration::from_secs(1))
+            .await
+            .unwrap_err();
+        assert_eq!(err.stage(), Stage::WaitReady);
+    }
+
+    #[tokio::test]
+    async fn test_pull_missing_image() {
+        let docker = mock::MockDocker::new();
+        let _handle = Builder::new("mongo:6")
+            .backend(docker.clone())
+            .build_disposable()
+            .await;
+        assert!(docker.pulled_images().contains("mongo:6"));
+
+        let _handle = Builder::new("redis:7")
+            .pull(false)
+            .backend(docker.clone())
+            .build_disposable()
+            .await;
+        assert!(!docker.pulled_images().contains("redis:7"));
+    }
+
+    #[tokio::test]
+    async fn test_registry_auth() {
+        let docker = mock::MockDocker::new();
+        let _handle = Builder::new("ghcr.io/owner/app:1")
+            .registry_auth("owner", "token", "ghcr.io")
+            .backend(docker.clone())
+            .build_disposable()
+            .await;
+
+        let auth = docker.pull_credentials("ghcr.io/owner/app:1").unwrap();
+        assert_eq!(auth.username.as_deref(), Some("owner"));
+        assert_eq!(auth.password.as_deref(), Some("token"));
+        assert_eq!(auth.serveraddress.as_deref(), Some("ghcr.io"));
+    }
+
+    #[tokio::test]
+    async fn test_prefetch_images() {
+        let docker = mock::MockDocker::new();
+        prefetch_images_with(&docker, &["mongo:6", "redis:7"])
+            .await
+            .unwrap();
+
+        let pulled = docker.pulled_images();
+        assert!(pulled.contains("mongo:6"));
+        assert!(pulled.contains("redis:7"));
+    }
+
     #[tokio::test]
     async fn test_build_docker_handle() {
         let host_ip = "localhost";
         let docker = bollard::Docker::connect_with_local_defaults().unwrap();
-        let name: String = fake::faker::lorem::en::Word().fake();
-        let host_port = "28017";
-        let port = "27017";
+        let name = unique_name("mongo");
+        let host_port = 28017;
+        let port = 27017;
 
         {
             let handle = Builder::new("mongo")
@@ -275,14 +2129,17 @@ mod tests {
             assert!(info_opt.is_ok());
 
             let info = info_opt.unwrap();
-            let expected_host_port = info.get_host_port(Some(host_ip), port);
+            let expected_host_port = info.get_host_port(Some(host_ip), port.into());
             let expected_url = format!("mongodb://localhost:{host_port}/");
 
-            assert_eq!(host_port, expected_host_port.as_ref().unwrap().as_str());
+            assert_eq!(expected_host_port, Some(HostPort(host_port)));
             assert_eq!(info.id.unwrap(), handle.container_id);
-            assert_eq!(handle.url(), expected_url);
+            assert_eq!(handle.url().unwrap(), expected_url);
             assert_eq!(handle.default_host_port, expected_host_port);
             assert_eq!(handle.name.as_ref().unwrap(), &name);
+            assert_eq!(handle.image(), Some("mongo"));
+            assert!(handle.env().contains_key("PATH"));
+            assert!(handle.docker().is_some());
         }
 
         // assert the container is stopped automatically after the handle destroy
@@ -295,9 +2152,9 @@ mod tests {
     async fn test_build_docker_handle_with_auto_port() {
         let host_ip = "localhost";
         let docker = bollard::Docker::connect_with_local_defaults().unwrap();
-        let name: String = fake::faker::lorem::en::Word().fake();
-        let host_port = "0";
-        let port = "27017";
+        let name = unique_name("mongo");
+        let host_port = HostPort::ANY;
+        let port = ContainerPort::tcp(27017);
 
         {
             let handle = Builder::new("mongo")
@@ -315,11 +2172,11 @@ mod tests {
             let expected_host_port = info.get_host_port(Some(host_ip), port);
             let expected_url = format!(
                 "mongodb://localhost:{host}/",
-                host = expected_host_port.as_ref().unwrap()
+                host = expected_host_port.unwrap()
             );
 
             assert_eq!(info.id.unwrap(), handle.container_id);
-            assert_eq!(handle.url(), expected_url);
+            assert_eq!(handle.url().unwrap(), expected_url);
             assert_eq!(expected_host_port, handle.default_host_port);
             assert_eq!(handle.name.as_ref().unwrap(), &name);
         }
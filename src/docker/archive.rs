@@ -0,0 +1,56 @@
+use std::io::{self, Read};
+use std::path::Path;
+
+/// Pack the file or directory at `local_path` into a tar archive, under `name`.
+pub(crate) fn pack(local_path: &Path, name: &str) -> io::Result<Vec<u8>> {
+    let mut builder = tar::Builder::new(Vec::new());
+    if local_path.is_dir() {
+        builder.append_dir_all(name, local_path)?;
+    } else {
+        builder.append_path_with_name(local_path, name)?;
+    }
+    builder.into_inner()
+}
+
+/// Content of the first regular file of the tar `archive`.
+pub(crate) fn unpack_file(archive: &[u8]) -> io::Result<Vec<u8>> {
+    let mut archive = tar::Archive::new(archive);
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if entry.header().entry_type().is_file() {
+            let mut content = Vec::new();
+            entry.read_to_end(&mut content)?;
+            return Ok(content);
+        }
+    }
+    Err(io::Error::new(
+        io::ErrorKind::InvalidInput,
+        "the archive holds no regular file",
+    ))
+}
+
+/// Split a path in a container into its parent directory, where an archive is extracted, and
+/// its last component.
+pub(crate) fn split_path(container_path: &str) -> (&str, &str) {
+    let path = container_path.trim_end_matches('/');
+    match path.rsplit_once('/') {
+        Some(("", name)) => ("/", name),
+        Some((dir, name)) => (dir, name),
+        None => ("/", path),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_path() {
+        assert_eq!(
+            split_path("/etc/app/config.toml"),
+            ("/etc/app", "config.toml")
+        );
+        assert_eq!(split_path("/data/"), ("/", "data"));
+        assert_eq!(split_path("seed.json"), ("/", "seed.json"));
+    }
+}
@@ -0,0 +1,503 @@
+use std::collections::HashMap;
+
+use bollard::auth::DockerCredentials;
+use bollard::container::{
+    Config, CreateContainerOptions, DownloadFromContainerOptions, ListContainersOptions, LogOutput,
+    LogsOptions, RemoveContainerOptions, StartContainerOptions, StatsOptions,
+    UploadToContainerOptions,
+};
+use bollard::exec::{CreateExecOptions, StartExecResults};
+use bollard::image::CreateImageOptions;
+use bollard::models::{ContainerInspectResponse, ContainerSummary, ImageInspect};
+use bollard::network::{CreateNetworkOptions, ListNetworksOptions};
+use bollard::volume::ListVolumesOptions;
+use futures::future::BoxFuture;
+use futures::stream::BoxStream;
+use futures::{StreamExt, TryStreamExt};
+
+use super::changes::FsChange;
+use super::logs::{LogLine, LogSource};
+use super::{reaper, CRATE_LABEL, SESSION_LABEL};
+
+pub type BackendResult<T> = Result<T, bollard::errors::Error>;
+
+/// Outcome of a command run in a container, see `ContainerHandle::exec`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExecOutput {
+    pub exit_code: i64,
+    /// Standard output and error of the command, interleaved
+    pub output: String,
+}
+
+impl ExecOutput {
+    pub fn success(&self) -> bool {
+        self.exit_code == 0
+    }
+}
+
+/// The subset of daemon operations the fixture layer relies on.
+///
+/// It is implemented by `bollard::Docker` for real containers and by `mock::MockDocker` for
+/// tests which should not depend on a running daemon.
+pub trait Backend: Send + Sync {
+    /// Pull `image` from its registry, reporting the progress through the log.
+    fn pull_image<'a>(
+        &'a self,
+        image: &'a str,
+        credentials: Option<DockerCredentials>,
+    ) -> BoxFuture<'a, BackendResult<()>>;
+
+    /// Inspect a local image, failing with a 404 if it has not been pulled.
+    fn inspect_image<'a>(&'a self, image: &'a str) -> BoxFuture<'a, BackendResult<ImageInspect>>;
+
+    /// Create a container and return its id.
+    fn create_container(
+        &self,
+        options: Option<CreateContainerOptions<String>>,
+        config: Config<String>,
+    ) -> BoxFuture<'_, BackendResult<String>>;
+
+    fn start_container<'a>(&'a self, id: &'a str) -> BoxFuture<'a, BackendResult<()>>;
+
+    fn inspect_container<'a>(
+        &'a self,
+        id: &'a str,
+    ) -> BoxFuture<'a, BackendResult<ContainerInspectResponse>>;
+
+    /// Output of the container so far, stdout and stderr interleaved.
+    fn logs<'a>(&'a self, id: &'a str) -> BoxFuture<'a, BackendResult<String>>;
+
+    /// Output of the container line by line, following it until the container stops if
+    /// `follow` is set.
+    fn log_stream<'a>(&'a self, id: &'a str, follow: bool)
+        -> BoxStream<'a, BackendResult<LogLine>>;
+
+    /// Extract the tar `archive` into the directory `path` of the container.
+    fn upload_archive<'a>(
+        &'a self,
+        id: &'a str,
+        path: &'a str,
+        archive: Vec<u8>,
+    ) -> BoxFuture<'a, BackendResult<()>>;
+
+    /// A tar archive of the file or directory at `path` in the container.
+    fn download_archive<'a>(
+        &'a self,
+        id: &'a str,
+        path: &'a str,
+    ) -> BoxFuture<'a, BackendResult<Vec<u8>>>;
+
+    /// Paths of the container which differ from its image.
+    fn container_changes<'a>(&'a self, id: &'a str) -> BoxFuture<'a, BackendResult<Vec<FsChange>>>;
+
+    /// Memory the container uses right now, in bytes.
+    fn memory_usage<'a>(&'a self, id: &'a str) -> BoxFuture<'a, BackendResult<u64>>;
+
+    /// Run `cmd` in the running container until it exits.
+    fn exec<'a>(
+        &'a self,
+        id: &'a str,
+        cmd: Vec<String>,
+    ) -> BoxFuture<'a, BackendResult<ExecOutput>>;
+
+    /// List the containers, stopped ones included, which carry `label`.
+    fn list_containers<'a>(
+        &'a self,
+        label: &'a str,
+    ) -> BoxFuture<'a, BackendResult<Vec<ContainerSummary>>>;
+
+    /// List the names of the networks which carry `label`.
+    fn list_networks<'a>(&'a self, label: &'a str) -> BoxFuture<'a, BackendResult<Vec<String>>>;
+
+    /// List the names of the volumes which carry `label`.
+    fn list_volumes<'a>(&'a self, label: &'a str) -> BoxFuture<'a, BackendResult<Vec<String>>>;
+
+    /// Create a bridge network named `name`, labeled with `network_labels`, and return its id.
+    fn create_network<'a>(&'a self, name: &'a str) -> BoxFuture<'a, BackendResult<String>>;
+
+    /// Remove a network, which fails while containers are attached to it. A network which is
+    /// gone already is not an error.
+    fn remove_network<'a>(&'a self, id: &'a str) -> BoxFuture<'a, BackendResult<()>>;
+
+    /// Stop the container and remove it as well if `remove` is set. Containers which are
+    /// already stopped or gone are not an error.
+    fn stop_container<'a>(&'a self, id: &'a str, remove: bool) -> BoxFuture<'a, BackendResult<()>>;
+
+    /// Like `stop_container`, blocking until it is done. Called when a handle is dropped.
+    fn dispose(&self, id: &str, remove: bool);
+
+    /// The underlying docker client, if the backend talks to a real daemon.
+    fn as_docker(&self) -> Option<&bollard::Docker> {
+        None
+    }
+
+    /// Host the published ports of the daemon are reached on, if the backend knows it.
+    fn host_ip(&self) -> Option<String> {
+        None
+    }
+}
+
+impl Backend for bollard::Docker {
+    fn pull_image<'a>(
+        &'a self,
+        image: &'a str,
+        credentials: Option<DockerCredentials>,
+    ) -> BoxFuture<'a, BackendResult<()>> {
+        Box::pin(async move {
+            let (repo, tag) = split_image_tag(image);
+            let options = CreateImageOptions {
+                from_image: repo,
+                tag,
+                ..Default::default()
+            };
+
+            log::info!("pulling image {image}");
+            self.create_image(Some(options), None, credentials)
+                .try_for_each(|info| async move {
+                    if let Some(status) = info.status {
+                        let progress = info.progress.unwrap_or_default();
+                        match info.id {
+                            Some(layer) => log::debug!("{image}: {layer}: {status} {progress}"),
+                            None => log::info!("{image}: {status}"),
+                        }
+                    }
+                    Ok(())
+                })
+                .await
+        })
+    }
+
+    fn inspect_image<'a>(&'a self, image: &'a str) -> BoxFuture<'a, BackendResult<ImageInspect>> {
+        Box::pin(bollard::Docker::inspect_image(self, image))
+    }
+
+    fn create_container(
+        &self,
+        options: Option<CreateContainerOptions<String>>,
+        config: Config<String>,
+    ) -> BoxFuture<'_, BackendResult<String>> {
+        Box::pin(async move {
+            let response = bollard::Docker::create_container(self, options, config).await?;
+            Ok(response.id)
+        })
+    }
+
+    fn start_container<'a>(&'a self, id: &'a str) -> BoxFuture<'a, BackendResult<()>> {
+        Box::pin(bollard::Docker::start_container(
+            self,
+            id,
+            None::<StartContainerOptions<String>>,
+        ))
+    }
+
+    fn inspect_container<'a>(
+        &'a self,
+        id: &'a str,
+    ) -> BoxFuture<'a, BackendResult<ContainerInspectResponse>> {
+        Box::pin(bollard::Docker::inspect_container(self, id, None))
+    }
+
+    fn logs<'a>(&'a self, id: &'a str) -> BoxFuture<'a, BackendResult<String>> {
+        let options = LogsOptions::<String> {
+            stdout: true,
+            stderr: true,
+            ..Default::default()
+        };
+        Box::pin(bollard::Docker::logs(self, id, Some(options)).try_fold(
+            String::new(),
+            |mut logs, output| async move {
+                logs.push_str(&output.to_string());
+                Ok(logs)
+            },
+        ))
+    }
+
+    fn log_stream<'a>(
+        &'a self,
+        id: &'a str,
+        follow: bool,
+    ) -> BoxStream<'a, BackendResult<LogLine>> {
+        let options = LogsOptions::<String> {
+            follow,
+            stdout: true,
+            stderr: true,
+            ..Default::default()
+        };
+        bollard::Docker::logs(self, id, Some(options))
+            .map_ok(|output| {
+                let source = match output {
+                    LogOutput::StdErr { .. } => LogSource::Stderr,
+                    _ => LogSource::Stdout,
+                };
+                let lines = LogLine::split(source, &output.to_string());
+                futures::stream::iter(lines.into_iter().map(Ok))
+            })
+            .try_flatten()
+            .boxed()
+    }
+
+    fn upload_archive<'a>(
+        &'a self,
+        id: &'a str,
+        path: &'a str,
+        archive: Vec<u8>,
+    ) -> BoxFuture<'a, BackendResult<()>> {
+        let options = UploadToContainerOptions {
+            path,
+            ..Default::default()
+        };
+        Box::pin(bollard::Docker::upload_to_container(
+            self,
+            id,
+            Some(options),
+            archive.into(),
+        ))
+    }
+
+    fn download_archive<'a>(
+        &'a self,
+        id: &'a str,
+        path: &'a str,
+    ) -> BoxFuture<'a, BackendResult<Vec<u8>>> {
+        let options = DownloadFromContainerOptions { path };
+        Box::pin(
+            bollard::Docker::download_from_container(self, id, Some(options)).try_fold(
+                Vec::new(),
+                |mut archive, chunk| async move {
+                    archive.extend_from_slice(&chunk);
+                    Ok(archive)
+                },
+            ),
+        )
+    }
+
+    fn container_changes<'a>(&'a self, id: &'a str) -> BoxFuture<'a, BackendResult<Vec<FsChange>>> {
+        Box::pin(async move {
+            let changes = bollard::Docker::container_changes(self, id).await?;
+            Ok(changes
+                .into_iter()
+                .flatten()
+                .filter_map(|change| FsChange::from_api(change.path, change.kind))
+                .collect())
+        })
+    }
+
+    fn memory_usage<'a>(&'a self, id: &'a str) -> BoxFuture<'a, BackendResult<u64>> {
+        Box::pin(async move {
+            let options = StatsOptions {
+                stream: false,
+                one_shot: true,
+            };
+            let stats = bollard::Docker::stats(self, id, Some(options))
+                .next()
+                .await
+                .transpose()?;
+            Ok(stats
+                .and_then(|stats| stats.memory_stats.usage)
+                .unwrap_or_default())
+        })
+    }
+
+    fn exec<'a>(
+        &'a self,
+        id: &'a str,
+        cmd: Vec<String>,
+    ) -> BoxFuture<'a, BackendResult<ExecOutput>> {
+        Box::pin(async move {
+            let options = CreateExecOptions {
+                cmd: Some(cmd),
+                attach_stdout: Some(true),
+                attach_stderr: Some(true),
+                ..Default::default()
+            };
+            let exec = bollard::Docker::create_exec(self, id, options).await?;
+            let mut output = String::new();
+            if let StartExecResults::Attached {
+                output: mut stream, ..
+            } = bollard::Docker::start_exec(self, &exec.id, None).await?
+            {
+                while let Some(chunk) = stream.next().await {
+                    output.push_str(&chunk?.to_string());
+                }
+            }
+            let inspect = bollard::Docker::inspect_exec(self, &exec.id).await?;
+            Ok(ExecOutput {
+                exit_code: inspect.exit_code.unwrap_or_default(),
+                output,
+            })
+        })
+    }
+
+    fn list_containers<'a>(
+        &'a self,
+        label: &'a str,
+    ) -> BoxFuture<'a, BackendResult<Vec<ContainerSummary>>> {
+        let options = ListContainersOptions {
+            all: true,
+            filters: [("label", vec![label])].into_iter().collect(),
+            ..Default::default()
+        };
+        Box::pin(bollard::Docker::list_containers(self, Some(options)))
+    }
+
+    fn list_networks<'a>(&'a self, label: &'a str) -> BoxFuture<'a, BackendResult<Vec<String>>> {
+        Box::pin(async move {
+            let options = ListNetworksOptions {
+                filters: HashMap::from([("label", vec![label])]),
+            };
+            let networks = bollard::Docker::list_networks(self, Some(options)).await?;
+            Ok(networks
+                .into_iter()
+                .filter_map(|network| network.name)
+                .collect())
+        })
+    }
+
+    fn list_volumes<'a>(&'a self, label: &'a str) -> BoxFuture<'a, BackendResult<Vec<String>>> {
+        Box::pin(async move {
+            let options = ListVolumesOptions {
+                filters: HashMap::from([("label", vec![label])]),
+            };
+            let volumes = bollard::Docker::list_volumes(self, Some(options)).await?;
+            Ok(volumes
+                .volumes
+                .unwrap_or_default()
+                .into_iter()
+                .map(|volume| volume.name)
+                .collect())
+        })
+    }
+
+    fn create_network<'a>(&'a self, name: &'a str) -> BoxFuture<'a, BackendResult<String>> {
+        Box::pin(async move {
+            let options = CreateNetworkOptions {
+                name: name.to_string(),
+                check_duplicate: true,
+                driver: "bridge".to_string(),
+                labels: network_labels(),
+                ..Default::default()
+            };
+            let created = bollard::Docker::create_network(self, options).await?;
+            Ok(created.id.unwrap_or_default())
+        })
+    }
+
+    fn remove_network<'a>(&'a self, id: &'a str) -> BoxFuture<'a, BackendResult<()>> {
+        Box::pin(async move {
+            match bollard::Docker::remove_network(self, id).await {
+                Err(bollard::errors::Error::DockerResponseServerError {
+                    status_code: 404, ..
+                }) => Ok(()),
+                removed => removed,
+            }
+        })
+    }
+
+    fn stop_container<'a>(&'a self, id: &'a str, remove: bool) -> BoxFuture<'a, BackendResult<()>> {
+        Box::pin(async move {
+            let stopped = if remove {
+                // along with its anonymous volumes, as a container removed on its own would be
+                let options = RemoveContainerOptions {
+                    force: true,
+                    v: true,
+                    ..Default::default()
+                };
+                bollard::Docker::remove_container(self, id, Some(options)).await
+            } else {
+                bollard::Docker::stop_container(self, id, None).await
+            };
+            match stopped {
+                // already stopped, or already removed by the daemon
+                Err(bollard::errors::Error::DockerResponseServerError {
+                    status_code: 304 | 404,
+                    ..
+                }) => Ok(()),
+                stopped => stopped,
+            }
+        })
+    }
+
+    fn dispose(&self, id: &str, remove: bool) {
+        let docker = self.clone();
+        let container_id = id.trim().to_string();
+        let disposed =
+            block_on(async move { Backend::stop_container(&docker, &container_id, remove).await });
+        // a panic here could abort the process
+        match disposed {
+            Ok(Ok(())) => {}
+            Ok(Err(err)) => log::warn!("failed to dispose container {id}: {err}"),
+            Err(err) => log::warn!("failed to dispose container {id}: {err}"),
+        }
+    }
+
+    fn as_docker(&self) -> Option<&bollard::Docker> {
+        Some(self)
+    }
+}
+
+/// Labels of the networks created by the crate, so that the reaper and `leakcheck` find them.
+pub(crate) fn network_labels() -> HashMap<String, String> {
+    HashMap::from([
+        (
+            CRATE_LABEL.to_string(),
+            env!("CARGO_PKG_VERSION").to_string(),
+        ),
+        (SESSION_LABEL.to_string(), reaper::session_id().to_string()),
+    ])
+}
+
+/// Run `future` to completion on a runtime of its own, in a thread of its own, for drop code
+/// which may itself run on a runtime that cannot be blocked on. The docker client does not pool
+/// connections, so none is shared across the runtimes.
+pub(crate) fn block_on<F>(future: F) -> std::io::Result<F::Output>
+where
+    F: std::future::Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    std::thread::spawn(move || {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map(|runtime| runtime.block_on(future))
+    })
+    .join()
+    .unwrap_or_else(|_| Err(std::io::Error::other("the thread panicked")))
+}
+
+/// Split an image reference into repository and tag, defaulting the tag to `latest`.
+///
+/// References pinned by digest are kept whole as the repository.
+pub(crate) fn split_image_tag(image: &str) -> (&str, &str) {
+    if image.contains('@') {
+        return (image, "");
+    }
+    // a colon before the last slash separates a registry port, not a tag
+    let name_start = image.rfind('/').map(|i| i + 1).unwrap_or(0);
+    match image[name_start..].rfind(':') {
+        Some(i) => (&image[..name_start + i], &image[name_start + i + 1..]),
+        None => (image, "latest"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_image_tag() {
+        assert_eq!(split_image_tag("mongo"), ("mongo", "latest"));
+        assert_eq!(split_image_tag("mongo:6"), ("mongo", "6"));
+        assert_eq!(
+            split_image_tag("localhost:5000/team/app"),
+            ("localhost:5000/team/app", "latest")
+        );
+        assert_eq!(
+            split_image_tag("localhost:5000/team/app:1.2"),
+            ("localhost:5000/team/app", "1.2")
+        );
+        assert_eq!(
+            split_image_tag("redis@sha256:abcd"),
+            ("redis@sha256:abcd", "")
+        );
+    }
+}
@@ -0,0 +1,141 @@
+use std::future::Future;
+use std::time::Duration;
+
+#[derive(Clone, Debug)]
+pub struct BackoffPolicy {
+    /// Delay before the first retry
+    initial_delay: Duration,
+    /// Factor applied to the delay after each failed attempt
+    multiplier: u32,
+    /// Upper bound of a single delay
+    max_delay: Duration,
+    /// Total number of attempts, including the first one
+    max_attempts: usize,
+}
+
+impl Default for BackoffPolicy {
+    fn default() -> Self {
+        BackoffPolicy {
+            initial_delay: Duration::from_millis(100),
+            multiplier: 2,
+            max_delay: Duration::from_secs(5),
+            max_attempts: 10,
+        }
+    }
+}
+
+impl BackoffPolicy {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn initial_delay(mut self, initial_delay: Duration) -> Self {
+        self.initial_delay = initial_delay;
+        self
+    }
+
+    pub fn multiplier(mut self, multiplier: u32) -> Self {
+        self.multiplier = multiplier;
+        self
+    }
+
+    pub fn max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = max_delay;
+        self
+    }
+
+    pub fn max_attempts(mut self, max_attempts: usize) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    fn delay(&self, attempt: usize) -> Duration {
+        let factor = self.multiplier.saturating_pow(attempt as u32);
+        self.initial_delay
+            .saturating_mul(factor)
+            .min(self.max_delay)
+    }
+}
+
+/// Connect to `url` with `connector`, retrying with exponential backoff until it succeeds or
+/// the attempts of `policy` are exhausted, in which case the last error is returned.
+pub async fn connect_with_backoff<S, F, Fut, T, E>(
+    url: S,
+    policy: &BackoffPolicy,
+    mut connector: F,
+) -> Result<T, E>
+where
+    S: AsRef<str>,
+    F: FnMut(String) -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+    E: std::fmt::Display,
+{
+    let url = url.as_ref();
+    let mut attempt = 0;
+    loop {
+        match connector(url.to_owned()).await {
+            Ok(conn) => return Ok(conn),
+            Err(err) if attempt + 1 < policy.max_attempts => {
+                let delay = policy.delay(attempt);
+                log::debug!("failed to connect to {url} ({err}), retrying in {delay:?}");
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_connect_with_backoff_retries_until_success() {
+        let policy = BackoffPolicy::new().initial_delay(Duration::from_millis(1));
+        let mut attempts = 0;
+        let conn = connect_with_backoff("tcp://localhost:1", &policy, |url| {
+            attempts += 1;
+            let ready = attempts >= 3;
+            async move {
+                if ready {
+                    Ok(url)
+                } else {
+                    Err("connection refused")
+                }
+            }
+        })
+        .await;
+
+        assert_eq!(conn, Ok("tcp://localhost:1".to_string()));
+        assert_eq!(attempts, 3);
+    }
+
+    #[tokio::test]
+    async fn test_connect_with_backoff_gives_up() {
+        let policy = BackoffPolicy::new()
+            .initial_delay(Duration::from_millis(1))
+            .max_attempts(4);
+        let mut attempts = 0;
+        let conn = connect_with_backoff("tcp://localhost:1", &policy, |_| {
+            attempts += 1;
+            async { Err::<(), _>("connection refused") }
+        })
+        .await;
+
+        assert_eq!(conn, Err("connection refused"));
+        assert_eq!(attempts, 4);
+    }
+
+    #[test]
+    fn test_backoff_delay_is_capped() {
+        let policy = BackoffPolicy::new()
+            .initial_delay(Duration::from_millis(100))
+            .multiplier(10)
+            .max_delay(Duration::from_secs(1));
+
+        assert_eq!(policy.delay(0), Duration::from_millis(100));
+        assert_eq!(policy.delay(1), Duration::from_secs(1));
+        assert_eq!(policy.delay(30), Duration::from_secs(1));
+    }
+}
@@ -0,0 +1,71 @@
+use std::fmt;
+use std::path::Path;
+
+/// How a path of a container changed relative to its image.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChangeKind {
+    Modified,
+    Added,
+    Deleted,
+}
+
+/// A path of a container which differs from its image, as reported by the diff API.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FsChange {
+    pub path: String,
+    pub kind: ChangeKind,
+}
+
+impl FsChange {
+    /// Convert the kind of the diff API, `0` for modified, `1` for added and `2` for deleted.
+    pub(crate) fn from_api<K: fmt::Display>(path: String, kind: K) -> Option<FsChange> {
+        let kind = match kind.to_string().as_str() {
+            "0" => ChangeKind::Modified,
+            "1" => ChangeKind::Added,
+            "2" => ChangeKind::Deleted,
+            _ => return None,
+        };
+        Some(FsChange { path, kind })
+    }
+
+    /// Whether the change is within one of `dirs`, or is the modification of a parent of one
+    /// of them, which any write below it causes.
+    pub fn is_within(&self, dirs: &[&str]) -> bool {
+        let path = Path::new(&self.path);
+        dirs.iter().map(Path::new).any(|dir| {
+            path.starts_with(dir) || (self.kind == ChangeKind::Modified && dir.starts_with(path))
+        })
+    }
+}
+
+impl fmt::Display for FsChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self.kind {
+            ChangeKind::Modified => 'C',
+            ChangeKind::Added => 'A',
+            ChangeKind::Deleted => 'D',
+        };
+        write!(f, "{kind} {}", self.path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_within() {
+        let change = |path: &str, kind| FsChange {
+            path: path.to_string(),
+            kind,
+        };
+        let dirs = ["/var/lib/app", "/tmp"];
+
+        assert!(change("/var/lib/app/db", ChangeKind::Added).is_within(&dirs));
+        assert!(change("/var", ChangeKind::Modified).is_within(&dirs));
+        assert!(change("/tmp", ChangeKind::Modified).is_within(&dirs));
+        assert!(!change("/var", ChangeKind::Deleted).is_within(&dirs));
+        assert!(!change("/var/lib/application", ChangeKind::Added).is_within(&dirs));
+        assert!(!change("/etc/passwd", ChangeKind::Modified).is_within(&dirs));
+    }
+}
@@ -0,0 +1,77 @@
+//! Throwaway credentials for services started by the tests.
+//!
+//! Every part is made of ASCII letters, digits and underscores, so that it can be embedded in
+//! urls and connection strings without escaping.
+
+use fake::faker::lorem::en::Word;
+use fake::{Dummy, Fake, Faker};
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+
+use crate::rng::TestRng;
+
+const PASSWORD_LEN: usize = 24;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Credentials {
+    pub username: String,
+    pub password: String,
+    /// Name of the database to create, for services which have a notion of it
+    pub database: String,
+}
+
+impl Credentials {
+    /// The `username:password` part of a url.
+    pub fn userinfo(&self) -> String {
+        format!("{}:{}", self.username, self.password)
+    }
+}
+
+impl Dummy<Faker> for Credentials {
+    fn dummy_with_rng<R: Rng + ?Sized>(_: &Faker, rng: &mut R) -> Self {
+        let password = (0..PASSWORD_LEN)
+            .map(|_| rng.sample(Alphanumeric) as char)
+            .collect();
+        Credentials {
+            username: format!("{}_{:04}", identifier(rng), rng.gen_range(0..10000)),
+            password,
+            database: format!("{}_db", identifier(rng)),
+        }
+    }
+}
+
+/// Fresh random credentials.
+pub fn random() -> Credentials {
+    Faker.fake_with_rng(&mut TestRng)
+}
+
+/// A lowercase word, starting with a letter as identifiers of most databases have to.
+fn identifier<R: Rng + ?Sized>(rng: &mut R) -> String {
+    let word: String = Word().fake_with_rng::<String, R>(rng);
+    let word: String = word.chars().filter(char::is_ascii_lowercase).collect();
+    if word.is_empty() {
+        "test".to_string()
+    } else {
+        word
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_random_credentials_are_url_safe() {
+        let is_safe =
+            |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        for _ in 0..100 {
+            let creds = random();
+            assert!(is_safe(&creds.username));
+            assert!(creds.username.starts_with(|c: char| c.is_ascii_lowercase()));
+            assert!(is_safe(&creds.password));
+            assert_eq!(creds.password.len(), PASSWORD_LEN);
+            assert!(is_safe(&creds.database));
+        }
+        assert_ne!(random(), random());
+    }
+}
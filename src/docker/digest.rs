@@ -0,0 +1,65 @@
+use super::backend::{split_image_tag, Backend};
+use super::{connect, Error, Stage};
+
+/// Resolve `image`, e.g. `mongo:6`, to the digest reference `mongo@sha256:...` of the manifest
+/// its tag currently points to on the local docker daemon, pulling the image if it is absent.
+///
+/// For multi-arch images this is the digest of the manifest list, which pins every platform.
+pub async fn resolve_digest<S: AsRef<str>>(image: S) -> Result<String, Error> {
+    let docker = connect().map_err(|err| Error::new(Stage::Connect, err))?;
+    resolve_digest_with(&docker, image.as_ref()).await
+}
+
+pub async fn resolve_digest_with<B: Backend + ?Sized>(
+    backend: &B,
+    image: &str,
+) -> Result<String, Error> {
+    let context = |err: Error| err.with_container(Some(image), None);
+
+    let inspect = match backend.inspect_image(image).await {
+        Err(bollard::errors::Error::DockerResponseServerError {
+            status_code: 404, ..
+        }) => {
+            backend
+                .pull_image(image, None)
+                .await
+                .map_err(|err| context(Error::new(Stage::Pull, err)))?;
+            backend.inspect_image(image).await
+        }
+        inspect => inspect,
+    }
+    .map_err(|err| context(Error::new(Stage::ResolveDigest, err)))?;
+
+    let digests = inspect.repo_digests.unwrap_or_default();
+    let (repo, _) = split_image_tag(image);
+    // an image tagged in several repositories has a digest for each of them
+    let digest = digests
+        .iter()
+        .find(|digest| digest.split_once('@').is_some_and(|(name, _)| name == repo))
+        .or_else(|| digests.first())
+        .cloned();
+    digest.ok_or_else(|| {
+        let err = format!("image {image} has no repo digest, it was probably built locally");
+        context(Error::new(Stage::ResolveDigest, err))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::docker::mock::MockDocker;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_resolve_digest_pulls_missing_image() {
+        let docker = MockDocker::new();
+        let digest = resolve_digest_with(&docker, "mongo:6").await.unwrap();
+
+        assert!(docker.pulled_images().contains("mongo:6"));
+        assert!(digest.starts_with("mongo@sha256:"));
+        assert_eq!(
+            resolve_digest_with(&docker, "mongo:6").await.unwrap(),
+            digest
+        );
+    }
+}
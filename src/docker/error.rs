@@ -0,0 +1,246 @@
+use std::fmt;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use super::{ContainerPort, HostPort};
+
+type BoxError = Box<dyn std::error::Error + Send + Sync + 'static>;
+
+/// The step of a container's lifecycle at which an error occurred.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stage {
+    Validate,
+    Pull,
+    ResolveDigest,
+    Create,
+    Start,
+    WaitReady,
+    Inspect,
+    ResolveUrl,
+    ReadLogs,
+    Copy,
+    Stop,
+    CreateNetwork,
+    Exec,
+    Connect,
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let stage = match self {
+            Stage::Validate => "validate container config",
+            Stage::Pull => "pull image",
+            Stage::ResolveDigest => "resolve digest of image",
+            Stage::Create => "create container",
+            Stage::Start => "start container",
+            Stage::WaitReady => "wait for container to be ready",
+            Stage::Inspect => "inspect container",
+            Stage::ResolveUrl => "resolve url of container",
+            Stage::ReadLogs => "read logs of container",
+            Stage::Copy => "copy files of container",
+            Stage::Stop => "stop container",
+            Stage::CreateNetwork => "create network",
+            Stage::Exec => "run command in container",
+            Stage::Connect => "connect to docker or container",
+        };
+        f.write_str(stage)
+    }
+}
+
+/// Reasons why the url of a running container cannot be built.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum UrlError {
+    /// Neither the image nor the builder specified an accessing protocol
+    MissingProtocol,
+    /// The given container port is not published on the host
+    UnboundPort(ContainerPort),
+    /// The container is not attached to the given network
+    NotOnNetwork(String),
+    /// The builder specified no default port to reach the service on
+    NoDefaultPort,
+}
+
+impl fmt::Display for UrlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UrlError::MissingProtocol => f.write_str("no accessing protocol is specified"),
+            UrlError::UnboundPort(port) => write!(f, "port {port} is not bound to the host"),
+            UrlError::NotOnNetwork(network) => {
+                write!(f, "container is not attached to network {network}")
+            }
+            UrlError::NoDefaultPort => f.write_str("no default port is specified"),
+        }
+    }
+}
+
+impl std::error::Error for UrlError {}
+
+/// Contradictions in a builder's configuration, caught before contacting the daemon.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ValidationError {
+    EmptyImage,
+    /// The default port is not published, so the url cannot be resolved
+    DefaultPortNotBound(ContainerPort),
+    /// The same host port is bound by several container ports
+    DuplicateHostPort(HostPort),
+    /// A bind mount has a relative host path, which the daemon would reject
+    RelativeBindPath(String),
+    /// The host path of a mount does not exist or cannot be resolved
+    UnresolvedMountSource(PathBuf),
+    /// The container path of a mount is not absolute
+    RelativeMountTarget(String),
+    /// A network alias is given without a network to join
+    AliasWithoutNetwork(String),
+    /// A stack has no service of the given name
+    UnknownService(String),
+    /// A service of a stack depends on a service the stack does not have
+    UnknownDependency {
+        service: String,
+        dependency: String,
+    },
+    /// Services of a stack depend on each other
+    DependencyCycle(Vec<String>),
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationError::EmptyImage => f.write_str("no image is specified"),
+            ValidationError::DefaultPortNotBound(port) => {
+                write!(f, "default port {port} is not among the bound ports")
+            }
+            ValidationError::DuplicateHostPort(port) => {
+                write!(f, "host port {port} is bound more than once")
+            }
+            ValidationError::RelativeBindPath(bind) => {
+                write!(f, "bind `{bind}` has a relative host path")
+            }
+            ValidationError::UnresolvedMountSource(path) => {
+                write!(
+                    f,
+                    "host path `{}` of a mount cannot be resolved",
+                    path.display()
+                )
+            }
+            ValidationError::RelativeMountTarget(path) => {
+                write!(f, "container path `{path}` of a mount is not absolute")
+            }
+            ValidationError::AliasWithoutNetwork(alias) => {
+                write!(f, "network alias `{alias}` is given without a network")
+            }
+            ValidationError::UnknownService(service) => {
+                write!(f, "no service is named `{service}`")
+            }
+            ValidationError::UnknownDependency {
+                service,
+                dependency,
+            } => write!(f, "`{service}` depends on unknown service `{dependency}`"),
+            ValidationError::DependencyCycle(services) => {
+                write!(f, "services {} depend on each other", services.join(", "))
+            }
+        }
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+/// Reasons why a container never became ready.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum WaitError {
+    TimedOut(Duration),
+    /// The container stopped before being ready
+    Exited,
+    Unhealthy,
+    /// `WaitStrategy::Healthy` on a container without healthcheck
+    NoHealthcheck,
+    /// `WaitStrategy::PortOpen` on a port not published on the host
+    UnboundPort(ContainerPort),
+}
+
+impl fmt::Display for WaitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WaitError::TimedOut(timeout) => write!(f, "not ready after {timeout:?}"),
+            WaitError::Exited => f.write_str("the container exited"),
+            WaitError::Unhealthy => f.write_str("the healthcheck failed"),
+            WaitError::NoHealthcheck => f.write_str("the container has no healthcheck"),
+            WaitError::UnboundPort(port) => write!(f, "port {port} is not bound to the host"),
+        }
+    }
+}
+
+impl std::error::Error for WaitError {}
+
+#[derive(Debug)]
+pub struct Error {
+    stage: Stage,
+    image: Option<String>,
+    name: Option<String>,
+    source: BoxError,
+}
+
+impl Error {
+    pub(crate) fn new<E: Into<BoxError>>(stage: Stage, source: E) -> Self {
+        Error {
+            stage,
+            image: None,
+            name: None,
+            source: source.into(),
+        }
+    }
+
+    pub(crate) fn with_container(mut self, image: Option<&str>, name: Option<&str>) -> Self {
+        self.image = image.map(str::to_string);
+        self.name = name.map(str::to_string);
+        self
+    }
+
+    pub fn stage(&self) -> Stage {
+        self.stage
+    }
+
+    pub fn image(&self) -> Option<&str> {
+        self.image.as_deref()
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "failed to {}", self.stage)?;
+        match (self.image.as_ref(), self.name.as_ref()) {
+            (Some(image), Some(name)) => write!(f, " `{name}` (image `{image}`)")?,
+            (Some(image), None) => write!(f, " (image `{image}`)")?,
+            (None, Some(name)) => write!(f, " `{name}`")?,
+            (None, None) => {}
+        }
+        write!(f, ": {}", self.source)
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(self.source.as_ref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_message_carries_context() {
+        let err = Error::new(Stage::Start, "port is already allocated")
+            .with_container(Some("mongo"), Some("brisk-otter"));
+
+        assert_eq!(err.stage(), Stage::Start);
+        assert_eq!(
+            err.to_string(),
+            "failed to start container `brisk-otter` (image `mongo`): port is already allocated"
+        );
+        assert!(std::error::Error::source(&err).is_some());
+    }
+}
@@ -0,0 +1,53 @@
+use std::collections::{BTreeSet, HashMap};
+
+/// Why nodes cannot be put in a start order.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum OrderError<'a> {
+    /// Node `node` depends on `dependency`, which is not among the nodes
+    Unknown { node: usize, dependency: &'a str },
+    /// The nodes depend on each other
+    Cycle(Vec<usize>),
+}
+
+/// Indices of the nodes named `names` grouped in levels, each depending only on the previous
+/// ones, given the names `dependencies` each node depends on.
+pub(crate) fn levels<'a, I>(
+    names: &[&str],
+    dependencies: I,
+) -> Result<Vec<Vec<usize>>, OrderError<'a>>
+where
+    I: IntoIterator,
+    I::Item: IntoIterator<Item = &'a str>,
+{
+    let index: HashMap<_, _> = names
+        .iter()
+        .enumerate()
+        .map(|(i, name)| (*name, i))
+        .collect();
+    let mut pending = Vec::new();
+    for (node, names) in dependencies.into_iter().enumerate() {
+        let mut dependencies = BTreeSet::new();
+        for dependency in names {
+            let i = index
+                .get(dependency)
+                .ok_or(OrderError::Unknown { node, dependency })?;
+            dependencies.insert(*i);
+        }
+        pending.push(dependencies);
+    }
+
+    let mut levels: Vec<Vec<usize>> = Vec::new();
+    let mut done = BTreeSet::new();
+    while done.len() < names.len() {
+        let level: Vec<_> = (0..names.len())
+            .filter(|i| !done.contains(i) && pending[*i].is_subset(&done))
+            .collect();
+        if level.is_empty() {
+            let cycle = (0..names.len()).filter(|i| !done.contains(i)).collect();
+            return Err(OrderError::Cycle(cycle));
+        }
+        done.extend(&level);
+        levels.push(level);
+    }
+    Ok(levels)
+}
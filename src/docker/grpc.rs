@@ -0,0 +1,96 @@
+use tonic::transport::Endpoint;
+use tonic_health::pb::health_check_response::ServingStatus;
+use tonic_health::pb::health_client::HealthClient;
+use tonic_health::pb::HealthCheckRequest;
+
+use super::{ContainerHandle, UrlError};
+
+/// Endpoint of the gRPC server on the default port of the container, in plaintext.
+pub(super) fn endpoint(handle: &ContainerHandle) -> Result<Endpoint, UrlError> {
+    let port = handle.default_port.ok_or(UrlError::NoDefaultPort)?;
+    let host_port = handle
+        .default_host_port
+        .ok_or(UrlError::UnboundPort(port))?;
+    // a well-formed uri, which is all `from_shared` checks
+    Ok(Endpoint::from_shared(format!("http://{}:{host_port}", handle.host_ip)).unwrap())
+}
+
+/// Whether the standard health service of the server at `endpoint` reports `service` serving,
+/// the empty name standing for the whole server. Servers which cannot be reached yet are not.
+pub(super) async fn is_serving(endpoint: &Endpoint, service: &str) -> bool {
+    let channel = match endpoint.connect().await {
+        Ok(channel) => channel,
+        Err(err) => {
+            log::debug!("failed to connect to {}: {err}", endpoint.uri());
+            return false;
+        }
+    };
+    let request = HealthCheckRequest {
+        service: service.to_string(),
+    };
+    match HealthClient::new(channel).check(request).await {
+        Ok(response) => response.into_inner().status == ServingStatus::Serving as i32,
+        Err(status) => {
+            log::debug!("{service:?} is not serving yet: {status}");
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::net::TcpListener;
+
+    use super::*;
+    use crate::docker::mock::MockDocker;
+    use crate::docker::{Builder, Stage};
+
+    #[tokio::test]
+    async fn test_is_serving() {
+        let port = TcpListener::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let endpoint = Endpoint::from_shared(format!("http://127.0.0.1:{port}")).unwrap();
+        assert!(!is_serving(&endpoint, "").await);
+
+        let (mut reporter, health) = tonic_health::server::health_reporter();
+        reporter
+            .set_service_status("orders", tonic_health::ServingStatus::NotServing)
+            .await;
+        tokio::spawn(
+            tonic::transport::Server::builder()
+                .add_service(health)
+                .serve(([127, 0, 0, 1], port).into()),
+        );
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while !is_serving(&endpoint, "").await {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+        })
+        .await
+        .unwrap();
+        assert!(!is_serving(&endpoint, "orders").await);
+        assert!(!is_serving(&endpoint, "payments").await);
+
+        reporter
+            .set_service_status("orders", tonic_health::ServingStatus::Serving)
+            .await;
+        assert!(is_serving(&endpoint, "orders").await);
+    }
+
+    #[tokio::test]
+    async fn test_grpc_channel_without_default_port() {
+        let handle = Builder::new("grpc")
+            .backend(MockDocker::new())
+            .build_disposable()
+            .await;
+
+        let err = handle.grpc_channel().await.err().unwrap();
+        assert_eq!(err.stage(), Stage::ResolveUrl);
+    }
+}
@@ -0,0 +1,95 @@
+/// Environment variable pointing to the daemon, e.g. `tcp://10.0.0.5:2376`, as for the docker
+/// CLI. The local daemon is used when it is unset or points to a socket.
+pub const DOCKER_HOST_ENV: &str = "DOCKER_HOST";
+
+/// Environment variable asking for TLS with the certificates of `DOCKER_CERT_PATH`, when set
+/// to anything but `0`.
+pub const DOCKER_TLS_VERIFY_ENV: &str = "DOCKER_TLS_VERIFY";
+
+/// Host ip ports are published on, which docker canonicalizes as `0.0.0.0`.
+pub(crate) const BIND_IP: &str = "localhost";
+
+/// Connect to the daemon `DOCKER_HOST` points to, over TLS if `DOCKER_TLS_VERIFY` is set, or
+/// to the local daemon. TLS requires the `docker-tls` feature.
+pub fn connect() -> Result<bollard::Docker, bollard::errors::Error> {
+    let docker_host = std::env::var(DOCKER_HOST_ENV).unwrap_or_default();
+    if !is_remote(&docker_host) {
+        return bollard::Docker::connect_with_local_defaults();
+    }
+    let tls_verify =
+        std::env::var(DOCKER_TLS_VERIFY_ENV).is_ok_and(|value| !value.is_empty() && value != "0");
+    if tls_verify {
+        connect_with_tls()
+    } else {
+        bollard::Docker::connect_with_http_defaults()
+    }
+}
+
+#[cfg(feature = "docker-tls")]
+fn connect_with_tls() -> Result<bollard::Docker, bollard::errors::Error> {
+    bollard::Docker::connect_with_ssl_defaults()
+}
+
+/// Refuse rather than talk to the daemon in plain text.
+#[cfg(not(feature = "docker-tls"))]
+fn connect_with_tls() -> Result<bollard::Docker, bollard::errors::Error> {
+    Err(bollard::errors::Error::IOError {
+        err: std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            format!("{DOCKER_TLS_VERIFY_ENV} is set, but the `docker-tls` feature is not enabled"),
+        ),
+    })
+}
+
+/// The host the published ports of the daemon `connect` talks to are reached on.
+pub(crate) fn default_host_ip() -> String {
+    let docker_host = std::env::var(DOCKER_HOST_ENV).unwrap_or_default();
+    host_of(&docker_host).unwrap_or("localhost").to_string()
+}
+
+fn is_remote(docker_host: &str) -> bool {
+    docker_host.starts_with("tcp://")
+        || docker_host.starts_with("http://")
+        || docker_host.starts_with("https://")
+}
+
+/// Host part of a remote `DOCKER_HOST`, e.g. `10.0.0.5` of `tcp://10.0.0.5:2376`.
+fn host_of(docker_host: &str) -> Option<&str> {
+    if !is_remote(docker_host) {
+        return None;
+    }
+    let (_, address) = docker_host.split_once("://")?;
+    let authority = address.split('/').next()?;
+    let host = match authority.strip_prefix('[') {
+        // an ipv6 address
+        Some(rest) => rest.split_once(']').map(|(host, _)| host)?,
+        None => authority
+            .split_once(':')
+            .map_or(authority, |(host, _)| host),
+    };
+    Some(host).filter(|host| !host.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_host_of() {
+        assert_eq!(host_of("tcp://10.0.0.5:2376"), Some("10.0.0.5"));
+        assert_eq!(
+            host_of("https://docker.example.com"),
+            Some("docker.example.com")
+        );
+        assert_eq!(host_of("tcp://[fd00::5]:2375"), Some("fd00::5"));
+        assert_eq!(host_of("unix:///var/run/docker.sock"), None);
+        assert_eq!(host_of(""), None);
+    }
+
+    #[cfg(not(feature = "docker-tls"))]
+    #[test]
+    fn test_tls_without_feature() {
+        let err = connect_with_tls().unwrap_err();
+        assert!(err.to_string().contains("`docker-tls`"));
+    }
+}
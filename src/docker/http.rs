@@ -0,0 +1,184 @@
+use std::time::{Duration, Instant};
+
+use reqwest::{Client, Method, RequestBuilder, Response};
+
+/// Time requests keep being retried while the service is not listening yet, by default
+pub const DEFAULT_WARMUP: Duration = Duration::from_secs(30);
+const RETRY_INTERVAL: Duration = Duration::from_millis(200);
+
+/// A `reqwest` client bound to the published port of a container, see `ContainerHandle::http`.
+///
+/// Requests are retried while the connection is refused or closed before any response, as
+/// services often publish their port before listening on it, and the userland proxy of docker
+/// accepts connections on the port until then only to close them:
+///
+/// ```no_run
+/// # async fn run() {
+/// use test_utilities::docker::{Builder, HostPort};
+///
+/// let nginx = Builder::new("nginx")
+///     .protocol("http")
+///     .bind_port_as_default(Some(HostPort::ANY), 80)
+///     .build_disposable()
+///     .await;
+/// let response = nginx.http().get("/index.html").await.unwrap();
+/// assert!(response.status().is_success());
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct HttpClient {
+    client: Client,
+    base_url: String,
+    warmup: Duration,
+}
+
+impl HttpClient {
+    pub(super) fn new(base_url: String) -> Self {
+        HttpClient {
+            client: Client::new(),
+            base_url,
+            warmup: DEFAULT_WARMUP,
+        }
+    }
+
+    /// Use `client` instead of the default one, e.g. with default headers or timeouts.
+    pub fn with_client(self, client: Client) -> Self {
+        Self { client, ..self }
+    }
+
+    /// Retry requests for up to `warmup` while the service is not listening yet.
+    pub fn warmup(self, warmup: Duration) -> Self {
+        Self { warmup, ..self }
+    }
+
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    /// Url of the container, like `http://127.0.0.1:32768`, without trailing slash.
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// Url of `path` on the container, e.g. `/api/health`.
+    pub fn url(&self, path: &str) -> String {
+        format!("{}/{}", self.base_url, path.trim_start_matches('/'))
+    }
+
+    /// A request of `path` to complete, and to send through `send`.
+    pub fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        self.client.request(method, self.url(path))
+    }
+
+    pub async fn get(&self, path: &str) -> reqwest::Result<Response> {
+        self.send(self.request(Method::GET, path)).await
+    }
+
+    /// Send `request`, retrying while the connection is refused or closed before any response
+    /// until the warmup elapses. Requests with a streamed body cannot be retried, and are sent
+    /// once.
+    pub async fn send(&self, request: RequestBuilder) -> reqwest::Result<Response> {
+        let deadline = Instant::now() + self.warmup;
+        loop {
+            let Some(attempt) = request.try_clone() else {
+                return request.send().await;
+            };
+            match attempt.send().await {
+                // nothing was received, e.g. the connection was closed by the proxy of docker
+                Err(err) if (err.is_connect() || err.is_request()) && Instant::now() < deadline => {
+                    log::debug!("{} is not listening yet, retrying: {err}", self.base_url);
+                    tokio::time::sleep(RETRY_INTERVAL).await;
+                }
+                sent => return sent,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    #[test]
+    fn test_url() {
+        let http = HttpClient::new("http://127.0.0.1:8080".to_string());
+        assert_eq!(http.url("/api/health"), "http://127.0.0.1:8080/api/health");
+        assert_eq!(http.url("api"), "http://127.0.0.1:8080/api");
+    }
+
+    #[tokio::test]
+    async fn test_retry_while_refused() {
+        // reserve a port, then only listen on it after a while
+        let port = TcpListener::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(500)).await;
+            let listener = TcpListener::bind(("127.0.0.1", port)).await.unwrap();
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = [0u8; 1024];
+            let _ = stream.read(&mut request).await.unwrap();
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok")
+                .await
+                .unwrap();
+        });
+
+        let http = HttpClient::new(format!("http://127.0.0.1:{port}"));
+        let response = http.get("/").await.unwrap();
+        assert_eq!(response.text().await.unwrap(), "ok");
+    }
+
+    #[tokio::test]
+    async fn test_retry_while_closed() {
+        // like the proxy of docker, close connections until the service listens
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            for _ in 0..2 {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut request = [0u8; 1024];
+                let _ = stream.read(&mut request).await.unwrap();
+            }
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = [0u8; 1024];
+            let _ = stream.read(&mut request).await.unwrap();
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok")
+                .await
+                .unwrap();
+        });
+
+        let http = HttpClient::new(format!("http://127.0.0.1:{port}"));
+        let response = http.get("/").await.unwrap();
+        assert_eq!(response.text().await.unwrap(), "ok");
+    }
+
+    #[tokio::test]
+    async fn test_https() {
+        // a server which does not speak tls
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            stream
+                .write_all(b"HTTP/1.1 400 Bad Request\r\n\r\n")
+                .await
+                .unwrap();
+        });
+
+        let http = HttpClient::new(format!("https://127.0.0.1:{port}")).warmup(Duration::ZERO);
+        let err = http.get("/").await.unwrap_err();
+        // the handshake is attempted, rather than the scheme refused without a tls backend
+        assert!(
+            !format!("{err:?}").contains("scheme is not http"),
+            "{err:?}"
+        );
+    }
+}
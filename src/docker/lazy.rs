@@ -0,0 +1,130 @@
+use futures::future::BoxFuture;
+use tokio::sync::OnceCell;
+
+use super::{Builder, ContainerHandle, Error};
+use crate::fixture::Fixture;
+
+/// A container started on first use, see `Builder::lazy`.
+///
+/// Suites declare it up front, and only the tests which reach for the service pay for its
+/// startup:
+///
+/// ```no_run
+/// # async fn run() {
+/// use test_utilities::docker::{Builder, HostPort};
+///
+/// let mongo = Builder::new("mongo")
+///     .bind_port_as_default(Some(HostPort::ANY), 27017)
+///     .lazy();
+/// assert!(!mongo.is_started());
+/// // started and waited for here
+/// let url = mongo.url().await.unwrap();
+/// # }
+/// ```
+pub struct LazyHandle {
+    /// Cloned by each attempt at starting the container, until one succeeds
+    builder: Builder,
+    handle: OnceCell<ContainerHandle>,
+}
+
+impl LazyHandle {
+    pub(super) fn new(builder: Builder) -> Self {
+        LazyHandle {
+            builder,
+            handle: OnceCell::new(),
+        }
+    }
+
+    pub fn is_started(&self) -> bool {
+        self.handle.initialized()
+    }
+
+    /// The handle of the container, started and waited for as configured on the builder on
+    /// first call. Concurrent callers wait for the same start.
+    ///
+    /// A start which failed, or whose caller gave up on it, is attempted again by the next
+    /// call.
+    pub async fn handle(&self) -> Result<&ContainerHandle, Error> {
+        self.handle
+            .get_or_try_init(|| self.builder.clone().try_build_disposable())
+            .await
+    }
+
+    /// Url of the container, starting it if need be.
+    pub async fn url(&self) -> Result<String, Error> {
+        self.handle().await?.url()
+    }
+}
+
+impl Fixture for LazyHandle {
+    /// Stop the container if it got started.
+    fn teardown(self: Box<Self>) -> BoxFuture<'static, crate::Result<()>> {
+        match self.handle.into_inner() {
+            Some(handle) => Box::new(handle).teardown(),
+            None => Box::pin(futures::future::ready(Ok(()))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use futures::FutureExt;
+
+    use crate::docker::mock::MockDocker;
+    use crate::docker::{HostPort, Stage, WaitStrategy};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_lazy() {
+        let docker = MockDocker::new();
+        let lazy = Builder::new("mongo")
+            .bind_port_as_default(Some(HostPort::ANY), 27017)
+            .protocol("mongodb")
+            .backend(docker.clone())
+            .lazy();
+        assert!(!lazy.is_started());
+        assert!(docker.containers().is_empty());
+
+        let (url, again) = tokio::join!(lazy.url(), lazy.url());
+        assert!(lazy.is_started());
+        assert_eq!(url.unwrap(), again.unwrap());
+        assert_eq!(docker.containers().len(), 1);
+
+        let id = lazy.handle().await.unwrap().container_id.clone();
+        Box::new(lazy).teardown().await.unwrap();
+        assert!(!docker.is_running(&id));
+    }
+
+    #[tokio::test]
+    async fn test_lazy_failed_start() {
+        let docker = MockDocker::new().reject_auto_remove();
+        let lazy = Builder::new("mongo")
+            .auto_remove(true)
+            .backend(docker)
+            .lazy();
+
+        let err = lazy.handle().await.err().unwrap();
+        assert_eq!(err.stage(), Stage::Create);
+        // attempted again rather than failing for good
+        let err = lazy.handle().await.err().unwrap();
+        assert_eq!(err.stage(), Stage::Create);
+        assert!(!lazy.is_started());
+    }
+
+    #[tokio::test]
+    async fn test_lazy_cancelled_start() {
+        let docker = MockDocker::new();
+        let lazy = Builder::new("mongo")
+            .wait_for(WaitStrategy::Delay(Duration::from_millis(10)))
+            .backend(docker)
+            .lazy();
+
+        // the caller gives up while the container is starting
+        assert!(lazy.handle().now_or_never().is_none());
+        assert!(lazy.handle().await.is_ok());
+        assert!(lazy.is_started());
+    }
+}
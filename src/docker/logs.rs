@@ -0,0 +1,37 @@
+use std::fmt;
+
+/// The stream of a container a line of output was written to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LogSource {
+    Stdout,
+    Stderr,
+}
+
+/// A line of output of a container, without its line terminator.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LogLine {
+    pub source: LogSource,
+    pub message: String,
+}
+
+impl LogLine {
+    /// Split a chunk of output into its lines.
+    pub(crate) fn split(source: LogSource, chunk: &str) -> Vec<LogLine> {
+        chunk
+            .lines()
+            .map(|message| LogLine {
+                source,
+                message: message.to_string(),
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for LogLine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.source {
+            LogSource::Stdout => write!(f, "{}", self.message),
+            LogSource::Stderr => write!(f, "[stderr] {}", self.message),
+        }
+    }
+}
@@ -0,0 +1,761 @@
+//! An in-memory stand-in for the docker daemon.
+//!
+//! `MockDocker` records the containers created through it and answers inspect requests the
+//! way the daemon would, so fixture composition can be tested without starting anything:
+//!
+//! ```no_run
+//! # async fn run() {
+//! use test_utilities::docker::{mock::MockDocker, Builder, HostPort};
+//!
+//! let docker = MockDocker::new();
+//! let handle = Builder::new("mongo")
+//!     .bind_port_as_default(Some(HostPort::ANY), 27017)
+//!     .backend(docker.clone())
+//!     .build_disposable()
+//!     .await;
+//! assert!(docker.is_running(&handle.container_id));
+//! # }
+//! ```
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bollard::auth::DockerCredentials;
+use bollard::container::{Config, CreateContainerOptions};
+use bollard::models::{
+    ContainerConfig, ContainerInspectResponse, ContainerState, ContainerStateStatusEnum,
+    ContainerSummary, Health, HealthStatusEnum, ImageInspect, NetworkSettings, PortBinding,
+    PortMap,
+};
+use futures::future::BoxFuture;
+use futures::stream::BoxStream;
+use futures::{StreamExt, TryFutureExt};
+use rand::Rng;
+
+use super::backend::{network_labels, split_image_tag, Backend, BackendResult, ExecOutput};
+use super::changes::{ChangeKind, FsChange};
+use super::logs::{LogLine, LogSource};
+
+/// First host port handed out for bindings which let the daemon choose.
+const FIRST_EPHEMERAL_PORT: u16 = 49153;
+
+#[derive(Clone, Default)]
+pub struct MockDocker {
+    state: Arc<Mutex<State>>,
+}
+
+#[derive(Default)]
+struct State {
+    containers: HashMap<String, MockContainer>,
+    images: HashSet<String>,
+    /// Credentials each image was pulled with
+    pull_credentials: HashMap<String, DockerCredentials>,
+    next_port: u16,
+    /// Host ports to publish container ports on instead of ephemeral ones, by container port
+    fixed_host_ports: HashMap<String, String>,
+    /// Lines printed by the containers of each image once started
+    startup_logs: HashMap<String, Vec<String>>,
+    /// Host the published ports are reached on, as told by `Backend::host_ip`
+    host_ip: Option<String>,
+    /// Whether creating containers with auto remove fails, like on some rootless daemons
+    reject_auto_remove: bool,
+    /// Names of the user-defined networks, by id
+    networks: HashMap<String, String>,
+}
+
+struct MockContainer {
+    name: String,
+    config: Config<String>,
+    ports: Option<PortMap>,
+    running: bool,
+    /// Exit code of a container which exited on its own, see `MockDocker::exit`
+    exit_code: Option<i64>,
+    /// Memory the container reports to use, see `MockDocker::set_memory_usage`
+    memory_usage: u64,
+    /// Creation time in seconds since the epoch
+    created: i64,
+    logs: String,
+    /// Content of the files copied into the container, by absolute path
+    files: BTreeMap<String, Vec<u8>>,
+    /// Commands run in the container, in order
+    execs: Vec<Vec<String>>,
+}
+
+impl MockDocker {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Make the mock reject containers which ask for auto removal.
+    pub fn reject_auto_remove(self) -> Self {
+        self.state.lock().unwrap().reject_auto_remove = true;
+        self
+    }
+
+    /// Make the mock stand for a remote daemon whose published ports are reached on `host_ip`.
+    pub fn host_ip<S: Into<String>>(self, host_ip: S) -> Self {
+        self.state.lock().unwrap().host_ip = Some(host_ip.into());
+        self
+    }
+
+    /// Make the containers of `image` print `line` once started.
+    pub fn startup_log<S: Into<String>, L: Into<String>>(self, image: S, line: L) -> Self {
+        self.state
+            .lock()
+            .unwrap()
+            .startup_logs
+            .entry(image.into())
+            .or_default()
+            .push(line.into());
+        self
+    }
+
+    /// Publish the TCP container port `port` on `host_port` wherever it is bound to any port,
+    /// e.g. to serve it from a listener of the test.
+    pub fn fixed_host_port(self, port: u16, host_port: u16) -> Self {
+        self.state
+            .lock()
+            .unwrap()
+            .fixed_host_ports
+            .insert(format!("{port}/tcp"), host_port.to_string());
+        self
+    }
+
+    /// Ids of all the containers known to the mock, running or not.
+    pub fn containers(&self) -> Vec<String> {
+        self.state
+            .lock()
+            .unwrap()
+            .containers
+            .keys()
+            .cloned()
+            .collect()
+    }
+
+    pub fn is_running(&self, id: &str) -> bool {
+        self.state
+            .lock()
+            .unwrap()
+            .containers
+            .get(id)
+            .map(|container| container.running)
+            .unwrap_or(false)
+    }
+
+    /// Make a running container exit on its own with `code`, as if it crashed.
+    pub fn exit(&self, id: &str, code: i64) {
+        if let Some(container) = self.state.lock().unwrap().containers.get_mut(id) {
+            container.running = false;
+            container.exit_code = Some(code);
+        }
+    }
+
+    /// Remove a container behind the back of its handle, as someone else would.
+    pub fn remove(&self, id: &str) {
+        self.state.lock().unwrap().containers.remove(id);
+    }
+
+    /// Make a container report that it uses `bytes` of memory.
+    pub fn set_memory_usage(&self, id: &str, bytes: u64) {
+        if let Some(container) = self.state.lock().unwrap().containers.get_mut(id) {
+            container.memory_usage = bytes;
+        }
+    }
+
+    /// Names of the networks created through the mock which still exist.
+    pub fn networks(&self) -> HashSet<String> {
+        self.state
+            .lock()
+            .unwrap()
+            .networks
+            .values()
+            .cloned()
+            .collect()
+    }
+
+    /// Images pulled through the mock so far.
+    pub fn pulled_images(&self) -> HashSet<String> {
+        self.state.lock().unwrap().images.clone()
+    }
+
+    /// The credentials `image` was last pulled with, if any.
+    pub fn pull_credentials(&self, image: &str) -> Option<DockerCredentials> {
+        self.state
+            .lock()
+            .unwrap()
+            .pull_credentials
+            .get(image)
+            .cloned()
+    }
+
+    /// Commands run in a container so far, which all succeed without output.
+    pub fn execs(&self, id: &str) -> Vec<Vec<String>> {
+        self.state
+            .lock()
+            .unwrap()
+            .containers
+            .get(id)
+            .map(|container| container.execs.clone())
+            .unwrap_or_default()
+    }
+
+    /// The config a container was created with, if it still exists.
+    pub fn config(&self, id: &str) -> Option<Config<String>> {
+        self.state
+            .lock()
+            .unwrap()
+            .containers
+            .get(id)
+            .map(|container| container.config.clone())
+    }
+}
+
+impl State {
+    /// Resolve a network id or name to the network id.
+    fn resolve_network(&self, network: &str) -> Option<String> {
+        self.networks
+            .iter()
+            .find(|(id, name)| *id == network || *name == network)
+            .map(|(id, _)| id.clone())
+    }
+
+    /// Resolve a container id or name to the container id.
+    fn resolve(&self, id: &str) -> BackendResult<String> {
+        if self.containers.contains_key(id) {
+            return Ok(id.to_string());
+        }
+        self.containers
+            .iter()
+            .find(|(_, container)| container.name == id)
+            .map(|(id, _)| id.clone())
+            .ok_or_else(|| not_found(id))
+    }
+
+    fn get_mut(&mut self, id: &str) -> BackendResult<&mut MockContainer> {
+        let id = self.resolve(id)?;
+        Ok(self.containers.get_mut(&id).unwrap())
+    }
+
+    /// Whether a running container other than `id` publishes `host_port`.
+    fn publishes(&self, id: &str, host_port: &str) -> bool {
+        self.containers
+            .iter()
+            .filter(|(other, container)| other.as_str() != id && container.running)
+            .filter_map(|(_, container)| container.ports.as_ref())
+            .flat_map(|ports| ports.values().flatten().flatten())
+            .any(|binding| binding.host_port.as_deref() == Some(host_port))
+    }
+
+    fn allocate_port(&mut self, port: &str) -> String {
+        if let Some(host_port) = self.fixed_host_ports.get(port) {
+            return host_port.clone();
+        }
+        let port = FIRST_EPHEMERAL_PORT.max(self.next_port);
+        self.next_port = port + 1;
+        port.to_string()
+    }
+}
+
+impl Backend for MockDocker {
+    fn pull_image<'a>(
+        &'a self,
+        image: &'a str,
+        credentials: Option<DockerCredentials>,
+    ) -> BoxFuture<'a, BackendResult<()>> {
+        Box::pin(async move {
+            let mut state = self.state.lock().unwrap();
+            state.images.insert(image.to_string());
+            if let Some(credentials) = credentials {
+                state
+                    .pull_credentials
+                    .insert(image.to_string(), credentials);
+            }
+            Ok(())
+        })
+    }
+
+    fn inspect_image<'a>(&'a self, image: &'a str) -> BoxFuture<'a, BackendResult<ImageInspect>> {
+        Box::pin(async move {
+            if !self.state.lock().unwrap().images.contains(image) {
+                return Err(server_error(404, format!("No such image: {image}")));
+            }
+            // the digest only has to be stable for the same reference
+            let (repo, _) = split_image_tag(image);
+            let digest = format!("{repo}@sha256:{:064x}", fnv1a(image.as_bytes()));
+            Ok(ImageInspect {
+                id: Some(format!("sha256:{}", random_hex(64))),
+                repo_tags: Some(vec![image.to_string()]),
+                repo_digests: Some(vec![digest]),
+                ..Default::default()
+            })
+        })
+    }
+
+    fn create_container(
+        &self,
+        options: Option<CreateContainerOptions<String>>,
+        config: Config<String>,
+    ) -> BoxFuture<'_, BackendResult<String>> {
+        Box::pin(async move {
+            let mut state = self.state.lock().unwrap();
+            let auto_remove = config
+                .host_config
+                .as_ref()
+                .and_then(|host_config| host_config.auto_remove)
+                .unwrap_or(false);
+            if auto_remove && state.reject_auto_remove {
+                return Err(server_error(
+                    400,
+                    "AutoRemove is not supported by this daemon".to_string(),
+                ));
+            }
+
+            let id = random_hex(64);
+            let name = options
+                .map(|options| options.name)
+                .filter(|name| !name.is_empty())
+                .unwrap_or_else(|| id[..12].to_string());
+            if state.containers.values().any(|c| c.name == name) {
+                return Err(server_error(
+                    409,
+                    format!("Conflict. The container name \"/{name}\" is already in use"),
+                ));
+            }
+
+            state.containers.insert(
+                id.clone(),
+                MockContainer {
+                    name,
+                    config,
+                    ports: None,
+                    running: false,
+                    exit_code: None,
+                    memory_usage: 0,
+                    created: SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .map(|since| since.as_secs() as i64)
+                        .unwrap_or_default(),
+                    logs: String::new(),
+                    files: BTreeMap::new(),
+                    execs: Vec::new(),
+                },
+            );
+            Ok(id)
+        })
+    }
+
+    fn start_container<'a>(&'a self, id: &'a str) -> BoxFuture<'a, BackendResult<()>> {
+        Box::pin(async move {
+            let mut state = self.state.lock().unwrap();
+            let bindings = state
+                .get_mut(id)?
+                .config
+                .host_config
+                .as_ref()
+                .and_then(|host_config| host_config.port_bindings.clone())
+                .unwrap_or_default();
+
+            let mut ports = PortMap::new();
+            for (port, bindings) in bindings {
+                let bindings = bindings
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|binding| PortBinding {
+                        // the daemon reports every localhost binding as bound to 0.0.0.0
+                        host_ip: Some("0.0.0.0".to_string()),
+                        host_port: match binding.host_port.as_deref() {
+                            None | Some("") | Some("0") => Some(state.allocate_port(&port)),
+                            Some(host_port) => Some(host_port.to_string()),
+                        },
+                    })
+                    .collect();
+                ports.insert(port, Some(bindings));
+            }
+
+            let id = state.resolve(id)?;
+            // like the daemon, fail to start on host ports other containers publish
+            let taken = ports
+                .values()
+                .flatten()
+                .flatten()
+                .filter_map(|binding| binding.host_port.as_deref())
+                .find(|host_port| state.publishes(&id, host_port));
+            if let Some(host_port) = taken {
+                return Err(server_error(
+                    500,
+                    format!("Bind for 0.0.0.0:{host_port} failed: port is already allocated"),
+                ));
+            }
+            let image = state.containers[&id]
+                .config
+                .image
+                .clone()
+                .unwrap_or_default();
+            let logs = state.startup_logs.get(&image).cloned().unwrap_or_default();
+            let container = state.containers.get_mut(&id).unwrap();
+            container.ports = Some(ports);
+            container.running = true;
+            for line in logs {
+                container.logs.push_str(&line);
+                container.logs.push('\n');
+            }
+            Ok(())
+        })
+    }
+
+    fn inspect_container<'a>(
+        &'a self,
+        id: &'a str,
+    ) -> BoxFuture<'a, BackendResult<ContainerInspectResponse>> {
+        Box::pin(async move {
+            let state = self.state.lock().unwrap();
+            let id = state.resolve(id)?;
+            let container = &state.containers[&id];
+            let config = &container.config;
+
+            Ok(ContainerInspectResponse {
+                id: Some(id.clone()),
+                name: Some(format!("/{}", container.name)),
+                config: Some(ContainerConfig {
+                    image: config.image.clone(),
+                    env: config.env.clone(),
+                    labels: config.labels.clone(),
+                    cmd: config.cmd.clone(),
+                    entrypoint: config.entrypoint.clone(),
+                    ..Default::default()
+                }),
+                host_config: config.host_config.clone(),
+                state: Some(ContainerState {
+                    running: Some(container.running),
+                    status: Some(match (container.running, container.exit_code) {
+                        (true, _) => ContainerStateStatusEnum::RUNNING,
+                        (false, Some(_)) => ContainerStateStatusEnum::EXITED,
+                        (false, None) => ContainerStateStatusEnum::CREATED,
+                    }),
+                    exit_code: container.exit_code,
+                    // healthchecks pass as soon as the container runs
+                    health: config.healthcheck.as_ref().map(|_| Health {
+                        status: Some(if container.running {
+                            HealthStatusEnum::HEALTHY
+                        } else {
+                            HealthStatusEnum::STARTING
+                        }),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                }),
+                network_settings: Some(NetworkSettings {
+                    ports: container.ports.clone(),
+                    networks: user_network(config).map(|network| {
+                        let endpoint = config
+                            .networking_config
+                            .as_ref()
+                            .and_then(|networking| networking.endpoints_config.get(network))
+                            .cloned()
+                            .unwrap_or_default();
+                        HashMap::from([(network.to_string(), endpoint)])
+                    }),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            })
+        })
+    }
+
+    fn logs<'a>(&'a self, id: &'a str) -> BoxFuture<'a, BackendResult<String>> {
+        Box::pin(async move {
+            let state = self.state.lock().unwrap();
+            let id = state.resolve(id)?;
+            Ok(state.containers[&id].logs.clone())
+        })
+    }
+
+    fn exec<'a>(
+        &'a self,
+        id: &'a str,
+        cmd: Vec<String>,
+    ) -> BoxFuture<'a, BackendResult<ExecOutput>> {
+        Box::pin(async move {
+            let mut state = self.state.lock().unwrap();
+            let container = state.get_mut(id)?;
+            if !container.running {
+                return Err(server_error(409, format!("Container {id} is not running")));
+            }
+            container.execs.push(cmd);
+            Ok(ExecOutput {
+                exit_code: 0,
+                output: String::new(),
+            })
+        })
+    }
+
+    fn list_containers<'a>(
+        &'a self,
+        label: &'a str,
+    ) -> BoxFuture<'a, BackendResult<Vec<ContainerSummary>>> {
+        Box::pin(async move {
+            let state = self.state.lock().unwrap();
+            Ok(state
+                .containers
+                .iter()
+                .filter(|(_, container)| has_label(container.config.labels.as_ref(), label))
+                .map(|(id, container)| ContainerSummary {
+                    id: Some(id.clone()),
+                    names: Some(vec![format!("/{}", container.name)]),
+                    image: container.config.image.clone(),
+                    labels: container.config.labels.clone(),
+                    created: Some(container.created),
+                    state: Some(
+                        if container.running {
+                            "running"
+                        } else {
+                            "created"
+                        }
+                        .to_string(),
+                    ),
+                    ..Default::default()
+                })
+                .collect())
+        })
+    }
+
+    fn log_stream<'a>(
+        &'a self,
+        id: &'a str,
+        _follow: bool,
+    ) -> BoxStream<'a, BackendResult<LogLine>> {
+        let logs = Backend::logs(self, id);
+        Box::pin(async move {
+            let lines = LogLine::split(LogSource::Stdout, &logs.await?);
+            Ok::<_, bollard::errors::Error>(futures::stream::iter(lines.into_iter().map(Ok)))
+        })
+        .try_flatten_stream()
+        .boxed()
+    }
+
+    fn upload_archive<'a>(
+        &'a self,
+        id: &'a str,
+        path: &'a str,
+        archive: Vec<u8>,
+    ) -> BoxFuture<'a, BackendResult<()>> {
+        Box::pin(async move {
+            let mut state = self.state.lock().unwrap();
+            let container = state.get_mut(id)?;
+            let mut archive = tar::Archive::new(archive.as_slice());
+            for entry in archive.entries()? {
+                let mut entry = entry?;
+                if !entry.header().entry_type().is_file() {
+                    continue;
+                }
+                let name = entry.path()?.to_string_lossy().into_owned();
+                let mut content = Vec::new();
+                std::io::Read::read_to_end(&mut entry, &mut content)?;
+                container.files.insert(join_path(path, &name), content);
+            }
+            Ok(())
+        })
+    }
+
+    fn download_archive<'a>(
+        &'a self,
+        id: &'a str,
+        path: &'a str,
+    ) -> BoxFuture<'a, BackendResult<Vec<u8>>> {
+        Box::pin(async move {
+            let mut state = self.state.lock().unwrap();
+            let container = state.get_mut(id)?;
+            // like the daemon, entries are named after the last component of `path`
+            let path = path.trim_end_matches('/');
+            let (parent, _) = path.rsplit_once('/').unwrap_or(("", path));
+            let dir_prefix = format!("{path}/");
+            let mut builder = tar::Builder::new(Vec::new());
+            let mut found = false;
+            for (file, content) in &container.files {
+                if file == path || file.starts_with(&dir_prefix) {
+                    let name = file[parent.len()..].trim_start_matches('/');
+                    let mut header = tar::Header::new_gnu();
+                    header.set_size(content.len() as u64);
+                    header.set_mode(0o644);
+                    header.set_cksum();
+                    builder.append_data(&mut header, name, content.as_slice())?;
+                    found = true;
+                }
+            }
+            if !found {
+                return Err(server_error(
+                    404,
+                    format!("Could not find the file {path} in container {id}"),
+                ));
+            }
+            Ok(builder.into_inner()?)
+        })
+    }
+
+    fn container_changes<'a>(&'a self, id: &'a str) -> BoxFuture<'a, BackendResult<Vec<FsChange>>> {
+        Box::pin(async move {
+            let mut state = self.state.lock().unwrap();
+            let container = state.get_mut(id)?;
+            // copied files are added, and their parent directories modified, as on the daemon
+            let mut changes = BTreeMap::new();
+            for file in container.files.keys() {
+                for parent in Path::new(file).ancestors().skip(1) {
+                    if parent != Path::new("/") {
+                        let parent = parent.to_string_lossy().into_owned();
+                        changes.entry(parent).or_insert(ChangeKind::Modified);
+                    }
+                }
+                changes.insert(file.clone(), ChangeKind::Added);
+            }
+            Ok(changes
+                .into_iter()
+                .map(|(path, kind)| FsChange { path, kind })
+                .collect())
+        })
+    }
+
+    fn memory_usage<'a>(&'a self, id: &'a str) -> BoxFuture<'a, BackendResult<u64>> {
+        Box::pin(async move {
+            let mut state = self.state.lock().unwrap();
+            Ok(state.get_mut(id)?.memory_usage)
+        })
+    }
+
+    fn list_networks<'a>(&'a self, label: &'a str) -> BoxFuture<'a, BackendResult<Vec<String>>> {
+        Box::pin(async move {
+            // every network is created with the same labels
+            if !has_label(Some(&network_labels()), label) {
+                return Ok(Vec::new());
+            }
+            Ok(self.networks().into_iter().collect())
+        })
+    }
+
+    fn list_volumes<'a>(&'a self, _label: &'a str) -> BoxFuture<'a, BackendResult<Vec<String>>> {
+        // the mock has no named volumes
+        Box::pin(futures::future::ready(Ok(Vec::new())))
+    }
+
+    fn create_network<'a>(&'a self, name: &'a str) -> BoxFuture<'a, BackendResult<String>> {
+        Box::pin(async move {
+            let mut state = self.state.lock().unwrap();
+            if state.resolve_network(name).is_some() {
+                return Err(server_error(
+                    409,
+                    format!("network with name {name} already exists"),
+                ));
+            }
+            let id = random_hex(64);
+            state.networks.insert(id.clone(), name.to_string());
+            Ok(id)
+        })
+    }
+
+    fn remove_network<'a>(&'a self, id: &'a str) -> BoxFuture<'a, BackendResult<()>> {
+        Box::pin(async move {
+            let mut state = self.state.lock().unwrap();
+            let Some(id) = state.resolve_network(id) else {
+                return Ok(());
+            };
+            let name = &state.networks[&id];
+            let attached = state.containers.values().any(|container| {
+                container.running
+                    && user_network(&container.config)
+                        .is_some_and(|network| network == id || network == name)
+            });
+            if attached {
+                return Err(server_error(
+                    403,
+                    format!("error while removing network: network {name} has active endpoints"),
+                ));
+            }
+            state.networks.remove(&id);
+            Ok(())
+        })
+    }
+
+    fn stop_container<'a>(&'a self, id: &'a str, remove: bool) -> BoxFuture<'a, BackendResult<()>> {
+        self.dispose(id, remove);
+        Box::pin(async { Ok(()) })
+    }
+
+    fn host_ip(&self) -> Option<String> {
+        self.state.lock().unwrap().host_ip.clone()
+    }
+
+    fn dispose(&self, id: &str, remove: bool) {
+        let mut state = self.state.lock().unwrap();
+        if let Ok(id) = state.resolve(id) {
+            let container = state.containers.get_mut(&id).unwrap();
+            container.running = false;
+            let auto_remove = container
+                .config
+                .host_config
+                .as_ref()
+                .and_then(|host_config| host_config.auto_remove)
+                .unwrap_or(false);
+            if remove || auto_remove {
+                state.containers.remove(&id);
+            }
+        }
+    }
+}
+
+fn random_hex(len: usize) -> String {
+    let mut rng = rand::thread_rng();
+    (0..len)
+        .map(|_| char::from_digit(rng.gen_range(0..16), 16).unwrap())
+        .collect()
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+fn join_path(dir: &str, name: &str) -> String {
+    format!(
+        "{}/{}",
+        dir.trim_end_matches('/'),
+        name.trim_start_matches("./")
+    )
+}
+
+fn not_found(id: &str) -> bollard::errors::Error {
+    server_error(404, format!("No such container: {id}"))
+}
+
+/// Whether `labels` match `filter`, which is either `key` or `key=value`.
+fn has_label(labels: Option<&HashMap<String, String>>, filter: &str) -> bool {
+    let (key, value) = match filter.split_once('=') {
+        Some((key, value)) => (key, Some(value)),
+        None => (filter, None),
+    };
+    match labels.and_then(|labels| labels.get(key)) {
+        Some(actual) => value.is_none_or(|value| actual == value),
+        None => false,
+    }
+}
+
+/// The user-defined network the container joins, if any, as opposed to the networks every
+/// daemon has and the network namespace of another container.
+fn user_network(config: &Config<String>) -> Option<&str> {
+    config
+        .host_config
+        .as_ref()
+        .and_then(|host_config| host_config.network_mode.as_deref())
+        .filter(|mode| {
+            !matches!(*mode, "" | "default" | "bridge" | "host" | "none")
+                && !mode.starts_with("container:")
+        })
+}
+
+fn server_error(status_code: u16, message: String) -> bollard::errors::Error {
+    bollard::errors::Error::DockerResponseServerError {
+        status_code,
+        message,
+    }
+}
@@ -0,0 +1,64 @@
+use std::collections::HashSet;
+use std::sync::{Mutex, OnceLock};
+
+use fake::faker::lorem::en::Word;
+use fake::Fake;
+use rand::Rng;
+
+use crate::rng::TestRng;
+
+/// Names handed out by `unique_name` in this process
+static NAMES: OnceLock<Mutex<HashSet<String>>> = OnceLock::new();
+
+/// Generate a human-readable container name like `mongo-brisk-otter-4821`, which is
+/// guaranteed not to be returned twice within the process.
+pub fn unique_name<S: AsRef<str>>(prefix: S) -> String {
+    let prefix = prefix.as_ref();
+    let names = NAMES.get_or_init(Default::default);
+    let mut rng = TestRng;
+    loop {
+        let first: String = Word().fake_with_rng(&mut rng);
+        let second: String = Word().fake_with_rng(&mut rng);
+        let number = rng.gen_range(0..10000);
+        let name = [prefix, &sanitize(&first), &sanitize(&second)]
+            .into_iter()
+            .filter(|part| !part.is_empty())
+            .chain([format!("{number:04}").as_str()])
+            .collect::<Vec<_>>()
+            .join("-");
+
+        if names.lock().unwrap().insert(name.clone()) {
+            return name;
+        }
+    }
+}
+
+fn sanitize(word: &str) -> String {
+    word.chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unique_name_is_unique() {
+        let names = (0..1000)
+            .map(|_| unique_name("mongo"))
+            .collect::<HashSet<_>>();
+
+        assert_eq!(names.len(), 1000);
+        assert!(names.iter().all(|name| name.starts_with("mongo-")));
+    }
+
+    #[test]
+    fn test_unique_name_without_prefix() {
+        let name = unique_name("");
+
+        assert!(!name.starts_with('-'));
+        assert_eq!(name.split('-').count(), 3);
+    }
+}
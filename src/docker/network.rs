@@ -0,0 +1,151 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::backend::{self, Backend};
+use super::{connect, Builder, Error, Stage};
+
+/// Attempts at removing a network whose auto-removed containers may still be detaching
+const REMOVE_ATTEMPTS: usize = 20;
+const REMOVE_RETRY_DELAY: Duration = Duration::from_millis(250);
+
+/// A user-defined bridge network, removed when the handle is dropped.
+///
+/// Containers on it reach each other by name and by their network aliases, which the default
+/// bridge network does not provide. Declare the handle before the containers, so that it is
+/// dropped after them:
+///
+/// ```no_run
+/// # async fn run() {
+/// use test_utilities::docker::{unique_name, NetworkHandle};
+///
+/// let network = NetworkHandle::create(unique_name("net")).await.unwrap();
+/// let mongo = network
+///     .builder("mongo")
+///     .network_alias("mongo")
+///     .build_disposable()
+///     .await;
+/// let app = network
+///     .builder("my-app")
+///     .env("MONGO_URL", mongo.network_url(&network.name).unwrap())
+///     .build_disposable()
+///     .await;
+/// # }
+/// ```
+pub struct NetworkHandle {
+    pub network_id: String,
+    pub name: String,
+    backend: Arc<dyn Backend>,
+    detached: bool,
+}
+
+impl NetworkHandle {
+    /// Create a network named `name` on the local docker daemon. The name has to be unused,
+    /// see `unique_name`.
+    pub async fn create<S: Into<String>>(name: S) -> Result<Self, Error> {
+        let docker = connect().map_err(|err| Error::new(Stage::Connect, err))?;
+        NetworkHandle::create_with(docker, name).await
+    }
+
+    pub async fn create_with<B, S>(backend: B, name: S) -> Result<Self, Error>
+    where
+        B: Backend + 'static,
+        S: Into<String>,
+    {
+        let name = name.into();
+        let network_id = backend
+            .create_network(&name)
+            .await
+            .map_err(|err| Error::new(Stage::CreateNetwork, err))?;
+        log::info!("created network {name}");
+        crate::leakcheck::record(crate::leakcheck::Leak::Network(name.clone()));
+        Ok(NetworkHandle {
+            network_id,
+            name,
+            backend: Arc::new(backend),
+            detached: false,
+        })
+    }
+
+    /// A builder of a container attached to the network, on the same daemon.
+    pub fn builder<S: Into<String>>(&self, image: S) -> Builder {
+        let mut builder = Builder::new(image).network(self.name.as_str());
+        builder.backend = Some(self.backend.clone());
+        builder
+    }
+
+    /// Keep the network after the handle is dropped, e.g. for containers which outlive the
+    /// process, returning its name. Removing it is then up to the caller.
+    pub fn detach(mut self) -> String {
+        self.detached = true;
+        self.name.clone()
+    }
+}
+
+impl Drop for NetworkHandle {
+    fn drop(&mut self) {
+        if !self.detached {
+            remove(self.backend.clone(), &self.network_id);
+        }
+    }
+}
+
+/// Remove the network `network`, by id or name, once the containers removed by the daemon have
+/// detached from it. Failures are logged.
+pub(crate) fn remove(backend: Arc<dyn Backend>, network: &str) {
+    let id = network.to_string();
+    let removed = backend::block_on(async move {
+        let mut attempt = 1;
+        loop {
+            match backend.remove_network(&id).await {
+                // containers removed by the daemon detach asynchronously
+                Err(bollard::errors::Error::DockerResponseServerError {
+                    status_code: 403 | 409,
+                    ..
+                }) if attempt < REMOVE_ATTEMPTS => {
+                    attempt += 1;
+                    tokio::time::sleep(REMOVE_RETRY_DELAY).await;
+                }
+                removed => return removed,
+            }
+        }
+    });
+    match removed {
+        Ok(Ok(())) => {}
+        Ok(Err(err)) => log::warn!("failed to remove network {network}: {err}"),
+        Err(err) => log::warn!("failed to remove network {network}: {err}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::docker::mock::MockDocker;
+    use crate::docker::HostPort;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_network() {
+        let docker = MockDocker::new();
+        let network = NetworkHandle::create_with(docker.clone(), "test-net")
+            .await
+            .unwrap();
+        let mongo = network
+            .builder("mongo")
+            .name("mongo-on-net")
+            .network_alias("db")
+            .bind_port_as_default(Some(HostPort::ANY), 27017)
+            .build_disposable()
+            .await;
+
+        assert_eq!(mongo.network_aliases("test-net"), ["db", "mongo-on-net"]);
+        assert_eq!(
+            mongo.network_url("test-net").unwrap(),
+            "mongodb://db:27017/"
+        );
+        assert!(mongo.network_url("other-net").is_err());
+
+        drop(mongo);
+        drop(network);
+        assert!(docker.networks().is_empty());
+    }
+}
@@ -0,0 +1,144 @@
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Protocol {
+    #[default]
+    Tcp,
+    Udp,
+    Sctp,
+}
+
+impl fmt::Display for Protocol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let protocol = match self {
+            Protocol::Tcp => "tcp",
+            Protocol::Udp => "udp",
+            Protocol::Sctp => "sctp",
+        };
+        f.write_str(protocol)
+    }
+}
+
+impl FromStr for Protocol {
+    type Err = ParsePortError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "tcp" => Ok(Protocol::Tcp),
+            "udp" => Ok(Protocol::Udp),
+            "sctp" => Ok(Protocol::Sctp),
+            _ => Err(ParsePortError(s.to_string())),
+        }
+    }
+}
+
+/// A port exposed inside a container, `tcp` unless specified otherwise.
+///
+/// It parses from and displays as docker's `<port>/<protocol>` notation, the protocol being
+/// optional when parsing.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ContainerPort(pub u16, pub Protocol);
+
+impl ContainerPort {
+    pub fn tcp(port: u16) -> Self {
+        ContainerPort(port, Protocol::Tcp)
+    }
+
+    pub fn udp(port: u16) -> Self {
+        ContainerPort(port, Protocol::Udp)
+    }
+
+    pub fn port(&self) -> u16 {
+        self.0
+    }
+
+    pub fn protocol(&self) -> Protocol {
+        self.1
+    }
+}
+
+impl From<u16> for ContainerPort {
+    fn from(port: u16) -> Self {
+        ContainerPort::tcp(port)
+    }
+}
+
+impl fmt::Display for ContainerPort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.0, self.1)
+    }
+}
+
+impl FromStr for ContainerPort {
+    type Err = ParsePortError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (port, protocol) = match s.split_once('/') {
+            Some((port, protocol)) => (port, protocol.parse()?),
+            None => (s, Protocol::Tcp),
+        };
+        let port = port.parse().map_err(|_| ParsePortError(s.to_string()))?;
+        Ok(ContainerPort(port, protocol))
+    }
+}
+
+/// A port on the host a container port is published to, `0` letting the daemon pick a free one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct HostPort(pub u16);
+
+impl HostPort {
+    /// Let the daemon pick a free port
+    pub const ANY: HostPort = HostPort(0);
+}
+
+impl From<u16> for HostPort {
+    fn from(port: u16) -> Self {
+        HostPort(port)
+    }
+}
+
+impl fmt::Display for HostPort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl FromStr for HostPort {
+    type Err = ParsePortError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse()
+            .map(HostPort)
+            .map_err(|_| ParsePortError(s.to_string()))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParsePortError(String);
+
+impl fmt::Display for ParsePortError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid port `{}`", self.0)
+    }
+}
+
+impl std::error::Error for ParsePortError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ports() {
+        assert_eq!("27017".parse(), Ok(ContainerPort::tcp(27017)));
+        assert_eq!("53/udp".parse(), Ok(ContainerPort::udp(53)));
+        assert_eq!("9/SCTP".parse(), Ok(ContainerPort(9, Protocol::Sctp)));
+        assert!("27017/quic".parse::<ContainerPort>().is_err());
+        assert!("http".parse::<ContainerPort>().is_err());
+        assert_eq!(ContainerPort::from(27017).to_string(), "27017/tcp");
+
+        assert_eq!("0".parse(), Ok(HostPort::ANY));
+        assert!("65536".parse::<HostPort>().is_err());
+    }
+}
@@ -0,0 +1,79 @@
+//! Builders preconfigured for services commonly needed by tests.
+//!
+//! Each preset is behind its own `preset-*` feature, `presets-all` enabling all of them.
+
+#[cfg(feature = "preset-cargo-app")]
+pub use cargo_app::{cargo_app, CargoApp};
+#[cfg(feature = "preset-clickhouse")]
+pub use clickhouse::{clickhouse, ClickHouse, ClickHouseHandleExt, FixtureFormat};
+#[cfg(feature = "preset-elasticsearch")]
+pub use elasticsearch::{elasticsearch, opensearch, Elasticsearch, ElasticsearchFixture};
+#[cfg(feature = "preset-ferretdb")]
+pub use ferretdb::{ferretdb, FerretDb, FerretDbFixture};
+#[cfg(feature = "preset-kafka")]
+pub use kafka::{kafka, redpanda, Kafka, KafkaFixture, SchemaType};
+#[cfg(feature = "preset-localstack")]
+pub use localstack::{localstack, AwsService, Localstack, LocalstackFixture};
+#[cfg(feature = "preset-minio")]
+pub use minio::{minio, Minio, MinioFixture};
+#[cfg(feature = "preset-mongo")]
+pub use mongo::{mongo, Mongo, MongoFixture};
+#[cfg(feature = "preset-mysql")]
+pub use mysql::{mariadb, mysql, MySql, MySqlFixture};
+#[cfg(feature = "preset-postgres")]
+pub use postgres::{postgres, Postgres, PostgresFixture, PostgresHandleExt};
+#[cfg(feature = "preset-rabbitmq")]
+pub use rabbitmq::{rabbitmq, RabbitMq, RabbitMqFixture};
+#[cfg(feature = "preset-recording-proxy")]
+pub use recording_proxy::{ProxyMode, RecordingProxy, PROXY_MODE_ENV};
+#[cfg(feature = "preset-redis")]
+pub use redis::{redis, Message, Redis, RedisFixture, RedisHandleExt, Subscription};
+#[cfg(feature = "preset-registry")]
+pub use registry::{registry, Registry, RegistryHandleExt};
+
+#[cfg(feature = "preset-cargo-app")]
+mod cargo_app;
+#[cfg(feature = "preset-clickhouse")]
+mod clickhouse;
+#[cfg(feature = "preset-elasticsearch")]
+mod elasticsearch;
+#[cfg(feature = "preset-ferretdb")]
+mod ferretdb;
+#[cfg(any(
+    feature = "preset-clickhouse",
+    feature = "preset-elasticsearch",
+    feature = "preset-kafka",
+    feature = "preset-localstack",
+    feature = "preset-minio",
+    feature = "preset-rabbitmq"
+))]
+mod http;
+#[cfg(feature = "preset-kafka")]
+mod kafka;
+#[cfg(feature = "preset-localstack")]
+mod localstack;
+#[cfg(feature = "preset-minio")]
+mod minio;
+#[cfg(feature = "preset-mongo")]
+mod mongo;
+#[cfg(feature = "preset-mysql")]
+mod mysql;
+#[cfg(feature = "preset-postgres")]
+mod postgres;
+#[cfg(feature = "preset-rabbitmq")]
+mod rabbitmq;
+#[cfg(feature = "preset-recording-proxy")]
+mod recording_proxy;
+#[cfg(feature = "preset-redis")]
+mod redis;
+#[cfg(feature = "preset-registry")]
+mod registry;
+#[cfg(any(
+    feature = "preset-clickhouse",
+    feature = "preset-minio",
+    feature = "preset-mysql",
+    feature = "preset-postgres",
+    feature = "preset-rabbitmq",
+    feature = "preset-redis"
+))]
+mod url;
@@ -0,0 +1,129 @@
+use std::path::{Path, PathBuf};
+
+use crate::docker::{BindOpts, Builder};
+
+const DEFAULT_IMAGE: &str = "debian:bookworm-slim";
+const CONTAINER_TARGET_DIR: &str = "/app/target";
+
+/// Run the locally built binary at `binary_path`, e.g. `env!("CARGO_BIN_EXE_app")`, in a
+/// container, to test the app under test against sibling service containers.
+///
+/// The whole cargo target directory is bind-mounted rather than the binary copied, so that a
+/// rebuilt binary is picked up by the next container without any image build. The binary has to
+/// be built for the platform of the daemon, i.e. Linux.
+pub fn cargo_app<P: AsRef<Path>>(binary_path: P) -> CargoApp {
+    CargoApp {
+        binary_path: binary_path.as_ref().to_path_buf(),
+        args: Vec::new(),
+        image: DEFAULT_IMAGE.to_string(),
+        network: None,
+    }
+}
+
+pub struct CargoApp {
+    binary_path: PathBuf,
+    args: Vec<String>,
+    image: String,
+    network: Option<String>,
+}
+
+impl CargoApp {
+    pub fn arg<S: Into<String>>(mut self, arg: S) -> Self {
+        self.args.push(arg.into());
+        self
+    }
+
+    pub fn args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.args.extend(args.into_iter().map(Into::into));
+        self
+    }
+
+    /// Image providing the runtime of the binary, `debian:bookworm-slim` by default.
+    pub fn image<S: Into<String>>(mut self, image: S) -> Self {
+        self.image = image.into();
+        self
+    }
+
+    /// Attach the app to the network its fixture services are on.
+    pub fn network<S: Into<String>>(mut self, network: S) -> Self {
+        self.network = Some(network.into());
+        self
+    }
+
+    pub fn builder(self) -> Builder {
+        let binary_path = std::path::absolute(&self.binary_path).unwrap_or(self.binary_path);
+        let target_dir = target_dir(&binary_path);
+        let relative = binary_path.strip_prefix(&target_dir).unwrap();
+
+        let mut cmd = vec![format!("{CONTAINER_TARGET_DIR}/{}", relative.display())];
+        cmd.extend(self.args);
+        let bind = format!("{}:{CONTAINER_TARGET_DIR}", target_dir.display());
+        let opts = BindOpts {
+            read_only: true,
+            ..Default::default()
+        };
+
+        let mut builder = Builder::new(self.image)
+            .bind_volume_opts(bind, opts)
+            .cmd(cmd);
+        if let Some(network) = self.network {
+            builder = builder.network(network);
+        }
+        builder
+    }
+}
+
+/// The cargo target directory containing `binary_path`, or its parent directory if it is not in
+/// one.
+fn target_dir(binary_path: &Path) -> PathBuf {
+    binary_path
+        .ancestors()
+        .find(|dir| dir.file_name().is_some_and(|name| name == "target"))
+        .or_else(|| binary_path.parent())
+        .unwrap_or(Path::new("/"))
+        .to_path_buf()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::docker::mock::MockDocker;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_cargo_app_mounts_target_dir() {
+        let docker = MockDocker::new();
+        let handle = cargo_app("/work/app/target/debug/app")
+            .arg("--port")
+            .arg("8080")
+            .network("fixtures")
+            .builder()
+            .backend(docker.clone())
+            .build_disposable()
+            .await;
+
+        let config = docker.config(&handle.container_id).unwrap();
+        assert_eq!(
+            config.cmd.unwrap(),
+            vec!["/app/target/debug/app", "--port", "8080"]
+        );
+        let host_config = config.host_config.unwrap();
+        assert_eq!(
+            host_config.binds.unwrap(),
+            vec!["/work/app/target:/app/target:ro".to_string()]
+        );
+        assert_eq!(host_config.network_mode.as_deref(), Some("fixtures"));
+    }
+
+    #[test]
+    fn test_target_dir_falls_back_to_parent() {
+        assert_eq!(
+            target_dir(Path::new("/opt/bin/app")),
+            PathBuf::from("/opt/bin")
+        );
+    }
+}
@@ -0,0 +1,229 @@
+use std::time::Duration;
+
+use futures::future::BoxFuture;
+
+use super::http;
+use crate::docker::{connect, Backend, Builder, ContainerHandle, Error, HostPort, Stage};
+use crate::fixture::Fixture;
+
+const DEFAULT_IMAGE: &str = "docker.elastic.co/elasticsearch/elasticsearch:8.13.4";
+const DEFAULT_OPENSEARCH_IMAGE: &str = "opensearchproject/opensearch:2.14.0";
+const PORT: u16 = 9200;
+/// Answers once the cluster is yellow, or with 408 after a second
+const HEALTH_CHECK: &str = "/_cluster/health?wait_for_status=yellow&timeout=1s";
+const DEFAULT_HEAP_MB: u64 = 512;
+/// The JVM takes more than its heap, and the container is killed beyond its limit
+const MEMORY_PER_HEAP: u64 = 2;
+/// Nodes take a while to boot, the JVM first
+const DEFAULT_READY_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// A single-node Elasticsearch cluster without security, started as an
+/// `ElasticsearchFixture` once its health is yellow:
+///
+/// ```no_run
+/// # async fn run() {
+/// use test_utilities::docker::presets::elasticsearch;
+///
+/// let elasticsearch = elasticsearch().heap_mb(256).start().await.unwrap();
+/// // e.g. `http://127.0.0.1:32768`
+/// let base_url = elasticsearch.base_url();
+/// # }
+/// ```
+pub fn elasticsearch() -> Elasticsearch {
+    Elasticsearch {
+        flavor: Flavor::Elasticsearch,
+        image: DEFAULT_IMAGE.to_string(),
+        heap_mb: DEFAULT_HEAP_MB,
+        memory_limit: None,
+        ready_timeout: DEFAULT_READY_TIMEOUT,
+    }
+}
+
+/// A single-node OpenSearch cluster, which speaks the API of Elasticsearch 7, without the
+/// security plugin.
+pub fn opensearch() -> Elasticsearch {
+    Elasticsearch {
+        flavor: Flavor::OpenSearch,
+        image: DEFAULT_OPENSEARCH_IMAGE.to_string(),
+        heap_mb: DEFAULT_HEAP_MB,
+        memory_limit: None,
+        ready_timeout: DEFAULT_READY_TIMEOUT,
+    }
+}
+
+/// Distributions of the search engine, which differ in how they are configured.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Flavor {
+    Elasticsearch,
+    OpenSearch,
+}
+
+pub struct Elasticsearch {
+    flavor: Flavor,
+    image: String,
+    heap_mb: u64,
+    memory_limit: Option<u64>,
+    ready_timeout: Duration,
+}
+
+impl Elasticsearch {
+    /// Image of the node, an `elasticsearch` 8 one by default, an `opensearch` 2 one for
+    /// `opensearch`.
+    pub fn image<S: Into<String>>(mut self, image: S) -> Self {
+        self.image = image.into();
+        self
+    }
+
+    /// Heap of the JVM in megabytes, 512 by default. The image sizes it after the memory of
+    /// the host otherwise.
+    pub fn heap_mb(mut self, heap_mb: u64) -> Self {
+        self.heap_mb = heap_mb;
+        self
+    }
+
+    /// Limit the memory of the container to `bytes`, twice the heap by default.
+    pub fn memory_limit(mut self, bytes: u64) -> Self {
+        self.memory_limit = Some(bytes);
+        self
+    }
+
+    /// Time the node has to report the cluster yellow once started, 2 minutes by default.
+    pub fn ready_timeout(mut self, timeout: Duration) -> Self {
+        self.ready_timeout = timeout;
+        self
+    }
+
+    /// The builder of the node, whose url is the one of the REST API. It does not wait for
+    /// the cluster to be healthy.
+    pub fn builder(&self) -> Builder {
+        let java_opts = format!("-Xms{0}m -Xmx{0}m", self.heap_mb);
+        let memory_limit = self
+            .memory_limit
+            .unwrap_or((self.heap_mb * MEMORY_PER_HEAP) << 20);
+        let builder = Builder::new(self.image.as_str())
+            .protocol("http")
+            .bind_port_as_default(Some(HostPort::ANY), PORT)
+            .memory_limit(memory_limit)
+            .env("discovery.type", "single-node");
+        match self.flavor {
+            Flavor::Elasticsearch => builder.envs([
+                ("ES_JAVA_OPTS", java_opts.as_str()),
+                ("xpack.security.enabled", "false"),
+            ]),
+            Flavor::OpenSearch => builder.envs([
+                ("OPENSEARCH_JAVA_OPTS", java_opts.as_str()),
+                ("DISABLE_SECURITY_PLUGIN", "true"),
+                ("DISABLE_INSTALL_DEMO_CONFIG", "true"),
+            ]),
+        }
+    }
+
+    /// Start the node on the local docker daemon.
+    pub async fn start(self) -> Result<ElasticsearchFixture, Error> {
+        let docker = connect().map_err(|err| Error::new(Stage::Connect, err))?;
+        self.start_with(&docker).await
+    }
+
+    pub async fn start_with<B>(self, backend: &B) -> Result<ElasticsearchFixture, Error>
+    where
+        B: Backend + Clone + 'static,
+    {
+        let handle = self
+            .builder()
+            .backend(backend.clone())
+            .try_build_disposable()
+            .await?;
+        let timeout = self.ready_timeout;
+        let port =
+            http::wait_until_ok(&handle, PORT, HEALTH_CHECK, None, timeout, |_| true).await?;
+        Ok(ElasticsearchFixture { handle, port })
+    }
+}
+
+/// A running single-node Elasticsearch or OpenSearch cluster.
+pub struct ElasticsearchFixture {
+    handle: ContainerHandle,
+    /// Host port the REST API is published on
+    port: HostPort,
+}
+
+impl ElasticsearchFixture {
+    pub fn handle(&self) -> &ContainerHandle {
+        &self.handle
+    }
+
+    /// Url of the REST API, like `http://127.0.0.1:32768`, without trailing slash.
+    pub fn base_url(&self) -> String {
+        format!("http://{}:{}", self.handle.host_ip, self.port)
+    }
+}
+
+impl Fixture for ElasticsearchFixture {
+    fn teardown(self: Box<Self>) -> BoxFuture<'static, crate::Result<()>> {
+        Box::new(self.handle).teardown()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::docker::mock::MockDocker;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_elasticsearch_waits_for_cluster_health() {
+        let (port, server) = http::serve(vec![
+            "HTTP/1.0 503 Service Unavailable\r\n\r\n",
+            "HTTP/1.0 408 Request Timeout\r\n\r\n{\"status\":\"red\"}",
+            "HTTP/1.0 200 OK\r\n\r\n{\"status\":\"yellow\"}",
+        ])
+        .await;
+        let docker = MockDocker::new().fixed_host_port(PORT, port);
+        let fixture = elasticsearch()
+            .heap_mb(256)
+            .start_with(&docker)
+            .await
+            .unwrap();
+
+        let requests = server.await.unwrap();
+        assert_eq!(requests.len(), 3);
+        assert!(requests
+            .iter()
+            .all(|request| request.starts_with(&format!("GET {HEALTH_CHECK} "))));
+        let handle = fixture.handle();
+        assert_eq!(handle.env()["discovery.type"], "single-node");
+        assert_eq!(handle.env()["ES_JAVA_OPTS"], "-Xms256m -Xmx256m");
+        assert_eq!(handle.env()["xpack.security.enabled"], "false");
+        let host_config = docker
+            .config(&handle.container_id)
+            .unwrap()
+            .host_config
+            .unwrap();
+        assert_eq!(host_config.memory, Some(512 << 20));
+        assert_eq!(
+            fixture.base_url(),
+            format!("http://{}:{port}", handle.host_ip)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_opensearch_builder() {
+        let docker = MockDocker::new();
+        let handle = opensearch()
+            .memory_limit(1 << 30)
+            .builder()
+            .backend(docker.clone())
+            .build_disposable()
+            .await;
+
+        assert_eq!(handle.env()["OPENSEARCH_JAVA_OPTS"], "-Xms512m -Xmx512m");
+        assert_eq!(handle.env()["DISABLE_SECURITY_PLUGIN"], "true");
+        assert!(!handle.env().contains_key("ES_JAVA_OPTS"));
+        let host_config = docker
+            .config(&handle.container_id)
+            .unwrap()
+            .host_config
+            .unwrap();
+        assert_eq!(host_config.memory, Some(1 << 30));
+    }
+}
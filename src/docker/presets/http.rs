@@ -0,0 +1,199 @@
+//! Requests of the presets to the admin APIs of their services, through `docker::HttpClient`.
+
+use std::io;
+use std::time::Duration;
+
+use reqwest::{Method, RequestBuilder};
+
+#[cfg(any(
+    feature = "preset-elasticsearch",
+    feature = "preset-localstack",
+    feature = "preset-minio",
+    feature = "preset-rabbitmq"
+))]
+use crate::docker::creds::Credentials;
+use crate::docker::HttpClient;
+#[cfg(any(
+    feature = "preset-elasticsearch",
+    feature = "preset-localstack",
+    feature = "preset-minio",
+    feature = "preset-rabbitmq"
+))]
+use crate::docker::{
+    connect_with_backoff, BackoffPolicy, ContainerHandle, Error, HostPort, Stage, UrlError,
+    WaitError,
+};
+
+/// Longest pause between two polls of a health check
+#[cfg(any(
+    feature = "preset-elasticsearch",
+    feature = "preset-localstack",
+    feature = "preset-minio",
+    feature = "preset-rabbitmq"
+))]
+const MAX_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Client of the server on `host:port`, leaving the retries to the callers.
+fn client(host: &str, port: u16) -> HttpClient {
+    HttpClient::new(format!("http://{host}:{port}")).warmup(Duration::ZERO)
+}
+
+/// POST `body` to `target`, e.g. `/subjects?x=y`, of the server on `host:port`, returning
+/// the body of a successful response.
+#[cfg(any(feature = "preset-clickhouse", feature = "preset-kafka"))]
+pub(super) async fn post(
+    host: &str,
+    port: u16,
+    target: &str,
+    headers: &[(&str, &str)],
+    body: &[u8],
+) -> io::Result<String> {
+    let client = client(host, port);
+    let request = headers.iter().fold(
+        client.request(Method::POST, target).body(body.to_vec()),
+        |request, (name, value)| request.header(*name, *value),
+    );
+    send(&client, request).await
+}
+
+/// GET `target` of the container port `port` of `handle` until the server answers it
+/// successfully with a body `ready` accepts, for at most `timeout`, returning the host port
+/// the container port is published on. Requests authenticate with `credentials`, if any.
+#[cfg(any(
+    feature = "preset-elasticsearch",
+    feature = "preset-localstack",
+    feature = "preset-minio",
+    feature = "preset-rabbitmq"
+))]
+pub(super) async fn wait_until_ok<F>(
+    handle: &ContainerHandle,
+    port: u16,
+    target: &str,
+    credentials: Option<&Credentials>,
+    timeout: Duration,
+    ready: F,
+) -> Result<HostPort, Error>
+where
+    F: Fn(&str) -> bool,
+{
+    let host_port = handle
+        .host_port(port)
+        .ok_or_else(|| handle.error(Stage::ResolveUrl, UrlError::UnboundPort(port.into())))?;
+    let client = &client(&handle.host_ip, host_port.0);
+    let ready = &ready;
+    // polled until the timeout, which bounds the attempts instead
+    let policy = BackoffPolicy::new()
+        .max_delay(MAX_POLL_INTERVAL)
+        .max_attempts(usize::MAX);
+    let poll = connect_with_backoff(client.url(target), &policy, |_| async move {
+        let mut request = client.request(Method::GET, target);
+        if let Some(credentials) = credentials {
+            request = request.basic_auth(&credentials.username, Some(&credentials.password));
+        }
+        let body = send(client, request).await?;
+        if ready(&body) {
+            Ok(())
+        } else {
+            Err(io::Error::other(format!("not ready yet: {body}")))
+        }
+    });
+    match tokio::time::timeout(timeout, poll).await {
+        Ok(Ok(())) => Ok(host_port),
+        Ok(Err(err)) => Err(handle.error(Stage::WaitReady, err)),
+        Err(_) => Err(handle.error(Stage::WaitReady, WaitError::TimedOut(timeout))),
+    }
+}
+
+/// Send `request` through `client`, returning the body of a successful response.
+async fn send(client: &HttpClient, request: RequestBuilder) -> io::Result<String> {
+    let response = client.send(request).await.map_err(io::Error::other)?;
+    let status = response.status();
+    let body = response.text().await.map_err(io::Error::other)?;
+    if status.is_success() {
+        Ok(body)
+    } else {
+        Err(io::Error::other(format!(
+            "server answered `{status}`: {body}"
+        )))
+    }
+}
+
+/// Answer the requests to a local port with `responses` in turn, returning the port and the
+/// task serving them, which returns the requests once all are answered.
+#[cfg(test)]
+pub(super) async fn serve(
+    responses: Vec<&'static str>,
+) -> (u16, tokio::task::JoinHandle<Vec<String>>) {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let server = tokio::spawn(async move {
+        let mut requests = Vec::new();
+        for response in responses {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = vec![0; 1024];
+            let len = stream.read(&mut request).await.unwrap();
+            stream.write_all(response.as_bytes()).await.unwrap();
+            requests.push(String::from_utf8_lossy(&request[..len]).into_owned());
+        }
+        requests
+    });
+    (port, server)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_send() {
+        let (port, server) = serve(vec!["HTTP/1.0 200 OK\r\n\r\nok"]).await;
+        let client = client("127.0.0.1", port);
+        let request = client.request(Method::GET, "/health").header("X-Key", "v");
+        assert_eq!(send(&client, request).await.unwrap(), "ok");
+        let sent = server.await.unwrap();
+        assert!(sent[0].starts_with("GET /health HTTP/1.1\r\n"));
+        assert!(sent[0].contains("x-key: v\r\n"));
+
+        let (port, _) = serve(vec!["HTTP/1.0 503 Service Unavailable\r\n\r\nstarting"]).await;
+        let unavailable = super::client("127.0.0.1", port);
+        let err = send(&unavailable, unavailable.request(Method::GET, "/health"))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("503"));
+    }
+
+    #[cfg(any(
+        feature = "preset-elasticsearch",
+        feature = "preset-localstack",
+        feature = "preset-minio",
+        feature = "preset-rabbitmq"
+    ))]
+    #[tokio::test]
+    async fn test_wait_until_ok_times_out() {
+        use crate::docker::mock::MockDocker;
+        use crate::docker::Builder;
+
+        let (port, _) = serve(vec!["HTTP/1.0 503 Service Unavailable\r\n\r\n"; 100]).await;
+        let docker = MockDocker::new().fixed_host_port(80, port);
+        let handle = Builder::new("nginx")
+            .bind_port_as_default(Some(HostPort::ANY), 80)
+            .backend(docker)
+            .build_disposable()
+            .await;
+        let err = wait_until_ok(
+            &handle,
+            80,
+            "/health",
+            None,
+            Duration::from_millis(300),
+            |_| true,
+        )
+        .await
+        .unwrap_err();
+
+        assert_eq!(err.stage(), Stage::WaitReady);
+        assert!(err.to_string().contains("nginx"));
+    }
+}
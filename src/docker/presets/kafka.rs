@@ -0,0 +1,554 @@
+use std::future::Future;
+use std::io;
+
+use super::http;
+use crate::docker::{connect, Backend, Builder, ContainerHandle, Error, Stage, WaitStrategy};
+
+const DEFAULT_IMAGE: &str = "apache/kafka:3.7.0";
+const DEFAULT_REDPANDA_IMAGE: &str = "docker.redpanda.com/redpandadata/redpanda:v23.3.10";
+const DEFAULT_SCHEMA_REGISTRY_IMAGE: &str = "confluentinc/cp-schema-registry:7.6.0";
+/// Listener advertised to the host, on the host port it is published on
+const EXTERNAL_PORT: u16 = 9094;
+/// Listener advertised to the containers sharing the network of the broker
+const INTERNAL_PORT: u16 = 9092;
+const SCHEMA_REGISTRY_PORT: u16 = 8081;
+/// Times the broker is started on newly picked host ports when another process takes them first
+const START_ATTEMPTS: usize = 3;
+
+/// A single-node Kafka broker in KRaft mode, optionally with a schema registry.
+///
+/// The broker advertises the host port it is published on, so clients on the host connect
+/// with `bootstrap_servers` as they would to a remote cluster:
+///
+/// ```no_run
+/// # async fn run() {
+/// use test_utilities::docker::presets::kafka;
+///
+/// let kafka = kafka().start().await.unwrap();
+/// kafka.create_topics(&["orders", "payments"]).await.unwrap();
+/// let bootstrap_servers = kafka.bootstrap_servers();
+/// # }
+/// ```
+pub fn kafka() -> Kafka {
+    Kafka {
+        flavor: Flavor::Apache,
+        image: DEFAULT_IMAGE.to_string(),
+        schema_registry: None,
+    }
+}
+
+/// A single-node Redpanda broker, which speaks the Kafka protocol, starts faster and serves
+/// a schema registry of its own.
+pub fn redpanda() -> Kafka {
+    Kafka {
+        flavor: Flavor::Redpanda,
+        image: DEFAULT_REDPANDA_IMAGE.to_string(),
+        schema_registry: None,
+    }
+}
+
+/// Distributions of the broker, which differ in how they are configured.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Flavor {
+    Apache,
+    Redpanda,
+}
+
+pub struct Kafka {
+    flavor: Flavor,
+    image: String,
+    /// Image of the schema registry, if one is started
+    schema_registry: Option<String>,
+}
+
+impl Kafka {
+    /// Image of the broker, `apache/kafka:3.7.0` by default, a `redpandadata/redpanda` one
+    /// for `redpanda`.
+    pub fn image<S: Into<String>>(mut self, image: S) -> Self {
+        self.image = image.into();
+        self
+    }
+
+    /// Start a Confluent schema registry next to the broker, sharing its network. Redpanda
+    /// serves its own registry instead.
+    pub fn schema_registry(mut self, schema_registry: bool) -> Self {
+        self.schema_registry = schema_registry.then(|| DEFAULT_SCHEMA_REGISTRY_IMAGE.to_string());
+        self
+    }
+
+    /// Image of the schema registry, `confluentinc/cp-schema-registry:7.6.0` by default.
+    pub fn schema_registry_image<S: Into<String>>(mut self, image: S) -> Self {
+        self.schema_registry = Some(image.into());
+        self
+    }
+
+    /// Start the broker, and the schema registry if enabled, on the local docker daemon.
+    pub async fn start(self) -> Result<KafkaFixture, Error> {
+        let docker = connect().map_err(|err| Error::new(Stage::Connect, err))?;
+        self.start_with(&docker).await
+    }
+
+    pub async fn start_with<B>(self, backend: &B) -> Result<KafkaFixture, Error>
+    where
+        B: Backend + Clone + 'static,
+    {
+        let this = &self;
+        let ports = if self.schema_registry.is_some() { 2 } else { 1 };
+        let (broker, ports) = on_free_ports(ports, |ports| async move {
+            let broker = this
+                .start_broker(backend, ports[0], ports.get(1).copied())
+                .await?;
+            Ok((broker, ports))
+        })
+        .await?;
+        let (broker_port, registry_port) = (ports[0], ports.get(1).copied());
+
+        let schema_registry = match (self.schema_registry, registry_port) {
+            (Some(_), Some(port)) if self.flavor == Flavor::Redpanda => {
+                Some(SchemaRegistry { handle: None, port })
+            }
+            (Some(image), Some(port)) => {
+                let mut builder = Builder::new(image)
+                    .envs([
+                        ("SCHEMA_REGISTRY_HOST_NAME", "localhost".to_string()),
+                        (
+                            "SCHEMA_REGISTRY_LISTENERS",
+                            format!("http://0.0.0.0:{SCHEMA_REGISTRY_PORT}"),
+                        ),
+                        (
+                            "SCHEMA_REGISTRY_KAFKASTORE_BOOTSTRAP_SERVERS",
+                            format!("PLAINTEXT://localhost:{INTERNAL_PORT}"),
+                        ),
+                    ])
+                    .wait_for(WaitStrategy::LogLine(
+                        "Server started, listening for requests".to_string(),
+                    ));
+                builder.host_config().network_mode =
+                    Some(format!("container:{}", broker.container_id));
+                let handle = builder
+                    .backend(backend.clone())
+                    .try_build_disposable()
+                    .await?;
+                Some(SchemaRegistry {
+                    handle: Some(handle),
+                    port,
+                })
+            }
+            _ => None,
+        };
+
+        Ok(KafkaFixture {
+            schema_registry,
+            broker,
+            broker_port,
+            flavor: self.flavor,
+        })
+    }
+
+    async fn start_broker<B>(
+        &self,
+        backend: &B,
+        broker_port: u16,
+        registry_port: Option<u16>,
+    ) -> Result<ContainerHandle, Error>
+    where
+        B: Backend + Clone + 'static,
+    {
+        let mut broker =
+            Builder::new(self.image.clone()).bind_port_as_default(Some(broker_port), EXTERNAL_PORT);
+        broker = match self.flavor {
+            Flavor::Apache => broker
+                .envs(broker_env(broker_port))
+                .wait_for(WaitStrategy::LogLine("Kafka Server started".to_string())),
+            Flavor::Redpanda => {
+                broker
+                    .cmd(redpanda_cmd(broker_port))
+                    .wait_for(WaitStrategy::LogLine(
+                        "Successfully started Redpanda!".to_string(),
+                    ))
+            }
+        };
+        // the registry shares the network of the broker, so its port is published by the broker
+        if let Some(port) = registry_port {
+            broker = broker.bind_port(Some(port), SCHEMA_REGISTRY_PORT);
+        }
+        broker.backend(backend.clone()).try_build_disposable().await
+    }
+}
+
+fn broker_env(broker_port: u16) -> Vec<(&'static str, String)> {
+    vec![
+        ("KAFKA_NODE_ID", "1".to_string()),
+        ("KAFKA_PROCESS_ROLES", "broker,controller".to_string()),
+        (
+            "KAFKA_LISTENERS",
+            format!("INTERNAL://:{INTERNAL_PORT},EXTERNAL://:{EXTERNAL_PORT},CONTROLLER://:9093"),
+        ),
+        (
+            "KAFKA_ADVERTISED_LISTENERS",
+            format!("INTERNAL://localhost:{INTERNAL_PORT},EXTERNAL://localhost:{broker_port}"),
+        ),
+        (
+            "KAFKA_LISTENER_SECURITY_PROTOCOL_MAP",
+            "INTERNAL:PLAINTEXT,EXTERNAL:PLAINTEXT,CONTROLLER:PLAINTEXT".to_string(),
+        ),
+        ("KAFKA_INTER_BROKER_LISTENER_NAME", "INTERNAL".to_string()),
+        ("KAFKA_CONTROLLER_LISTENER_NAMES", "CONTROLLER".to_string()),
+        (
+            "KAFKA_CONTROLLER_QUORUM_VOTERS",
+            "1@localhost:9093".to_string(),
+        ),
+        ("KAFKA_OFFSETS_TOPIC_REPLICATION_FACTOR", "1".to_string()),
+        (
+            "KAFKA_TRANSACTION_STATE_LOG_REPLICATION_FACTOR",
+            "1".to_string(),
+        ),
+        ("KAFKA_TRANSACTION_STATE_LOG_MIN_ISR", "1".to_string()),
+        ("KAFKA_GROUP_INITIAL_REBALANCE_DELAY_MS", "0".to_string()),
+    ]
+}
+
+fn redpanda_cmd(broker_port: u16) -> Vec<String> {
+    [
+        "redpanda",
+        "start",
+        "--mode",
+        "dev-container",
+        "--smp",
+        "1",
+        "--kafka-addr",
+        &format!("internal://0.0.0.0:{INTERNAL_PORT},external://0.0.0.0:{EXTERNAL_PORT}"),
+        "--advertise-kafka-addr",
+        &format!("internal://localhost:{INTERNAL_PORT},external://localhost:{broker_port}"),
+        "--schema-registry-addr",
+        &format!("0.0.0.0:{SCHEMA_REGISTRY_PORT}"),
+    ]
+    .map(str::to_string)
+    .to_vec()
+}
+
+/// The advertised listener must name the host port, so it is picked before the container is
+/// created rather than left to the daemon.
+fn free_port() -> Result<u16, Error> {
+    std::net::TcpListener::bind(("localhost", 0))
+        .and_then(|listener| listener.local_addr())
+        .map(|addr| addr.port())
+        .map_err(|err| Error::new(Stage::Connect, err))
+}
+
+/// Run `start` with `count` free host ports, picking others and running it again when another
+/// process takes one of them between its pick and its binding by the daemon.
+async fn on_free_ports<T, F, Fut>(count: usize, mut start: F) -> Result<T, Error>
+where
+    F: FnMut(Vec<u16>) -> Fut,
+    Fut: Future<Output = Result<T, Error>>,
+{
+    let mut attempt = 1;
+    loop {
+        let ports = (0..count).map(|_| free_port()).collect::<Result<_, _>>()?;
+        match start(ports).await {
+            Err(err) if attempt < START_ATTEMPTS && is_port_conflict(&err) => {
+                log::debug!("retrying on other ports: {err}");
+                attempt += 1;
+            }
+            started => return started,
+        }
+    }
+}
+
+/// Whether the daemon failed to publish a port taken by another container or process.
+fn is_port_conflict(err: &Error) -> bool {
+    let err = err.to_string();
+    err.contains("port is already allocated") || err.contains("address already in use")
+}
+
+/// Schema formats accepted by the schema registry.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SchemaType {
+    Avro,
+    Json,
+}
+
+impl SchemaType {
+    fn as_str(&self) -> &'static str {
+        match self {
+            SchemaType::Avro => "AVRO",
+            SchemaType::Json => "JSON",
+        }
+    }
+}
+
+struct SchemaRegistry {
+    /// Container of the registry, unless served by the broker
+    handle: Option<ContainerHandle>,
+    port: u16,
+}
+
+/// A running broker and its schema registry, both removed on drop.
+pub struct KafkaFixture {
+    // dropped before the broker whose network it shares
+    schema_registry: Option<SchemaRegistry>,
+    broker: ContainerHandle,
+    broker_port: u16,
+    flavor: Flavor,
+}
+
+impl KafkaFixture {
+    pub fn broker(&self) -> &ContainerHandle {
+        &self.broker
+    }
+
+    /// The `bootstrap.servers` of clients on the host.
+    pub fn bootstrap_servers(&self) -> String {
+        format!("localhost:{}", self.broker_port)
+    }
+
+    /// The container serving the schema registry, the broker itself for Redpanda.
+    pub fn schema_registry(&self) -> Option<&ContainerHandle> {
+        self.schema_registry
+            .as_ref()
+            .map(|registry| registry.handle.as_ref().unwrap_or(&self.broker))
+    }
+
+    pub fn schema_registry_url(&self) -> Option<String> {
+        let registry = self.schema_registry.as_ref()?;
+        let host = &self.schema_registry()?.host_ip;
+        Some(format!("http://{host}:{}", registry.port))
+    }
+
+    /// Register `schema` under `subject`, e.g. `orders-value`, returning its id.
+    pub async fn register_schema(
+        &self,
+        subject: &str,
+        schema_type: SchemaType,
+        schema: &str,
+    ) -> io::Result<u32> {
+        let registry = self.schema_registry.as_ref().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, "no schema registry is started")
+        })?;
+        let body = serde_json::json!({
+            "schemaType": schema_type.as_str(),
+            "schema": schema,
+        });
+        let path = format!("/subjects/{subject}/versions");
+        let headers = [("Content-Type", "application/vnd.schemaregistry.v1+json")];
+        let host = &self.schema_registry().unwrap().host_ip;
+        let body = body.to_string();
+        let response = http::post(host, registry.port, &path, &headers, body.as_bytes()).await?;
+        let response: serde_json::Value = serde_json::from_str(&response)?;
+        response["id"]
+            .as_u64()
+            .map(|id| id as u32)
+            .ok_or_else(|| io::Error::other(format!("unexpected response {response}")))
+    }
+
+    /// Create `topic` with `partitions` partitions, unless it exists already, with the CLI
+    /// shipped in the image of the broker.
+    pub async fn create_topic(&self, topic: &str, partitions: u32) -> io::Result<()> {
+        let partitions = partitions.to_string();
+        let bootstrap_server = format!("localhost:{INTERNAL_PORT}");
+        let cmd = match self.flavor {
+            Flavor::Apache => vec![
+                "/opt/kafka/bin/kafka-topics.sh",
+                "--bootstrap-server",
+                &bootstrap_server,
+                "--create",
+                "--if-not-exists",
+                "--topic",
+                topic,
+                "--partitions",
+                &partitions,
+                "--replication-factor",
+                "1",
+            ],
+            Flavor::Redpanda => vec![
+                "rpk",
+                "topic",
+                "create",
+                topic,
+                "--partitions",
+                &partitions,
+                "--replicas",
+                "1",
+            ],
+        };
+        let output = self
+            .broker
+            .exec(cmd.into_iter().map(str::to_string).collect::<Vec<_>>())
+            .await
+            .map_err(io::Error::other)?;
+        // rpk has no flag to ignore existing topics
+        if output.success() || output.output.contains("TOPIC_ALREADY_EXISTS") {
+            Ok(())
+        } else {
+            Err(io::Error::other(format!(
+                "failed to create topic {topic}: {}",
+                output.output.trim()
+            )))
+        }
+    }
+
+    /// Create each of `topics` with a single partition, e.g. the topics a test produces to.
+    pub async fn create_topics(&self, topics: &[&str]) -> io::Result<()> {
+        for topic in topics {
+            self.create_topic(topic, 1).await?;
+        }
+        Ok(())
+    }
+
+    pub async fn register_avro_schema(&self, subject: &str, schema: &str) -> io::Result<u32> {
+        self.register_schema(subject, SchemaType::Avro, schema)
+            .await
+    }
+
+    pub async fn register_json_schema(&self, subject: &str, schema: &str) -> io::Result<u32> {
+        self.register_schema(subject, SchemaType::Json, schema)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::docker::mock::MockDocker;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_retry_on_port_conflict() {
+        let mut attempts = Vec::new();
+        let ports = on_free_ports(2, |ports| {
+            attempts.push(ports.clone());
+            let conflict = attempts.len() == 1;
+            async move {
+                if conflict {
+                    let err = format!(
+                        "Bind for 0.0.0.0:{} failed: port is already allocated",
+                        ports[0]
+                    );
+                    Err(Error::new(Stage::Start, err))
+                } else {
+                    Ok(ports)
+                }
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(attempts.len(), 2);
+        assert_eq!(ports, attempts[1]);
+
+        let mut calls = 0;
+        let err = on_free_ports(1, |_| {
+            calls += 1;
+            async { Err::<(), _>(Error::new(Stage::Start, "port is already allocated")) }
+        })
+        .await
+        .unwrap_err();
+        assert_eq!(calls, START_ATTEMPTS);
+        assert_eq!(err.stage(), Stage::Start);
+
+        let mut calls = 0;
+        on_free_ports(1, |_| {
+            calls += 1;
+            async { Err::<(), _>(Error::new(Stage::Pull, "manifest unknown")) }
+        })
+        .await
+        .unwrap_err();
+        assert_eq!(calls, 1);
+    }
+
+    #[tokio::test]
+    async fn test_kafka_with_schema_registry() {
+        let docker = MockDocker::new()
+            .startup_log(DEFAULT_IMAGE, "[KafkaServer id=1] Kafka Server started")
+            .startup_log(
+                DEFAULT_SCHEMA_REGISTRY_IMAGE,
+                "Server started, listening for requests...",
+            );
+        let fixture = kafka()
+            .schema_registry(true)
+            .start_with(&docker)
+            .await
+            .unwrap();
+
+        let broker = fixture.broker();
+        let port = fixture.bootstrap_servers()["localhost:".len()..].to_string();
+        assert!(broker.env()["KAFKA_ADVERTISED_LISTENERS"].ends_with(&port));
+
+        let registry = fixture.schema_registry().unwrap();
+        let config = docker.config(&registry.container_id).unwrap();
+        assert_eq!(
+            config.host_config.unwrap().network_mode.unwrap(),
+            format!("container:{}", broker.container_id)
+        );
+        let registry_port =
+            fixture.schema_registry_url().unwrap()["http://localhost:".len()..].to_string();
+        let bindings = docker
+            .config(&broker.container_id)
+            .unwrap()
+            .host_config
+            .unwrap()
+            .port_bindings
+            .unwrap();
+        let binding = bindings["8081/tcp"].as_ref().unwrap();
+        assert_eq!(
+            binding[0].host_port.as_deref(),
+            Some(registry_port.as_str())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_kafka_without_schema_registry() {
+        let docker = MockDocker::new().startup_log(DEFAULT_IMAGE, "Kafka Server started");
+        let fixture = kafka().start_with(&docker).await.unwrap();
+
+        assert!(fixture.schema_registry().is_none());
+        fixture.create_topics(&["orders"]).await.unwrap();
+        let execs = docker.execs(&fixture.broker().container_id);
+        assert_eq!(execs.len(), 1);
+        assert_eq!(execs[0][0], "/opt/kafka/bin/kafka-topics.sh");
+        assert!(execs[0].contains(&"orders".to_string()));
+        let err = fixture
+            .register_avro_schema("orders-value", r#"{"type": "string"}"#)
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_redpanda() {
+        let docker =
+            MockDocker::new().startup_log(DEFAULT_REDPANDA_IMAGE, "Successfully started Redpanda!");
+        let fixture = redpanda()
+            .schema_registry(true)
+            .start_with(&docker)
+            .await
+            .unwrap();
+
+        let broker = fixture.broker();
+        let cmd = docker.config(&broker.container_id).unwrap().cmd.unwrap();
+        let port = &fixture.bootstrap_servers()["localhost:".len()..];
+        assert!(cmd
+            .iter()
+            .any(|arg| arg.ends_with(&format!("external://localhost:{port}"))));
+        assert_eq!(
+            fixture.schema_registry().unwrap().container_id,
+            broker.container_id
+        );
+        assert_eq!(docker.containers().len(), 1);
+
+        fixture.create_topic("orders", 3).await.unwrap();
+        assert_eq!(
+            docker.execs(&broker.container_id),
+            [[
+                "rpk",
+                "topic",
+                "create",
+                "orders",
+                "--partitions",
+                "3",
+                "--replicas",
+                "1"
+            ]]
+        );
+    }
+}
@@ -0,0 +1,239 @@
+use std::time::Duration;
+
+use futures::future::BoxFuture;
+
+use super::http;
+use crate::docker::{
+    connect, Backend, Builder, ContainerHandle, Error, HostPort, Stage, DEFAULT_WAIT_TIMEOUT,
+};
+use crate::fixture::Fixture;
+
+const DEFAULT_IMAGE: &str = "localstack/localstack:3.4";
+/// Edge port, serving the APIs of every service
+const PORT: u16 = 4566;
+const HEALTH_CHECK: &str = "/_localstack/health";
+/// Region Localstack answers for unless configured otherwise
+const REGION: &str = "us-east-1";
+
+/// Localstack emulating the requested AWS services, started as a `LocalstackFixture` once
+/// each of them is available:
+///
+/// ```no_run
+/// # async fn run() {
+/// use test_utilities::docker::presets::{localstack, AwsService};
+///
+/// let localstack = localstack()
+///     .services([AwsService::S3, AwsService::Sqs])
+///     .start()
+///     .await
+///     .unwrap();
+/// // e.g. `Some("http://127.0.0.1:32768")`
+/// let s3_endpoint_url = localstack.s3_endpoint_url();
+/// # }
+/// ```
+pub fn localstack() -> Localstack {
+    Localstack {
+        image: DEFAULT_IMAGE.to_string(),
+        services: Vec::new(),
+        ready_timeout: DEFAULT_WAIT_TIMEOUT,
+    }
+}
+
+/// Services of Localstack the preset knows to enable.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AwsService {
+    S3,
+    Sqs,
+    DynamoDb,
+}
+
+impl AwsService {
+    /// Name of the service in the config and health of Localstack.
+    fn as_str(&self) -> &'static str {
+        match self {
+            AwsService::S3 => "s3",
+            AwsService::Sqs => "sqs",
+            AwsService::DynamoDb => "dynamodb",
+        }
+    }
+}
+
+pub struct Localstack {
+    image: String,
+    services: Vec<AwsService>,
+    ready_timeout: Duration,
+}
+
+impl Localstack {
+    /// Image of Localstack, `localstack/localstack:3.4` by default.
+    pub fn image<S: Into<String>>(mut self, image: S) -> Self {
+        self.image = image.into();
+        self
+    }
+
+    /// Services to enable and wait for. Without any, every service is enabled, each started
+    /// on its first request, and only Localstack itself is waited for.
+    pub fn services<I: IntoIterator<Item = AwsService>>(mut self, services: I) -> Self {
+        for service in services {
+            if !self.services.contains(&service) {
+                self.services.push(service);
+            }
+        }
+        self
+    }
+
+    /// Time the services have to be available once started.
+    pub fn ready_timeout(mut self, timeout: Duration) -> Self {
+        self.ready_timeout = timeout;
+        self
+    }
+
+    /// The builder of Localstack, whose url is the one of the edge port. It does not wait for
+    /// the services to be available.
+    pub fn builder(&self) -> Builder {
+        let builder = Builder::new(self.image.as_str())
+            .protocol("http")
+            .bind_port_as_default(Some(HostPort::ANY), PORT);
+        if self.services.is_empty() {
+            return builder;
+        }
+        let services: Vec<_> = self.services.iter().map(AwsService::as_str).collect();
+        builder.env("SERVICES", services.join(","))
+    }
+
+    /// Start Localstack on the local docker daemon.
+    pub async fn start(self) -> Result<LocalstackFixture, Error> {
+        let docker = connect().map_err(|err| Error::new(Stage::Connect, err))?;
+        self.start_with(&docker).await
+    }
+
+    pub async fn start_with<B>(self, backend: &B) -> Result<LocalstackFixture, Error>
+    where
+        B: Backend + Clone + 'static,
+    {
+        let handle = self
+            .builder()
+            .backend(backend.clone())
+            .try_build_disposable()
+            .await?;
+        let port = http::wait_until_ok(
+            &handle,
+            PORT,
+            HEALTH_CHECK,
+            None,
+            self.ready_timeout,
+            |health| all_available(health, &self.services),
+        )
+        .await?;
+        Ok(LocalstackFixture {
+            handle,
+            port,
+            services: self.services,
+        })
+    }
+}
+
+/// A running Localstack, whose services accept any credentials.
+pub struct LocalstackFixture {
+    handle: ContainerHandle,
+    /// Host port the edge port is published on
+    port: HostPort,
+    /// Services enabled explicitly, all of them if empty
+    services: Vec<AwsService>,
+}
+
+impl LocalstackFixture {
+    pub fn handle(&self) -> &ContainerHandle {
+        &self.handle
+    }
+
+    /// Region to sign requests for.
+    pub fn region(&self) -> &str {
+        REGION
+    }
+
+    /// Url to point the client of `service` to, like `http://127.0.0.1:32768`, if it is
+    /// enabled.
+    pub fn endpoint_url(&self, service: AwsService) -> Option<String> {
+        if !self.services.is_empty() && !self.services.contains(&service) {
+            return None;
+        }
+        Some(format!("http://{}:{}", self.handle.host_ip, self.port))
+    }
+
+    pub fn s3_endpoint_url(&self) -> Option<String> {
+        self.endpoint_url(AwsService::S3)
+    }
+
+    pub fn sqs_endpoint_url(&self) -> Option<String> {
+        self.endpoint_url(AwsService::Sqs)
+    }
+
+    pub fn dynamodb_endpoint_url(&self) -> Option<String> {
+        self.endpoint_url(AwsService::DynamoDb)
+    }
+}
+
+impl Fixture for LocalstackFixture {
+    fn teardown(self: Box<Self>) -> BoxFuture<'static, crate::Result<()>> {
+        Box::new(self.handle).teardown()
+    }
+}
+
+/// Whether the health report of Localstack, like `{"services": {"s3": "available"}}`, tells
+/// each of `services` available or already running.
+fn all_available(health: &str, services: &[AwsService]) -> bool {
+    let Ok(health) = serde_json::from_str::<serde_json::Value>(health) else {
+        return false;
+    };
+    services.iter().all(|service| {
+        matches!(
+            health["services"][service.as_str()].as_str(),
+            Some("available" | "running")
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::docker::mock::MockDocker;
+
+    use super::*;
+
+    #[test]
+    fn test_all_available() {
+        let health = r#"{"services": {"s3": "running", "sqs": "available", "dynamodb": "initialized"}, "edition": "community"}"#;
+        assert!(all_available(health, &[]));
+        assert!(all_available(health, &[AwsService::S3, AwsService::Sqs]));
+        assert!(!all_available(
+            health,
+            &[AwsService::S3, AwsService::DynamoDb]
+        ));
+        assert!(!all_available("{}", &[AwsService::S3]));
+        assert!(!all_available("not json", &[]));
+    }
+
+    #[tokio::test]
+    async fn test_localstack_waits_for_services() {
+        let (port, server) = http::serve(vec![
+            "HTTP/1.0 503 Service Unavailable\r\n\r\n",
+            "HTTP/1.0 200 OK\r\n\r\n{\"services\": {\"s3\": \"available\"}}",
+            "HTTP/1.0 200 OK\r\n\r\n{\"services\": {\"s3\": \"running\", \"sqs\": \"available\"}}",
+        ])
+        .await;
+        let docker = MockDocker::new().fixed_host_port(PORT, port);
+        let fixture = localstack()
+            .services([AwsService::S3, AwsService::Sqs, AwsService::S3])
+            .start_with(&docker)
+            .await
+            .unwrap();
+
+        assert_eq!(server.await.unwrap().len(), 3);
+        let handle = fixture.handle();
+        assert_eq!(handle.env()["SERVICES"], "s3,sqs");
+        let endpoint_url = format!("http://{}:{port}", handle.host_ip);
+        assert_eq!(fixture.s3_endpoint_url(), Some(endpoint_url.clone()));
+        assert_eq!(fixture.sqs_endpoint_url(), Some(endpoint_url));
+        assert_eq!(fixture.dynamodb_endpoint_url(), None);
+    }
+}
@@ -0,0 +1,158 @@
+use std::time::Duration;
+
+use futures::future::BoxFuture;
+use mongodb::bson::doc;
+use mongodb::{Client, Database};
+
+use crate::docker::{
+    connect, Backend, Builder, ContainerHandle, Error, HostPort, Stage, WaitError, WaitStrategy,
+    DEFAULT_WAIT_TIMEOUT,
+};
+use crate::fixture::Fixture;
+
+const DEFAULT_IMAGE: &str = "mongo:7";
+const MONGO_PORT: u16 = 27017;
+const PING_INTERVAL: Duration = Duration::from_millis(200);
+
+/// A mongo server, started as a `MongoFixture` with a client ready to use:
+///
+/// ```no_run
+/// # async fn run() {
+/// use test_utilities::docker::presets::mongo;
+///
+/// let mongo = mongo().start().await.unwrap();
+/// let users = mongo.database("testdb").collection::<mongodb::bson::Document>("users");
+/// # }
+/// ```
+pub fn mongo() -> Mongo {
+    Mongo {
+        image: DEFAULT_IMAGE.to_string(),
+        ready_timeout: DEFAULT_WAIT_TIMEOUT,
+    }
+}
+
+pub struct Mongo {
+    image: String,
+    ready_timeout: Duration,
+}
+
+impl Mongo {
+    /// Image of the server, `mongo:7` by default.
+    pub fn image<S: Into<String>>(mut self, image: S) -> Self {
+        self.image = image.into();
+        self
+    }
+
+    /// Time the server has to answer `ping` once started.
+    pub fn ready_timeout(mut self, timeout: Duration) -> Self {
+        self.ready_timeout = timeout;
+        self
+    }
+
+    pub fn builder(&self) -> Builder {
+        Builder::new(self.image.as_str())
+            .protocol("mongodb")
+            .bind_port_as_default(Some(HostPort::ANY), MONGO_PORT)
+            .wait_for(WaitStrategy::PortOpen(MONGO_PORT.into()))
+    }
+
+    /// Start the server on the local docker daemon.
+    pub async fn start(self) -> Result<MongoFixture, Error> {
+        let docker = connect().map_err(|err| Error::new(Stage::Connect, err))?;
+        self.start_with(&docker).await
+    }
+
+    pub async fn start_with<B>(self, backend: &B) -> Result<MongoFixture, Error>
+    where
+        B: Backend + Clone + 'static,
+    {
+        let handle = self
+            .builder()
+            .backend(backend.clone())
+            .try_build_disposable()
+            .await?;
+        let url = handle
+            .url()
+            .map_err(|err| Error::new(Stage::ResolveUrl, err))?;
+        let client = Client::with_uri_str(url)
+            .await
+            .map_err(|err| Error::new(Stage::WaitReady, err))?;
+        ping_until_ready(&client, self.ready_timeout).await?;
+        Ok(MongoFixture { handle, client })
+    }
+}
+
+/// The port accepts connections before the server answers, so it is pinged until it does.
+async fn ping_until_ready(client: &Client, timeout: Duration) -> Result<(), Error> {
+    let ping = async {
+        loop {
+            let pinged = client
+                .database("admin")
+                .run_command(doc! { "ping": 1 }, None)
+                .await;
+            match pinged {
+                Ok(_) => return,
+                Err(err) => {
+                    log::debug!("mongo did not answer ping yet: {err}");
+                    tokio::time::sleep(PING_INTERVAL).await;
+                }
+            }
+        }
+    };
+    tokio::time::timeout(timeout, ping)
+        .await
+        .map_err(|_| Error::new(Stage::WaitReady, WaitError::TimedOut(timeout)))
+}
+
+/// A running mongo server and a client connected to it.
+pub struct MongoFixture {
+    client: Client,
+    handle: ContainerHandle,
+}
+
+impl MongoFixture {
+    pub fn handle(&self) -> &ContainerHandle {
+        &self.handle
+    }
+
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    pub fn database(&self, name: &str) -> Database {
+        self.client.database(name)
+    }
+
+    /// Url of the server, for code under test which connects on its own.
+    pub fn url(&self) -> String {
+        // resolved once already when starting
+        self.handle.url().unwrap()
+    }
+}
+
+impl Fixture for MongoFixture {
+    fn teardown(self: Box<Self>) -> BoxFuture<'static, crate::Result<()>> {
+        Box::new(self.handle).teardown()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use mongodb::bson::Document;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_mongo_fixture() {
+        let mongo = mongo().start().await.unwrap();
+        let users = mongo.database("testdb").collection::<Document>("users");
+        users
+            .insert_one(doc! { "name": "alice" }, None)
+            .await
+            .unwrap();
+
+        let count = users.count_documents(None, None).await.unwrap();
+        assert_eq!(count, 1);
+        assert!(mongo.url().starts_with("mongodb://"));
+    }
+}
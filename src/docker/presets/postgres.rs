@@ -0,0 +1,358 @@
+use std::time::Duration;
+
+use futures::future::BoxFuture;
+use tokio_postgres::{Client, Config, NoTls};
+
+use crate::docker::creds::{self, Credentials};
+use crate::docker::{
+    connect, Backend, Builder, ContainerHandle, Error, HostPort, Stage, WaitStrategy,
+};
+use crate::fixture::Fixture;
+
+use super::url::encode;
+
+const DEFAULT_IMAGE: &str = "postgres:16";
+const POSTGRES_PORT: u16 = 5432;
+const HEALTHCHECK_INTERVAL: Duration = Duration::from_millis(500);
+
+/// A postgres server with random credentials, optionally set up for logical replication.
+pub fn postgres() -> Postgres {
+    Postgres {
+        image: DEFAULT_IMAGE.to_string(),
+        credentials: creds::random(),
+        logical_replication: false,
+    }
+}
+
+pub struct Postgres {
+    image: String,
+    credentials: Credentials,
+    logical_replication: bool,
+}
+
+impl Postgres {
+    /// Image of the server, `postgres:16` by default.
+    pub fn image<S: Into<String>>(mut self, image: S) -> Self {
+        self.image = image.into();
+        self
+    }
+
+    pub fn credentials(mut self, credentials: Credentials) -> Self {
+        self.credentials = credentials;
+        self
+    }
+
+    /// Name of the superuser, random by default.
+    pub fn user<S: Into<String>>(mut self, user: S) -> Self {
+        self.credentials.username = user.into();
+        self
+    }
+
+    /// Password of the superuser, random by default.
+    pub fn password<S: Into<String>>(mut self, password: S) -> Self {
+        self.credentials.password = password.into();
+        self
+    }
+
+    /// Name of the database created on startup, random by default.
+    pub fn database<S: Into<String>>(mut self, database: S) -> Self {
+        self.credentials.database = database.into();
+        self
+    }
+
+    /// Start the server with `wal_level=logical`, which cannot be changed without a restart,
+    /// so that replication slots and publications can be created on the handle.
+    pub fn logical_replication(mut self, logical_replication: bool) -> Self {
+        self.logical_replication = logical_replication;
+        self
+    }
+
+    pub fn builder(self) -> Builder {
+        let mut cmd = vec!["postgres".to_string()];
+        if self.logical_replication {
+            cmd.extend(["-c".to_string(), "wal_level=logical".to_string()]);
+        }
+        Builder::new(self.image)
+            .protocol("postgres")
+            .bind_port_as_default(Some(HostPort::ANY), POSTGRES_PORT)
+            .envs([
+                ("POSTGRES_USER", self.credentials.username.as_str()),
+                ("POSTGRES_PASSWORD", self.credentials.password.as_str()),
+                ("POSTGRES_DB", self.credentials.database.as_str()),
+            ])
+            .credentials(self.credentials)
+            .cmd(cmd)
+            // the server started by the init scripts does not listen on tcp, unlike the final one
+            .healthcheck(
+                vec!["pg_isready", "-h", "127.0.0.1"],
+                HEALTHCHECK_INTERVAL,
+                120,
+            )
+            .wait_for(WaitStrategy::Healthy)
+    }
+
+    /// Start the server on the local docker daemon, once `pg_isready` passes.
+    pub async fn start(self) -> Result<PostgresFixture, Error> {
+        let docker = connect().map_err(|err| Error::new(Stage::Connect, err))?;
+        self.start_with(&docker).await
+    }
+
+    pub async fn start_with<B>(self, backend: &B) -> Result<PostgresFixture, Error>
+    where
+        B: Backend + Clone + 'static,
+    {
+        let handle = self
+            .builder()
+            .backend(backend.clone())
+            .try_build_disposable()
+            .await?;
+        Ok(PostgresFixture { handle })
+    }
+}
+
+/// A running postgres server, with the credentials it was set up with.
+pub struct PostgresFixture {
+    handle: ContainerHandle,
+}
+
+impl PostgresFixture {
+    pub fn handle(&self) -> &ContainerHandle {
+        &self.handle
+    }
+
+    pub fn credentials(&self) -> &Credentials {
+        // always recorded by the preset
+        self.handle.credentials().unwrap()
+    }
+
+    /// Url of the database with the credentials of the superuser, e.g. for `DATABASE_URL`.
+    pub fn connection_string(&self) -> String {
+        let credentials = self.credentials();
+        let port = self
+            .handle
+            .default_host_port
+            .map_or(POSTGRES_PORT, |port| port.0);
+        format!(
+            "postgres://{}:{}@{}:{port}/{}",
+            encode(&credentials.username),
+            encode(&credentials.password),
+            self.handle.host_ip,
+            encode(&credentials.database)
+        )
+    }
+
+    /// Connect with `tokio_postgres`, driving the connection in the background.
+    pub async fn client(&self) -> Result<Client, tokio_postgres::Error> {
+        self.handle.connect().await
+    }
+
+    /// Connect a `sqlx` pool to the database.
+    #[cfg(feature = "postgres-sqlx")]
+    pub async fn pg_pool(&self) -> Result<sqlx::PgPool, sqlx::Error> {
+        sqlx::PgPool::connect(&self.connection_string()).await
+    }
+}
+
+impl Fixture for PostgresFixture {
+    fn teardown(self: Box<Self>) -> BoxFuture<'static, crate::Result<()>> {
+        Box::new(self.handle).teardown()
+    }
+}
+
+/// Helpers on the handle of a postgres container, for testing change data capture consumers.
+pub trait PostgresHandleExt {
+    /// Connection parameters of the server, with the credentials of the preset.
+    fn postgres_config(&self) -> Config;
+
+    /// Connect to the server, driving the connection in the background.
+    fn connect(&self) -> BoxFuture<'_, Result<Client, tokio_postgres::Error>>;
+
+    /// Publish the changes to `tables`, or to all the tables if empty.
+    fn create_publication<'a>(
+        &'a self,
+        name: &'a str,
+        tables: &'a [&'a str],
+    ) -> BoxFuture<'a, Result<(), tokio_postgres::Error>>;
+
+    /// Create a logical replication slot decoded by `plugin`, e.g. `pgoutput`, returning the
+    /// LSN from which it streams.
+    fn create_replication_slot<'a>(
+        &'a self,
+        name: &'a str,
+        plugin: &'a str,
+    ) -> BoxFuture<'a, Result<String, tokio_postgres::Error>>;
+
+    /// Drop a replication slot, which otherwise keeps the server from recycling its WAL.
+    fn drop_replication_slot<'a>(
+        &'a self,
+        name: &'a str,
+    ) -> BoxFuture<'a, Result<(), tokio_postgres::Error>>;
+}
+
+impl PostgresHandleExt for ContainerHandle {
+    fn postgres_config(&self) -> Config {
+        let mut config = Config::new();
+        config.host(&self.host_ip);
+        config.port(self.default_host_port.map_or(POSTGRES_PORT, |port| port.0));
+        if let Some(credentials) = self.credentials() {
+            config
+                .user(&credentials.username)
+                .password(&credentials.password)
+                .dbname(&credentials.database);
+        }
+        config
+    }
+
+    fn connect(&self) -> BoxFuture<'_, Result<Client, tokio_postgres::Error>> {
+        Box::pin(async move {
+            let (client, connection) = self.postgres_config().connect(NoTls).await?;
+            tokio::spawn(async move {
+                if let Err(err) = connection.await {
+                    log::warn!("postgres connection closed: {err}");
+                }
+            });
+            Ok(client)
+        })
+    }
+
+    fn create_publication<'a>(
+        &'a self,
+        name: &'a str,
+        tables: &'a [&'a str],
+    ) -> BoxFuture<'a, Result<(), tokio_postgres::Error>> {
+        Box::pin(async move {
+            let client = self.connect().await?;
+            client.batch_execute(&publication_sql(name, tables)).await
+        })
+    }
+
+    fn create_replication_slot<'a>(
+        &'a self,
+        name: &'a str,
+        plugin: &'a str,
+    ) -> BoxFuture<'a, Result<String, tokio_postgres::Error>> {
+        Box::pin(async move {
+            let client = self.connect().await?;
+            let row = client
+                .query_one(
+                    "SELECT lsn::text FROM pg_create_logical_replication_slot($1, $2)",
+                    &[&name, &plugin],
+                )
+                .await?;
+            Ok(row.get(0))
+        })
+    }
+
+    fn drop_replication_slot<'a>(
+        &'a self,
+        name: &'a str,
+    ) -> BoxFuture<'a, Result<(), tokio_postgres::Error>> {
+        Box::pin(async move {
+            let client = self.connect().await?;
+            client
+                .execute("SELECT pg_drop_replication_slot($1)", &[&name])
+                .await?;
+            Ok(())
+        })
+    }
+}
+
+/// Identifiers cannot be bound as parameters, so they are quoted instead.
+fn quote_ident(ident: &str) -> String {
+    format!("\"{}\"", ident.replace('"', "\"\""))
+}
+
+fn publication_sql(name: &str, tables: &[&str]) -> String {
+    let target = if tables.is_empty() {
+        "ALL TABLES".to_string()
+    } else {
+        let tables: Vec<_> = tables.iter().map(|table| quote_ident(table)).collect();
+        format!("TABLE {}", tables.join(", "))
+    };
+    format!("CREATE PUBLICATION {} FOR {target}", quote_ident(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::docker::mock::MockDocker;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_postgres_logical_replication_config() {
+        let docker = MockDocker::new();
+        let handle = postgres()
+            .logical_replication(true)
+            .builder()
+            .backend(docker.clone())
+            .build_disposable()
+            .await;
+
+        let config = docker.config(&handle.container_id).unwrap();
+        assert_eq!(config.cmd.unwrap(), ["postgres", "-c", "wal_level=logical"]);
+        let credentials = handle.credentials().unwrap();
+        assert_eq!(handle.env()["POSTGRES_USER"], credentials.username);
+        assert_eq!(
+            handle.postgres_config().get_dbname(),
+            Some(credentials.database.as_str())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_postgres_fixture() {
+        let docker = MockDocker::new();
+        let fixture = postgres()
+            .user("app")
+            .password("p@ss word")
+            .database("orders")
+            .start_with(&docker)
+            .await
+            .unwrap();
+
+        let env = fixture.handle().env();
+        assert_eq!(env["POSTGRES_USER"], "app");
+        assert_eq!(env["POSTGRES_DB"], "orders");
+        assert_eq!(
+            fixture.connection_string(),
+            format!(
+                "postgres://app:p%40ss%20word@{}:{}/orders",
+                fixture.handle().host_ip,
+                fixture.handle().default_host_port.unwrap().0
+            )
+        );
+    }
+
+    #[test]
+    fn test_publication_sql() {
+        assert_eq!(
+            publication_sql("cdc", &[]),
+            r#"CREATE PUBLICATION "cdc" FOR ALL TABLES"#
+        );
+        assert_eq!(
+            publication_sql("cdc", &["orders", "odd\"name"]),
+            r#"CREATE PUBLICATION "cdc" FOR TABLE "orders", "odd""name""#
+        );
+    }
+
+    #[tokio::test]
+    async fn test_replication_slot() {
+        let handle = postgres()
+            .logical_replication(true)
+            .builder()
+            .build_disposable()
+            .await;
+        let client = handle.connect().await.unwrap();
+        client
+            .batch_execute("CREATE TABLE orders (id INT PRIMARY KEY)")
+            .await
+            .unwrap();
+
+        handle.create_publication("cdc", &["orders"]).await.unwrap();
+        let lsn = handle
+            .create_replication_slot("cdc_slot", "pgoutput")
+            .await
+            .unwrap();
+        assert!(lsn.contains('/'));
+        handle.drop_replication_slot("cdc_slot").await.unwrap();
+    }
+}
@@ -0,0 +1,180 @@
+use std::path::PathBuf;
+
+use crate::docker::{BindOpts, Builder, HostPort};
+
+/// Environment variable selecting the mode of `RecordingProxy`, either `record` or `replay`
+pub const PROXY_MODE_ENV: &str = "TEST_UTILITIES_PROXY_MODE";
+
+const DEFAULT_IMAGE: &str = "mitmproxy/mitmproxy:10.1.0";
+const PROXY_PORT: u16 = 8080;
+const CONTAINER_CASSETTE_DIR: &str = "/cassettes";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProxyMode {
+    /// Forward requests to the upstream and save the exchanges to the cassette
+    Record,
+    /// Answer requests from the cassette, failing those which were never recorded
+    Replay,
+}
+
+impl ProxyMode {
+    /// Read the mode from `TEST_UTILITIES_PROXY_MODE`, replaying by default.
+    pub fn from_env() -> Self {
+        match std::env::var(PROXY_MODE_ENV) {
+            Ok(mode) if mode.eq_ignore_ascii_case("record") => ProxyMode::Record,
+            _ => ProxyMode::Replay,
+        }
+    }
+}
+
+/// A reverse proxy, run by mitmproxy, which records the traffic to a real upstream once and
+/// replays it in later runs, so that tests do not depend on the upstream being reachable.
+pub struct RecordingProxy {
+    upstream: String,
+    cassette_dir: PathBuf,
+    cassette: String,
+    mode: ProxyMode,
+    image: String,
+}
+
+impl RecordingProxy {
+    /// Proxy `upstream`, e.g. `https://api.example.com`, keeping cassettes in `cassette_dir`.
+    ///
+    /// The mode is taken from the environment, see `ProxyMode::from_env`.
+    pub fn new<S: Into<String>, P: Into<PathBuf>>(upstream: S, cassette_dir: P) -> Self {
+        RecordingProxy {
+            upstream: upstream.into(),
+            cassette_dir: cassette_dir.into(),
+            cassette: "flows".to_string(),
+            mode: ProxyMode::from_env(),
+            image: DEFAULT_IMAGE.to_string(),
+        }
+    }
+
+    /// Name of the cassette file in the cassette directory, `flows` by default.
+    pub fn cassette<S: Into<String>>(mut self, cassette: S) -> Self {
+        self.cassette = cassette.into();
+        self
+    }
+
+    pub fn mode(mut self, mode: ProxyMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    pub fn image<S: Into<String>>(mut self, image: S) -> Self {
+        self.image = image.into();
+        self
+    }
+
+    pub fn cassette_path(&self) -> PathBuf {
+        self.cassette_dir.join(&self.cassette)
+    }
+
+    /// The builder of the proxy container, whose url is the one to use in place of the
+    /// upstream's.
+    pub fn builder(self) -> Builder {
+        let cassette = format!("{CONTAINER_CASSETTE_DIR}/{}", self.cassette);
+        let mut cmd = vec![
+            "mitmdump".to_string(),
+            "--mode".to_string(),
+            format!("reverse:{}", self.upstream),
+            "--listen-port".to_string(),
+            PROXY_PORT.to_string(),
+        ];
+        let opts = match self.mode {
+            ProxyMode::Record => {
+                if let Err(err) = std::fs::create_dir_all(&self.cassette_dir) {
+                    log::warn!(
+                        "failed to create cassette dir {}: {err}",
+                        self.cassette_dir.display()
+                    );
+                }
+                cmd.extend(["--save-stream-file".to_string(), cassette]);
+                BindOpts {
+                    open_permissions: true,
+                    ..Default::default()
+                }
+            }
+            ProxyMode::Replay => {
+                cmd.extend([
+                    "--server-replay".to_string(),
+                    cassette,
+                    "--set".to_string(),
+                    "server_replay_extra=kill".to_string(),
+                    "--set".to_string(),
+                    "server_replay_reuse=true".to_string(),
+                ]);
+                BindOpts {
+                    read_only: true,
+                    ..Default::default()
+                }
+            }
+        };
+
+        let bind = format!(
+            "{}:{CONTAINER_CASSETTE_DIR}",
+            std::path::absolute(&self.cassette_dir)
+                .unwrap_or_else(|_| self.cassette_dir.clone())
+                .display()
+        );
+        Builder::new(self.image)
+            .protocol("http")
+            .bind_port_as_default(Some(HostPort::ANY), PROXY_PORT)
+            .bind_volume_opts(bind, opts)
+            .cmd(cmd)
+    }
+
+    /// The builder of a proxy recording into a fresh temporary cassette directory, which is
+    /// removed when the returned `TempDir` is dropped.
+    #[cfg(feature = "fs")]
+    pub fn record_to_temp_dir<S: Into<String>>(upstream: S) -> (Builder, tempfile::TempDir) {
+        let dir = crate::fs::temp_dir();
+        let builder = RecordingProxy::new(upstream, dir.path())
+            .mode(ProxyMode::Record)
+            .builder();
+        (builder, dir)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::docker::mock::MockDocker;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_replay_proxy_mounts_cassettes_read_only() {
+        let docker = MockDocker::new();
+        let handle = RecordingProxy::new("https://example.com", "/data/cassettes")
+            .mode(ProxyMode::Replay)
+            .builder()
+            .backend(docker.clone())
+            .build_disposable()
+            .await;
+
+        let config = docker.config(&handle.container_id).unwrap();
+        let cmd = config.cmd.unwrap();
+        assert!(cmd.contains(&"reverse:https://example.com".to_string()));
+        assert!(cmd.contains(&"/cassettes/flows".to_string()));
+        let binds = config.host_config.unwrap().binds.unwrap();
+        assert_eq!(binds, vec!["/data/cassettes:/cassettes:ro".to_string()]);
+        assert!(handle.url().unwrap().starts_with("http://localhost:"));
+    }
+
+    #[cfg(feature = "fs")]
+    #[tokio::test]
+    async fn test_record_to_temp_dir() {
+        let docker = MockDocker::new();
+        let (builder, dir) = RecordingProxy::record_to_temp_dir("http://example.com");
+        let handle = builder.backend(docker.clone()).build_disposable().await;
+
+        let config = docker.config(&handle.container_id).unwrap();
+        assert!(config
+            .cmd
+            .unwrap()
+            .contains(&"--save-stream-file".to_string()));
+        let binds = config.host_config.unwrap().binds.unwrap();
+        assert_eq!(binds, vec![format!("{}:/cassettes", dir.path().display())]);
+    }
+}
@@ -0,0 +1,488 @@
+use std::time::Duration;
+
+use futures::future::BoxFuture;
+use futures::StreamExt;
+use redis::{Client, ErrorKind, RedisError, RedisResult};
+
+use crate::docker::creds::Credentials;
+use crate::docker::{
+    connect, Backend, Builder, ContainerHandle, Error, HostPort, Stage, WaitError, WaitStrategy,
+    DEFAULT_WAIT_TIMEOUT,
+};
+use crate::fixture::Fixture;
+
+use super::url::encode;
+
+const DEFAULT_IMAGE: &str = "redis:7";
+const REDIS_PORT: u16 = 6379;
+/// User `requirepass` sets the password of
+const DEFAULT_USER: &str = "default";
+const PING_INTERVAL: Duration = Duration::from_millis(200);
+/// Hash slots of a redis cluster, all served by the single node of the cluster mode
+const CLUSTER_SLOTS: (u16, u16) = (0, 16383);
+
+/// A redis server, optionally started with keyspace notifications enabled.
+///
+/// Started as a `RedisFixture`, it comes with a client ready to use:
+///
+/// ```no_run
+/// # async fn run() {
+/// use test_utilities::docker::presets::redis;
+///
+/// let redis = redis().password("secret").start().await.unwrap();
+/// let mut conn = redis.client().get_async_connection().await.unwrap();
+/// # }
+/// ```
+pub fn redis() -> Redis {
+    Redis {
+        image: DEFAULT_IMAGE.to_string(),
+        notify_keyspace_events: None,
+        password: None,
+        cluster: false,
+        args: Vec::new(),
+        ready_timeout: DEFAULT_WAIT_TIMEOUT,
+    }
+}
+
+pub struct Redis {
+    image: String,
+    notify_keyspace_events: Option<String>,
+    password: Option<String>,
+    cluster: bool,
+    args: Vec<String>,
+    ready_timeout: Duration,
+}
+
+impl Redis {
+    /// Image of the server, `redis:7` by default.
+    pub fn image<S: Into<String>>(mut self, image: S) -> Self {
+        self.image = image.into();
+        self
+    }
+
+    /// Enable keyspace notifications of the classes in `flags` from the start, e.g. `KEA`.
+    pub fn notify_keyspace_events<S: Into<String>>(mut self, flags: S) -> Self {
+        self.notify_keyspace_events = Some(flags.into());
+        self
+    }
+
+    /// Require clients to authenticate with `password`.
+    pub fn password<S: Into<String>>(mut self, password: S) -> Self {
+        self.password = Some(password.into());
+        self
+    }
+
+    /// Run the server in cluster mode, as a cluster of a single node serving all the hash
+    /// slots, for code which issues cluster commands.
+    ///
+    /// The node announces its address in the container, which cluster-aware clients on the
+    /// host may not reach; plain clients work as with a standalone server.
+    pub fn cluster(mut self, cluster: bool) -> Self {
+        self.cluster = cluster;
+        self
+    }
+
+    /// Time the server has to answer `PING`, and in cluster mode to serve all the slots, once
+    /// started.
+    pub fn ready_timeout(mut self, timeout: Duration) -> Self {
+        self.ready_timeout = timeout;
+        self
+    }
+
+    /// Extra argument of `redis-server`, e.g. `--appendonly yes` as two arguments.
+    pub fn arg<S: Into<String>>(mut self, arg: S) -> Self {
+        self.args.push(arg.into());
+        self
+    }
+
+    /// The builder of the server, which records the password, if any, as the credentials of
+    /// the handle for `RedisHandleExt` to authenticate with.
+    pub fn builder(&self) -> Builder {
+        let mut cmd = vec!["redis-server".to_string()];
+        if let Some(flags) = &self.notify_keyspace_events {
+            cmd.extend(["--notify-keyspace-events".to_string(), flags.clone()]);
+        }
+        if let Some(password) = &self.password {
+            cmd.extend(["--requirepass".to_string(), password.clone()]);
+        }
+        if self.cluster {
+            cmd.extend(["--cluster-enabled".to_string(), "yes".to_string()]);
+        }
+        cmd.extend(self.args.iter().cloned());
+        let builder = Builder::new(self.image.as_str())
+            .protocol("redis")
+            .bind_port_as_default(Some(HostPort::ANY), REDIS_PORT)
+            .cmd(cmd)
+            .wait_for(WaitStrategy::LogLine(
+                "Ready to accept connections".to_string(),
+            ));
+        match &self.password {
+            Some(password) => builder.credentials(Credentials {
+                username: DEFAULT_USER.to_string(),
+                password: password.clone(),
+                database: "0".to_string(),
+            }),
+            None => builder,
+        }
+    }
+
+    /// Start the server on the local docker daemon, once it answers `PING`.
+    pub async fn start(self) -> Result<RedisFixture, Error> {
+        let docker = connect().map_err(|err| Error::new(Stage::Connect, err))?;
+        self.start_with(&docker).await
+    }
+
+    pub async fn start_with<B>(self, backend: &B) -> Result<RedisFixture, Error>
+    where
+        B: Backend + Clone + 'static,
+    {
+        let handle = self
+            .builder()
+            .backend(backend.clone())
+            .try_build_disposable()
+            .await?;
+        let url = server_url(&handle)?;
+        let client =
+            Client::open(url.as_str()).map_err(|err| handle.error(Stage::ResolveUrl, err))?;
+        let ready = async {
+            ping_until_pong(&client).await;
+            if self.cluster {
+                assign_slots(&client).await;
+            }
+        };
+        tokio::time::timeout(self.ready_timeout, ready)
+            .await
+            .map_err(|_| Error::new(Stage::WaitReady, WaitError::TimedOut(self.ready_timeout)))?;
+        Ok(RedisFixture {
+            client,
+            url,
+            handle,
+        })
+    }
+}
+
+/// Url of the server in the container of `handle`, like `redis://:password@127.0.0.1:32768`,
+/// with the password recorded by the preset, if any.
+fn server_url(handle: &ContainerHandle) -> Result<String, Error> {
+    let url = handle.url()?;
+    let address = url.trim_start_matches("redis://").trim_end_matches('/');
+    Ok(match handle.credentials() {
+        Some(credentials) => format!("redis://:{}@{address}", encode(&credentials.password)),
+        None => format!("redis://{address}"),
+    })
+}
+
+/// The log line shows before the server accepts commands from the host, so it is pinged until
+/// it does.
+async fn ping_until_pong(client: &Client) {
+    loop {
+        let pinged = async {
+            let mut conn = client.get_async_connection().await?;
+            redis::cmd("PING").query_async::<_, String>(&mut conn).await
+        };
+        match pinged.await {
+            Ok(pong) if pong == "PONG" => return,
+            Ok(reply) => log::debug!("redis answered ping with {reply}"),
+            Err(err) => log::debug!("redis did not answer ping yet: {err}"),
+        }
+        tokio::time::sleep(PING_INTERVAL).await;
+    }
+}
+
+/// Assign all the hash slots to the node, then wait for the cluster to report its state ok.
+async fn assign_slots(client: &Client) {
+    loop {
+        let assigned = async {
+            let mut conn = client.get_async_connection().await?;
+            let info = redis::cmd("CLUSTER")
+                .arg("INFO")
+                .query_async::<_, String>(&mut conn)
+                .await?;
+            if info.contains("cluster_state:ok") {
+                return Ok(true);
+            }
+            if info.contains("cluster_slots_assigned:0") {
+                redis::cmd("CLUSTER")
+                    .arg("ADDSLOTSRANGE")
+                    .arg(CLUSTER_SLOTS.0)
+                    .arg(CLUSTER_SLOTS.1)
+                    .query_async::<_, ()>(&mut conn)
+                    .await?;
+            }
+            RedisResult::Ok(false)
+        };
+        match assigned.await {
+            Ok(true) => return,
+            Ok(false) => log::debug!("redis cluster is not ok yet"),
+            Err(err) => log::debug!("failed to assign the slots of the redis cluster: {err}"),
+        }
+        tokio::time::sleep(PING_INTERVAL).await;
+    }
+}
+
+/// A running redis server and a client authenticated to it.
+pub struct RedisFixture {
+    client: Client,
+    url: String,
+    handle: ContainerHandle,
+}
+
+impl RedisFixture {
+    pub fn handle(&self) -> &ContainerHandle {
+        &self.handle
+    }
+
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    /// Url of the server with the password, if any, for code under test which connects on its
+    /// own.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+}
+
+impl Fixture for RedisFixture {
+    fn teardown(self: Box<Self>) -> BoxFuture<'static, crate::Result<()>> {
+        Box::new(self.handle).teardown()
+    }
+}
+
+/// Helpers on the handle of a redis container, for asserting against pub/sub consumers.
+pub trait RedisHandleExt {
+    fn redis_client(&self) -> RedisResult<Client>;
+
+    /// Enable keyspace notifications of the classes in `flags` on the running server.
+    fn enable_keyspace_notifications<'a>(
+        &'a self,
+        flags: &'a str,
+    ) -> BoxFuture<'a, RedisResult<()>>;
+
+    /// Open a connection dedicated to subscriptions.
+    fn pubsub(&self) -> BoxFuture<'_, RedisResult<Subscription>>;
+}
+
+impl RedisHandleExt for ContainerHandle {
+    fn redis_client(&self) -> RedisResult<Client> {
+        let url = server_url(self).map_err(|err| {
+            RedisError::from((
+                ErrorKind::InvalidClientConfig,
+                "no url for the redis container",
+                err.to_string(),
+            ))
+        })?;
+        Client::open(url.as_str())
+    }
+
+    fn enable_keyspace_notifications<'a>(
+        &'a self,
+        flags: &'a str,
+    ) -> BoxFuture<'a, RedisResult<()>> {
+        Box::pin(async move {
+            let mut conn = self.redis_client()?.get_async_connection().await?;
+            redis::cmd("CONFIG")
+                .arg("SET")
+                .arg("notify-keyspace-events")
+                .arg(flags)
+                .query_async(&mut conn)
+                .await
+        })
+    }
+
+    fn pubsub(&self) -> BoxFuture<'_, RedisResult<Subscription>> {
+        Box::pin(async move {
+            let conn = self.redis_client()?.get_async_connection().await?;
+            Ok(Subscription {
+                pubsub: conn.into_pubsub(),
+            })
+        })
+    }
+}
+
+/// A message received on a subscribed channel.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Message {
+    pub channel: String,
+    /// The pattern the channel matched, for messages received through `psubscribe`
+    pub pattern: Option<String>,
+    pub payload: String,
+}
+
+impl Message {
+    fn from_msg(msg: redis::Msg) -> Self {
+        Message {
+            channel: msg.get_channel_name().to_string(),
+            pattern: msg.get_pattern().ok(),
+            payload: msg.get_payload().unwrap_or_default(),
+        }
+    }
+}
+
+/// A pub/sub connection collecting the messages of its channels.
+pub struct Subscription {
+    pubsub: redis::aio::PubSub,
+}
+
+impl Subscription {
+    pub async fn subscribe(&mut self, channel: &str) -> RedisResult<()> {
+        self.pubsub.subscribe(channel).await
+    }
+
+    /// Subscribe to the channels matching `pattern`, e.g. `__keyspace@0__:*`.
+    pub async fn psubscribe(&mut self, pattern: &str) -> RedisResult<()> {
+        self.pubsub.psubscribe(pattern).await
+    }
+
+    /// Wait for `count` messages, returning those received if `timeout` elapses first.
+    pub async fn collect(&mut self, count: usize, timeout: Duration) -> Vec<Message> {
+        let mut messages = Vec::new();
+        let mut stream = self.pubsub.on_message();
+        let _ = tokio::time::timeout(timeout, async {
+            while messages.len() < count {
+                match stream.next().await {
+                    Some(msg) => messages.push(Message::from_msg(msg)),
+                    None => break,
+                }
+            }
+        })
+        .await;
+        messages
+    }
+
+    /// The next message, or `None` if none arrives within `timeout`.
+    pub async fn next_message(&mut self, timeout: Duration) -> Option<Message> {
+        self.collect(1, timeout).await.pop()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::docker::mock::MockDocker;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_redis_command() {
+        let docker = MockDocker::new().startup_log(DEFAULT_IMAGE, "Ready to accept connections");
+        let handle = redis()
+            .notify_keyspace_events("KEA")
+            .password("secret")
+            .cluster(true)
+            .arg("--appendonly")
+            .arg("yes")
+            .builder()
+            .backend(docker.clone())
+            .build_disposable()
+            .await;
+
+        let config = docker.config(&handle.container_id).unwrap();
+        assert_eq!(
+            config.cmd.unwrap(),
+            [
+                "redis-server",
+                "--notify-keyspace-events",
+                "KEA",
+                "--requirepass",
+                "secret",
+                "--cluster-enabled",
+                "yes",
+                "--appendonly",
+                "yes"
+            ]
+        );
+        assert!(handle.url().unwrap().starts_with("redis://"));
+    }
+
+    #[tokio::test]
+    async fn test_redis_client_with_password() {
+        let docker = MockDocker::new().startup_log(DEFAULT_IMAGE, "Ready to accept connections");
+        let handle = redis()
+            .password("p@ss")
+            .builder()
+            .backend(docker.clone())
+            .build_disposable()
+            .await;
+
+        let client = handle.redis_client().unwrap();
+        let info = &client.get_connection_info().redis;
+        assert_eq!(info.password.as_deref(), Some("p@ss"));
+        assert_eq!(info.username, None);
+        assert_eq!(
+            server_url(&handle).unwrap(),
+            format!(
+                "redis://:p%40ss@{}:{}",
+                handle.host_ip,
+                handle.default_host_port.unwrap()
+            )
+        );
+
+        let handle = redis().builder().backend(docker).build_disposable().await;
+        let client = handle.redis_client().unwrap();
+        assert_eq!(client.get_connection_info().redis.password, None);
+    }
+
+    #[tokio::test]
+    async fn test_keyspace_notifications() {
+        let handle = redis().builder().build_disposable().await;
+        handle.enable_keyspace_notifications("KEA").await.unwrap();
+
+        let mut subscription = handle.pubsub().await.unwrap();
+        subscription.psubscribe("__keyspace@0__:*").await.unwrap();
+
+        let mut conn = handle
+            .redis_client()
+            .unwrap()
+            .get_async_connection()
+            .await
+            .unwrap();
+        redis::cmd("SET")
+            .arg("greeting")
+            .arg("hello")
+            .query_async::<_, ()>(&mut conn)
+            .await
+            .unwrap();
+
+        let message = subscription
+            .next_message(Duration::from_secs(5))
+            .await
+            .unwrap();
+        assert_eq!(message.channel, "__keyspace@0__:greeting");
+        assert_eq!(message.pattern.as_deref(), Some("__keyspace@0__:*"));
+        assert_eq!(message.payload, "set");
+    }
+
+    #[tokio::test]
+    async fn test_redis_fixture() {
+        let redis = redis()
+            .password("p@ss")
+            .cluster(true)
+            .start()
+            .await
+            .unwrap();
+        let mut conn = redis.client().get_async_connection().await.unwrap();
+        redis::cmd("SET")
+            .arg("greeting")
+            .arg("hello")
+            .query_async::<_, ()>(&mut conn)
+            .await
+            .unwrap();
+
+        let greeting: String = redis::cmd("GET")
+            .arg("greeting")
+            .query_async(&mut conn)
+            .await
+            .unwrap();
+        assert_eq!(greeting, "hello");
+        assert!(redis.url().starts_with("redis://:p%40ss@"));
+
+        // the helpers on the handle authenticate as well
+        redis
+            .handle()
+            .enable_keyspace_notifications("KEA")
+            .await
+            .unwrap();
+        let mut subscription = redis.handle().pubsub().await.unwrap();
+        subscription.subscribe("events").await.unwrap();
+    }
+}
@@ -0,0 +1,26 @@
+/// Percent-encode `component` for the userinfo, path or query string of a url.
+pub(super) fn encode(component: &str) -> String {
+    component
+        .bytes()
+        .map(|byte| match byte {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{byte:02X}"),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode() {
+        assert_eq!(
+            encode("INSERT INTO t FORMAT CSV"),
+            "INSERT%20INTO%20t%20FORMAT%20CSV"
+        );
+        assert_eq!(encode("a=b&c"), "a%3Db%26c");
+    }
+}
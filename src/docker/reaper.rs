@@ -0,0 +1,166 @@
+//! Opt-in removal of the containers of a test process which was killed before it could remove
+//! them, e.g. by `SIGKILL` or a CI timeout.
+//!
+//! Every container is labeled with the session of the process that created it. Once `enable`
+//! is called or `TEST_UTILITIES_REAPER` is set, a Ryuk sidecar container is started on the
+//! daemon along with the first container. It removes every container of the session once the
+//! connection the process keeps open to it closes, whichever way the process ends:
+//!
+//! ```no_run
+//! # async fn run() {
+//! use test_utilities::docker::{reaper, Builder};
+//!
+//! reaper::enable();
+//! let handle = Builder::new("mongo").build_disposable().await;
+//! # }
+//! ```
+
+use std::io;
+use std::net::TcpStream;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::sync::OnceCell;
+
+use super::{connect, Builder, Error, HostPort, Stage, WaitStrategy};
+
+/// Label of every container created by the crate, whose value is the session of the process
+pub const SESSION_LABEL: &str = "io.github.limoiie.test-utilities.session";
+
+/// Environment variable enabling the reaper when set to anything but `0`
+pub const REAPER_ENV: &str = "TEST_UTILITIES_REAPER";
+
+const REAPER_IMAGE: &str = "testcontainers/ryuk:0.7.0";
+const REAPER_PORT: u16 = 8080;
+/// Time the reaper has to acknowledge the session
+const REGISTER_TIMEOUT: Duration = Duration::from_secs(10);
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static SESSION_ID: OnceLock<String> = OnceLock::new();
+/// Connection to the reaper, kept open for the lifetime of the process, or why it failed
+static REAPER: OnceCell<Result<TcpStream, String>> = OnceCell::const_new();
+
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+        || std::env::var(REAPER_ENV).is_ok_and(|value| !value.is_empty() && value != "0")
+}
+
+/// Id of the session of the process, unique across the processes using the daemon.
+pub fn session_id() -> &'static str {
+    SESSION_ID.get_or_init(|| {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+        format!("{:x}-{nanos:x}", std::process::id())
+    })
+}
+
+/// Start the reaper of the session on the daemon `connect` talks to, unless already started.
+pub(crate) async fn ensure_started(host_ip: &str) -> Result<(), Error> {
+    // concurrent callers wait for the same start, boxed as it builds a container itself
+    let started = REAPER
+        .get_or_init(|| async {
+            Box::pin(start(host_ip))
+                .await
+                .map_err(|err| err.to_string())
+        })
+        .await;
+    match started {
+        Ok(_) => Ok(()),
+        Err(err) => Err(Error::new(
+            Stage::Create,
+            format!("failed to start the reaper: {err}"),
+        )),
+    }
+}
+
+async fn start(host_ip: &str) -> Result<TcpStream, Box<dyn std::error::Error + Send + Sync>> {
+    let docker = connect()?;
+    let mut builder = Builder::new(REAPER_IMAGE)
+        .bind_port_as_default(Some(HostPort::ANY), REAPER_PORT)
+        .bind_volume("/var/run/docker.sock:/var/run/docker.sock")
+        .configure(|config| {
+            config
+                .host_config
+                .get_or_insert_with(Default::default)
+                .privileged = Some(true)
+        })
+        .wait_for(WaitStrategy::PortOpen(REAPER_PORT.into()))
+        .docker(docker)
+        .host_ip(host_ip);
+    // not labeled with the session, so that it does not remove itself
+    builder.session = false;
+    let mut handle = builder.try_build_disposable().await?;
+    // the reaper removes itself once done, after the process is gone
+    handle.detached = true;
+
+    let port = handle
+        .default_host_port
+        .ok_or("the reaper port is not published")?;
+    let mut stream = tokio::net::TcpStream::connect((handle.host_ip.as_str(), port.0)).await?;
+    register(
+        &mut stream,
+        &format!("label={SESSION_LABEL}={}", session_id()),
+    )
+    .await?;
+    // the connection outlives the runtime of the test, so it leaves it
+    let stream = stream.into_std()?;
+    stream.set_nonblocking(false)?;
+    log::info!(
+        "started reaper {} for session {}",
+        handle.container_id,
+        session_id()
+    );
+    Ok(stream)
+}
+
+/// Ask the reaper on the other end of `stream` to remove what matches `filter` once the
+/// stream closes.
+async fn register(stream: &mut tokio::net::TcpStream, filter: &str) -> io::Result<()> {
+    stream.write_all(format!("{filter}\n").as_bytes()).await?;
+    let mut ack = String::new();
+    let mut reader = BufReader::new(stream);
+    tokio::time::timeout(REGISTER_TIMEOUT, reader.read_line(&mut ack))
+        .await
+        .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "the reaper did not reply"))??;
+    if ack.trim() != "ACK" {
+        return Err(io::Error::other(format!("unexpected reply {ack:?}")));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, Write};
+    use std::net::TcpListener;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_register() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let reaper = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut filter = String::new();
+            std::io::BufReader::new(&stream)
+                .read_line(&mut filter)
+                .unwrap();
+            (&stream).write_all(b"ACK\n").unwrap();
+            filter
+        });
+
+        let mut stream = tokio::net::TcpStream::connect(("127.0.0.1", port))
+            .await
+            .unwrap();
+        register(&mut stream, "label=session=1").await.unwrap();
+        assert_eq!(reaper.join().unwrap(), "label=session=1\n");
+    }
+}
@@ -0,0 +1,92 @@
+use std::fmt::Write;
+
+use bollard::container::Config;
+
+use super::{Backend, BackendResult};
+
+/// Label of the containers started with `Builder::reuse`, whose value is the key of their
+/// config, so that a builder configured the same way finds them.
+pub const REUSE_LABEL: &str = "io.github.limoiie.test-utilities.reuse";
+
+/// Key of what makes a container fit for reuse in `config`, stable across processes.
+pub(crate) fn key(config: &Config<String>) -> String {
+    let sorted = |entries: Option<Vec<String>>| {
+        let mut entries = entries.unwrap_or_default();
+        entries.sort();
+        entries
+    };
+    let host_config = config.host_config.as_ref();
+    let mut ports: Vec<_> = host_config
+        .and_then(|host_config| host_config.port_bindings.as_ref())
+        .into_iter()
+        .flatten()
+        .map(|(port, bindings)| {
+            let host_ports: Vec<_> = bindings
+                .iter()
+                .flatten()
+                .map(|binding| binding.host_port.clone().unwrap_or_default())
+                .collect();
+            format!("{port}->{}", host_ports.join(","))
+        })
+        .collect();
+    ports.sort();
+    let labels = config.labels.as_ref().map(|labels| {
+        labels
+            .iter()
+            .map(|(key, value)| format!("{key}={value}"))
+            .collect()
+    });
+
+    let mut description = String::new();
+    let _ = write!(
+        description,
+        "{:?}|{:?}|{:?}|{:?}|{:?}|{:?}|{:?}|{:?}",
+        config.image,
+        sorted(config.env.clone()),
+        config.cmd,
+        config.entrypoint,
+        sorted(labels),
+        ports,
+        sorted(host_config.and_then(|host_config| host_config.binds.clone())),
+        host_config.and_then(|host_config| host_config.network_mode.as_ref()),
+    );
+    format!("{:016x}", fnv1a(description.as_bytes()))
+}
+
+/// The id of a running container labeled with `key`, if any.
+pub(crate) async fn find<B: Backend + ?Sized>(
+    backend: &B,
+    key: &str,
+) -> BackendResult<Option<String>> {
+    let containers = backend
+        .list_containers(&format!("{REUSE_LABEL}={key}"))
+        .await?;
+    Ok(containers
+        .into_iter()
+        .filter(|container| container.state.as_deref() == Some("running"))
+        .find_map(|container| container.id))
+}
+
+/// 64-bit FNV-1a, which unlike the hasher of std is specified to stay the same.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_ignores_order() {
+        let config = |env: &[&str]| Config {
+            image: Some("mongo".to_string()),
+            env: Some(env.iter().map(ToString::to_string).collect()),
+            ..Default::default()
+        };
+
+        assert_eq!(key(&config(&["A=1", "B=2"])), key(&config(&["B=2", "A=1"])));
+        assert_ne!(key(&config(&["A=1"])), key(&config(&["A=2"])));
+    }
+}
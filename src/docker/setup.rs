@@ -0,0 +1,457 @@
+//! Fixtures shared by a whole test run, started once by a cargo-nextest setup script.
+//!
+//! The `test-utilities-setupd` binary starts the fixtures declared in a JSON file, records
+//! their descriptors in another one, and passes their coordinates to the tests through
+//! `NEXTEST_ENV`, as `<NAME>_URL`, `<NAME>_HOST` and `<NAME>_PORT`:
+//!
+//! ```toml
+//! # .config/nextest.toml
+//! [scripts.setup.fixtures]
+//! command = "test-utilities-setupd up fixtures.json target/fixtures.json"
+//!
+//! [[profile.default.scripts]]
+//! filter = "all()"
+//! setup = "fixtures"
+//! ```
+//!
+//! with `fixtures.json` like `{ "fixtures": [{ "name": "mongo", "image": "mongo:6", "port":
+//! 27017 }] }`. The fixtures outlive the setup script, so that they stay up for the whole run,
+//! and are removed by `test-utilities-setupd down target/fixtures.json` once it is over.
+//!
+//! The env of a fixture can refer to the values another fixture produces, i.e. its `url`,
+//! `host`, `port`, `username`, `password` and `database`, as `${<name>.<value>}`, e.g.
+//! `"DATABASE_URL": "${postgres.url}"`. Fixtures then start once those they refer to are ready.
+//! They share a network on which each is reachable by its name, so that the references resolve
+//! to the name and the container port, e.g. `postgres://postgres:5432/`, while the variables
+//! exported to the tests point at the ports bound on the host.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use super::graph::{self, OrderError};
+use super::network::{self, NetworkHandle};
+use super::{
+    connect, unique_name, Backend, Builder, ContainerHandle, Error, HostPort, Stage, WaitStrategy,
+};
+
+/// A fixture to start, as declared in the setup file.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FixtureSpec {
+    /// Name of the fixture, from which the container name and the variable prefix derive
+    pub name: String,
+    pub image: String,
+    /// Container port the url points to
+    #[serde(default)]
+    pub port: Option<u16>,
+    #[serde(default)]
+    pub protocol: Option<String>,
+    /// Variables of the container, whose values may refer to other fixtures
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    /// Line of output telling that the fixture is ready, awaited before its dependents start
+    #[serde(default)]
+    pub ready_log: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SetupConfig {
+    pub fixtures: Vec<FixtureSpec>,
+}
+
+/// A started fixture, as recorded for the tests and the teardown.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FixtureDescriptor {
+    pub name: String,
+    pub container_id: String,
+    pub image: String,
+    /// Variables exported to the tests
+    pub env: BTreeMap<String, String>,
+    /// Network shared by the fixtures, removed with them
+    #[serde(default)]
+    pub network: Option<String>,
+}
+
+impl SetupConfig {
+    pub fn from_file<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = std::fs::File::open(path)?;
+        Ok(serde_json::from_reader(file)?)
+    }
+}
+
+impl FixtureSpec {
+    pub fn builder(&self) -> Builder {
+        let mut builder = Builder::new(self.image.as_str()).name(unique_name(&self.name));
+        if let Some(protocol) = self.protocol.as_ref() {
+            builder = builder.protocol(protocol);
+        }
+        if let Some(port) = self.port {
+            builder = builder.bind_port_as_default(Some(HostPort::ANY), port);
+        }
+        if let Some(line) = self.ready_log.as_ref() {
+            builder = builder.wait_for(WaitStrategy::LogLine(line.clone()));
+        }
+        builder.envs(&self.env)
+    }
+
+    /// Names of the fixtures the env refers to.
+    pub fn dependencies(&self) -> BTreeSet<&str> {
+        self.env
+            .values()
+            .flat_map(|value| references(value))
+            .map(|(fixture, _)| fixture)
+            .collect()
+    }
+
+    /// Prefix of the exported variables, the name in upper snake case.
+    pub fn env_prefix(&self) -> String {
+        self.name
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() {
+                    c.to_ascii_uppercase()
+                } else {
+                    '_'
+                }
+            })
+            .collect()
+    }
+}
+
+/// Start `fixtures` on the local docker daemon and detach them, so that they outlive the
+/// process. If any fails to start, those already started are removed.
+pub async fn start(fixtures: &[FixtureSpec]) -> Result<Vec<FixtureDescriptor>, Error> {
+    let docker = connect().map_err(|err| Error::new(Stage::Connect, err))?;
+    start_with(&docker, fixtures).await
+}
+
+pub async fn start_with<B>(
+    backend: &B,
+    fixtures: &[FixtureSpec],
+) -> Result<Vec<FixtureDescriptor>, Error>
+where
+    B: Backend + Clone + 'static,
+{
+    let levels = start_order(fixtures).map_err(|err| Error::new(Stage::Validate, err))?;
+    // dropped after the containers, so that it is removed if any fails to start
+    let network = NetworkHandle::create_with(backend.clone(), unique_name("fixtures")).await?;
+    let mut values = HashMap::new();
+    let mut started = Vec::new();
+    // the fixtures of a level only depend on those of the previous ones
+    for level in levels {
+        let builders = level
+            .iter()
+            .map(|&i| {
+                let fixture = resolve(&fixtures[i], &values)
+                    .map_err(|err| Error::new(Stage::Validate, err))?;
+                Ok(fixture
+                    .builder()
+                    .backend(backend.clone())
+                    .network(network.name.as_str())
+                    .network_alias(fixture.name.as_str()))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        let handles =
+            futures::future::try_join_all(builders.into_iter().map(Builder::try_build_disposable))
+                .await?;
+        for (i, handle) in level.into_iter().zip(handles) {
+            values.insert(fixtures[i].name.as_str(), produced(&handle, &network.name));
+            started.push((i, handle));
+        }
+    }
+
+    started.sort_by_key(|(i, _)| *i);
+    let network = network.detach();
+    Ok(started
+        .into_iter()
+        .map(|(i, handle)| {
+            let fixture = &fixtures[i];
+            FixtureDescriptor {
+                name: fixture.name.clone(),
+                image: fixture.image.clone(),
+                env: handle.env_vars(fixture.env_prefix()).into_iter().collect(),
+                container_id: handle.detach(),
+                network: Some(network.clone()),
+            }
+        })
+        .collect())
+}
+
+/// A fixture graph which cannot be started.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum GraphError {
+    /// The env of `fixture` refers to a fixture which is not declared
+    UnknownFixture { fixture: String, reference: String },
+    /// The env of `fixture` refers to a value the other fixture does not produce
+    UnknownValue { fixture: String, reference: String },
+    /// The fixtures refer to each other
+    Cycle(Vec<String>),
+}
+
+impl fmt::Display for GraphError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GraphError::UnknownFixture { fixture, reference } => {
+                write!(
+                    f,
+                    "`{fixture}` refers to an unknown fixture in `{reference}`"
+                )
+            }
+            GraphError::UnknownValue { fixture, reference } => {
+                write!(f, "`{fixture}` refers to an unknown value in `{reference}`")
+            }
+            GraphError::Cycle(fixtures) => {
+                write!(f, "fixtures {} depend on each other", fixtures.join(", "))
+            }
+        }
+    }
+}
+
+impl std::error::Error for GraphError {}
+
+/// References `${fixture.value}` in `template`. Placeholders without a dot, such as `${HOME}`,
+/// are left alone.
+fn references(template: &str) -> Vec<(&str, &str)> {
+    let mut references = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find("${") {
+        rest = &rest[start + 2..];
+        let Some(end) = rest.find('}') else { break };
+        if let Some(reference) = rest[..end].rsplit_once('.') {
+            references.push(reference);
+        }
+        rest = &rest[end + 1..];
+    }
+    references
+}
+
+/// Indices of `fixtures` grouped in levels, each depending only on the previous ones.
+fn start_order(fixtures: &[FixtureSpec]) -> Result<Vec<Vec<usize>>, GraphError> {
+    let names: Vec<_> = fixtures
+        .iter()
+        .map(|fixture| fixture.name.as_str())
+        .collect();
+    graph::levels(&names, fixtures.iter().map(FixtureSpec::dependencies)).map_err(|err| match err {
+        OrderError::Unknown { node, dependency } => GraphError::UnknownFixture {
+            fixture: fixtures[node].name.clone(),
+            reference: dependency.to_string(),
+        },
+        OrderError::Cycle(nodes) => GraphError::Cycle(
+            nodes
+                .into_iter()
+                .map(|i| fixtures[i].name.clone())
+                .collect(),
+        ),
+    })
+}
+
+/// The values a fixture produces for the others, by lowercase name, e.g. `url`, as seen from
+/// the containers on `network`.
+fn produced(handle: &ContainerHandle, network: &str) -> BTreeMap<String, String> {
+    let mut values = BTreeMap::new();
+    if let Some(alias) = handle.network_aliases(network).into_iter().next() {
+        values.insert("host".to_string(), alias);
+    }
+    if let Ok(url) = handle.network_url(network) {
+        values.insert("url".to_string(), url);
+    }
+    if let Some(port) = handle.default_port {
+        values.insert("port".to_string(), port.port().to_string());
+    }
+    if let Some(credentials) = handle.credentials.as_ref() {
+        values.insert("username".to_string(), credentials.username.clone());
+        values.insert("password".to_string(), credentials.password.clone());
+        values.insert("database".to_string(), credentials.database.clone());
+    }
+    values
+}
+
+/// `fixture` with the references of its env replaced by the values of started fixtures.
+fn resolve(
+    fixture: &FixtureSpec,
+    values: &HashMap<&str, BTreeMap<String, String>>,
+) -> Result<FixtureSpec, GraphError> {
+    let mut resolved = fixture.clone();
+    for value in resolved.env.values_mut() {
+        for (name, key) in references(&value.clone()) {
+            let produced = values
+                .get(name)
+                .and_then(|values| values.get(key))
+                .ok_or_else(|| GraphError::UnknownValue {
+                    fixture: fixture.name.clone(),
+                    reference: format!("{name}.{key}"),
+                })?;
+            *value = value.replace(&format!("${{{name}.{key}}}"), produced);
+        }
+    }
+    Ok(resolved)
+}
+
+/// Remove the fixtures started by `start`.
+pub fn teardown(fixtures: &[FixtureDescriptor]) -> Result<(), Error> {
+    let docker = connect().map_err(|err| Error::new(Stage::Connect, err))?;
+    teardown_with(&docker, fixtures);
+    Ok(())
+}
+
+pub fn teardown_with<B: Backend + Clone + 'static>(backend: &B, fixtures: &[FixtureDescriptor]) {
+    for fixture in fixtures {
+        log::info!("removing fixture {}", fixture.name);
+        backend.dispose(&fixture.container_id, true);
+    }
+    let networks: BTreeSet<_> = fixtures
+        .iter()
+        .filter_map(|fixture| fixture.network.as_deref())
+        .collect();
+    for name in networks {
+        network::remove(Arc::new(backend.clone()), name);
+    }
+}
+
+pub fn write_descriptors<P: AsRef<Path>>(
+    path: P,
+    fixtures: &[FixtureDescriptor],
+) -> io::Result<()> {
+    let file = std::fs::File::create(path)?;
+    Ok(serde_json::to_writer_pretty(file, fixtures)?)
+}
+
+pub fn read_descriptors<P: AsRef<Path>>(path: P) -> io::Result<Vec<FixtureDescriptor>> {
+    let file = std::fs::File::open(path)?;
+    Ok(serde_json::from_reader(file)?)
+}
+
+/// Write the variables of `fixtures` as `KEY=value` lines, the format of `NEXTEST_ENV`.
+pub fn write_nextest_env<W: Write>(
+    fixtures: &[FixtureDescriptor],
+    mut writer: W,
+) -> io::Result<()> {
+    for (key, value) in fixtures.iter().flat_map(|fixture| &fixture.env) {
+        writeln!(writer, "{key}={value}")?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::docker::mock::MockDocker;
+
+    use super::*;
+
+    #[cfg(feature = "fs")]
+    #[tokio::test]
+    async fn test_start_and_teardown_fixtures() {
+        let config: SetupConfig = serde_json::from_str(
+            r#"{ "fixtures": [
+                { "name": "mongo-main", "image": "mongo", "port": 27017 },
+                { "name": "cache", "image": "redis", "env": { "REDIS_ARGS": "--save ''" } }
+            ] }"#,
+        )
+        .unwrap();
+        let docker = MockDocker::new();
+
+        let fixtures = start_with(&docker, &config.fixtures).await.unwrap();
+        // detached, so still running
+        assert!(docker.is_running(&fixtures[0].container_id));
+        assert!(fixtures[0].env["MONGO_MAIN_URL"].starts_with("mongodb://localhost:"));
+        assert_eq!(fixtures[1].env["CACHE_HOST"], "localhost");
+        let env = docker
+            .config(&fixtures[1].container_id)
+            .unwrap()
+            .env
+            .unwrap();
+        assert!(env.contains(&"REDIS_ARGS=--save ''".to_string()));
+
+        let mut nextest_env = Vec::new();
+        write_nextest_env(&fixtures, &mut nextest_env).unwrap();
+        let nextest_env = String::from_utf8(nextest_env).unwrap();
+        assert!(nextest_env.contains("MONGO_MAIN_PORT="));
+
+        let dir = crate::fs::temp_dir();
+        let path = dir.path().join("fixtures.json");
+        write_descriptors(&path, &fixtures).unwrap();
+        assert_eq!(read_descriptors(&path).unwrap(), fixtures);
+
+        teardown_with(&docker, &fixtures);
+        assert!(docker.containers().is_empty());
+        assert!(docker.networks().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_start_fixture_graph() {
+        let config: SetupConfig = serde_json::from_str(
+            r#"{ "fixtures": [
+                { "name": "app", "image": "app", "env": {
+                    "DATABASE_URL": "${db.url}",
+                    "CACHE": "${cache.host}:${cache.port}",
+                    "HOME": "${HOME}"
+                } },
+                { "name": "db", "image": "postgres", "port": 5432, "protocol": "postgres",
+                  "ready_log": "ready to accept connections" },
+                { "name": "cache", "image": "redis", "port": 6379 }
+            ] }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            start_order(&config.fixtures).unwrap(),
+            vec![vec![1, 2], vec![0]]
+        );
+        let docker = MockDocker::new().startup_log("postgres", "ready to accept connections");
+
+        let fixtures = start_with(&docker, &config.fixtures).await.unwrap();
+        assert_eq!(fixtures[0].name, "app");
+        let env = docker
+            .config(&fixtures[0].container_id)
+            .unwrap()
+            .env
+            .unwrap();
+        // the other containers reach the fixtures on the network, the tests on the host
+        assert!(env.contains(&"DATABASE_URL=postgres://db:5432/".to_string()));
+        assert!(env.contains(&"CACHE=cache:6379".to_string()));
+        assert!(env.contains(&"HOME=${HOME}".to_string()));
+        assert!(fixtures[1].env["DB_URL"].starts_with("postgres://localhost:"));
+        assert_ne!(fixtures[2].env["CACHE_PORT"], "6379");
+    }
+
+    #[tokio::test]
+    async fn test_invalid_fixture_graph() {
+        let fixture = |name: &str, env: &[(&str, &str)]| FixtureSpec {
+            name: name.to_string(),
+            image: "app".to_string(),
+            port: None,
+            protocol: None,
+            env: env
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+            ready_log: None,
+        };
+
+        let fixtures = [
+            fixture("a", &[("B", "${b.url}")]),
+            fixture("b", &[("A", "${a.url}")]),
+        ];
+        assert_eq!(
+            start_order(&fixtures).unwrap_err(),
+            GraphError::Cycle(vec!["a".to_string(), "b".to_string()])
+        );
+
+        let fixtures = [fixture("a", &[("B", "${missing.url}")])];
+        assert!(matches!(
+            start_order(&fixtures).unwrap_err(),
+            GraphError::UnknownFixture { .. }
+        ));
+
+        // without a protocol, the fixture has no url
+        let docker = MockDocker::new();
+        let fixtures = [fixture("a", &[]), fixture("b", &[("A", "${a.url}")])];
+        let err = start_with(&docker, &fixtures).await.unwrap_err();
+        assert_eq!(err.stage(), Stage::Validate);
+        assert!(docker.containers().is_empty());
+        assert!(docker.networks().is_empty());
+    }
+}
@@ -0,0 +1,73 @@
+use bollard::models::ContainerInspectResponse;
+use tracing::Span;
+
+/// Span of a container, recording what tells it apart from the others of a test.
+pub(super) fn container_span(container_id: &str, info: &ContainerInspectResponse) -> Span {
+    let name = info
+        .name
+        .as_deref()
+        .map(|name| name.trim_start_matches('/'))
+        .unwrap_or_default();
+    let image = info
+        .config
+        .as_ref()
+        .and_then(|config| config.image.as_deref())
+        .unwrap_or_default();
+    tracing::info_span!(
+        "container",
+        container.name = name,
+        container.id = &container_id[..container_id.len().min(12)],
+        image,
+        ports = published_ports(info).as_str(),
+    )
+}
+
+/// The published ports, like `27017/tcp->49153`, sorted and comma-separated.
+fn published_ports(info: &ContainerInspectResponse) -> String {
+    let mut ports: Vec<_> = info
+        .network_settings
+        .as_ref()
+        .and_then(|settings| settings.ports.as_ref())
+        .into_iter()
+        .flatten()
+        .flat_map(|(port, bindings)| {
+            bindings
+                .iter()
+                .flatten()
+                .filter_map(|binding| binding.host_port.as_deref())
+                .map(move |host_port| format!("{port}->{host_port}"))
+        })
+        .collect();
+    ports.sort();
+    ports.dedup();
+    ports.join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use bollard::models::{NetworkSettings, PortBinding};
+
+    use super::*;
+
+    #[test]
+    fn test_published_ports() {
+        let binding = |port: &str| PortBinding {
+            host_ip: Some("0.0.0.0".to_string()),
+            host_port: Some(port.to_string()),
+        };
+        let info = ContainerInspectResponse {
+            network_settings: Some(NetworkSettings {
+                ports: Some(HashMap::from([
+                    ("9000/tcp".to_string(), Some(vec![binding("49154")])),
+                    ("8123/tcp".to_string(), Some(vec![binding("49153")])),
+                    ("9009/tcp".to_string(), None),
+                ])),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        assert_eq!(published_ports(&info), "8123/tcp->49153,9000/tcp->49154");
+    }
+}
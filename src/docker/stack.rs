@@ -0,0 +1,232 @@
+use super::graph::{self, OrderError};
+use super::{Builder, ContainerHandle, Error, Stage, ValidationError};
+
+/// Several containers started together, compose-style, each once the services it depends on
+/// are ready:
+///
+/// ```no_run
+/// # async fn run() {
+/// use test_utilities::docker::{Builder, Stack};
+///
+/// let stack = Stack::new()
+///     .service("db", Builder::new("mongo"))
+///     .service("cache", Builder::new("redis"))
+///     .service("app", Builder::new("my-app"))
+///     .depends_on("app", "db")
+///     .depends_on("app", "cache")
+///     .up()
+///     .await
+///     .unwrap();
+/// let db = &stack["db"];
+/// # }
+/// ```
+#[derive(Default)]
+pub struct Stack {
+    services: Vec<(String, Builder)>,
+    /// Pairs of a service and a service it depends on
+    dependencies: Vec<(String, String)>,
+    /// Network all the services join, aliased by their service names
+    network: Option<String>,
+}
+
+impl Stack {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Add a service named `name`, started as `builder` configures it, readiness wait
+    /// included.
+    pub fn service<S: Into<String>>(mut self, name: S, builder: Builder) -> Self {
+        self.services.push((name.into(), builder));
+        self
+    }
+
+    /// Start `service` only once `dependency` is ready.
+    pub fn depends_on<S: Into<String>, D: Into<String>>(
+        mut self,
+        service: S,
+        dependency: D,
+    ) -> Self {
+        self.dependencies.push((service.into(), dependency.into()));
+        self
+    }
+
+    /// Attach all the services to `network`, e.g. of a `NetworkHandle`, where they reach each
+    /// other by their service names.
+    pub fn network<S: Into<String>>(mut self, network: S) -> Self {
+        self.network = Some(network.into());
+        self
+    }
+
+    /// Start the services level by level, those of a level concurrently, returning once all
+    /// of them are ready. Services started before a failure are torn down.
+    pub async fn up(self) -> Result<StackHandle, Error> {
+        let names: Vec<_> = self
+            .services
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect();
+        let mut dependencies = vec![Vec::new(); names.len()];
+        for (service, dependency) in &self.dependencies {
+            let i = names
+                .iter()
+                .position(|name| name == service)
+                .ok_or_else(|| {
+                    Error::new(
+                        Stage::Validate,
+                        ValidationError::UnknownService(service.clone()),
+                    )
+                })?;
+            dependencies[i].push(dependency.as_str());
+        }
+        let levels = graph::levels(&names, dependencies).map_err(|err| {
+            let err = match err {
+                OrderError::Unknown { node, dependency } => ValidationError::UnknownDependency {
+                    service: names[node].to_string(),
+                    dependency: dependency.to_string(),
+                },
+                OrderError::Cycle(nodes) => ValidationError::DependencyCycle(
+                    nodes.into_iter().map(|i| names[i].to_string()).collect(),
+                ),
+            };
+            Error::new(Stage::Validate, err)
+        })?;
+
+        let mut services: Vec<_> = self.services.into_iter().map(Some).collect();
+        let mut handle = StackHandle {
+            services: Vec::new(),
+        };
+        for level in levels {
+            let starting = level.into_iter().map(|i| {
+                let (name, builder) = services[i].take().unwrap();
+                let builder = match &self.network {
+                    Some(network) => builder
+                        .network(network.as_str())
+                        .network_alias(name.as_str()),
+                    None => builder,
+                };
+                async move {
+                    let container = builder.try_build_disposable().await?;
+                    log::info!("started service {name}");
+                    Ok::<_, Error>((name, container))
+                }
+            });
+            let started = futures::future::try_join_all(starting).await?;
+            handle.services.extend(started);
+        }
+        Ok(handle)
+    }
+}
+
+/// The containers of a `Stack`, torn down together, in the reverse order they started in, when
+/// the handle is dropped.
+pub struct StackHandle {
+    /// Containers by service name, in the order they started in
+    services: Vec<(String, ContainerHandle)>,
+}
+
+impl StackHandle {
+    /// The container of service `name`, if the stack has one.
+    pub fn service(&self, name: &str) -> Option<&ContainerHandle> {
+        self.services
+            .iter()
+            .find(|(service, _)| service == name)
+            .map(|(_, container)| container)
+    }
+
+    /// Names of the services, in the order they started in.
+    pub fn service_names(&self) -> impl Iterator<Item = &str> {
+        self.services.iter().map(|(name, _)| name.as_str())
+    }
+}
+
+impl std::ops::Index<&str> for StackHandle {
+    type Output = ContainerHandle;
+
+    fn index(&self, name: &str) -> &ContainerHandle {
+        self.service(name)
+            .unwrap_or_else(|| panic!("the stack has no service `{name}`"))
+    }
+}
+
+impl Drop for StackHandle {
+    fn drop(&mut self) {
+        // dependents go first, so they do not see their dependencies vanish
+        while let Some((_, container)) = self.services.pop() {
+            drop(container);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::docker::mock::MockDocker;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_stack() {
+        let docker = MockDocker::new();
+        let stack = Stack::new()
+            .service("app", Builder::new("my-app").backend(docker.clone()))
+            .service("db", Builder::new("mongo").backend(docker.clone()))
+            .service("cache", Builder::new("redis").backend(docker.clone()))
+            .depends_on("app", "db")
+            .depends_on("app", "cache")
+            .network("test-stack")
+            .up()
+            .await
+            .unwrap();
+
+        assert_eq!(
+            stack.service_names().collect::<Vec<_>>(),
+            ["db", "cache", "app"]
+        );
+        assert!(stack["db"]
+            .network_aliases("test-stack")
+            .contains(&"db".to_string()));
+        assert_eq!(docker.containers().len(), 3);
+
+        drop(stack);
+        assert!(docker.containers().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_invalid_stack() {
+        let validation_error = |stack: Stack| async {
+            let err = stack.up().await.err().unwrap();
+            std::error::Error::source(&err)
+                .and_then(|source| source.downcast_ref::<ValidationError>())
+                .cloned()
+                .unwrap()
+        };
+
+        assert_eq!(
+            validation_error(Stack::new().depends_on("app", "db")).await,
+            ValidationError::UnknownService("app".to_string())
+        );
+        assert_eq!(
+            validation_error(
+                Stack::new()
+                    .service("app", Builder::new("my-app"))
+                    .depends_on("app", "db")
+            )
+            .await,
+            ValidationError::UnknownDependency {
+                service: "app".to_string(),
+                dependency: "db".to_string()
+            }
+        );
+        assert_eq!(
+            validation_error(
+                Stack::new()
+                    .service("a", Builder::new("a"))
+                    .service("b", Builder::new("b"))
+                    .depends_on("a", "b")
+                    .depends_on("b", "a")
+            )
+            .await,
+            ValidationError::DependencyCycle(vec!["a".to_string(), "b".to_string()])
+        );
+    }
+}
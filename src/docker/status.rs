@@ -0,0 +1,191 @@
+use std::fmt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use bollard::models::{ContainerInspectResponse, HealthStatusEnum};
+
+use super::{
+    connect, Backend, BackendResult, ContainerInspectResponseExt, ContainerPort, HostPort,
+};
+
+/// Label put on every container created by the crate, whose value is the crate version.
+pub const CRATE_LABEL: &str = "io.github.limoiie.test-utilities";
+
+/// Health of a container, as reported by its healthcheck.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Health {
+    /// The image defines no healthcheck
+    None,
+    Starting,
+    Healthy,
+    Unhealthy,
+}
+
+/// A container created by the crate, as listed by `status_report`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FixtureStatus {
+    pub container_id: String,
+    pub name: String,
+    pub image: String,
+    pub running: bool,
+    /// Time since the container was created
+    pub uptime: Duration,
+    /// Published ports, sorted by container port
+    pub ports: Vec<(ContainerPort, HostPort)>,
+    pub health: Health,
+}
+
+impl fmt::Display for Health {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Health::None => "-",
+            Health::Starting => "starting",
+            Health::Healthy => "healthy",
+            Health::Unhealthy => "unhealthy",
+        })
+    }
+}
+
+impl fmt::Display for FixtureStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = if self.running { "up" } else { "exited" };
+        let ports: Vec<_> = self
+            .ports
+            .iter()
+            .map(|(port, host_port)| format!("{host_port}->{port}"))
+            .collect();
+        write!(
+            f,
+            "{} ({}) {} {}s, {}, health: {}",
+            self.name,
+            self.image,
+            state,
+            self.uptime.as_secs(),
+            if ports.is_empty() {
+                "no ports".to_string()
+            } else {
+                ports.join(" ")
+            },
+            self.health
+        )
+    }
+}
+
+/// Summarize the containers of the crate alive on the local docker daemon, e.g. to print at the
+/// start and the end of a run. Failures to reach the daemon are logged and give an empty report.
+pub async fn status_report() -> Vec<FixtureStatus> {
+    let report = match connect() {
+        Ok(docker) => status_report_with(&docker).await,
+        Err(err) => Err(err),
+    };
+    report.unwrap_or_else(|err| {
+        log::warn!("failed to list the containers of test-utilities: {err}");
+        Vec::new()
+    })
+}
+
+pub async fn status_report_with<B: Backend + ?Sized>(
+    backend: &B,
+) -> BackendResult<Vec<FixtureStatus>> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    let mut report = Vec::new();
+    for summary in backend.list_containers(CRATE_LABEL).await? {
+        let Some(id) = summary.id else {
+            continue;
+        };
+        // gone since listed
+        let Ok(info) = backend.inspect_container(&id).await else {
+            continue;
+        };
+        let created = Duration::from_secs(summary.created.unwrap_or_default().max(0) as u64);
+        report.push(FixtureStatus {
+            name: info.get_name().unwrap_or_else(|| id.clone()),
+            container_id: id,
+            image: summary.image.unwrap_or_default(),
+            running: info
+                .state
+                .as_ref()
+                .and_then(|state| state.running)
+                .unwrap_or(false),
+            uptime: now.saturating_sub(created),
+            ports: published_ports(&info),
+            health: health(&info),
+        });
+    }
+    report.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(report)
+}
+
+fn published_ports(info: &ContainerInspectResponse) -> Vec<(ContainerPort, HostPort)> {
+    let mut ports: Vec<(ContainerPort, HostPort)> = info
+        .network_settings
+        .as_ref()
+        .and_then(|settings| settings.ports.as_ref())
+        .into_iter()
+        .flatten()
+        .filter_map(|(port, bindings)| Some((port.parse().ok()?, bindings.as_ref()?)))
+        .flat_map(|(port, bindings)| {
+            bindings
+                .iter()
+                .filter_map(move |binding| Some((port, binding.host_port.as_ref()?.parse().ok()?)))
+        })
+        .collect();
+    ports.sort_by_key(|(port, host_port)| (port.port(), host_port.0));
+    ports.dedup();
+    ports
+}
+
+fn health(info: &ContainerInspectResponse) -> Health {
+    let status = info
+        .state
+        .as_ref()
+        .and_then(|state| state.health.as_ref())
+        .and_then(|health| health.status);
+    match status {
+        Some(HealthStatusEnum::STARTING) => Health::Starting,
+        Some(HealthStatusEnum::HEALTHY) => Health::Healthy,
+        Some(HealthStatusEnum::UNHEALTHY) => Health::Unhealthy,
+        _ => Health::None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::docker::{mock::MockDocker, Builder};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_status_report() {
+        let docker = MockDocker::new();
+        let handle = Builder::new("mongo")
+            .name("status-mongo")
+            .bind_port_as_default(Some(HostPort::ANY), 27017)
+            .backend(docker.clone())
+            .build_disposable()
+            .await;
+        assert_eq!(
+            handle.labels()[CRATE_LABEL],
+            env!("CARGO_PKG_VERSION").to_string()
+        );
+        // not created by the crate
+        docker
+            .create_container(None, Default::default())
+            .await
+            .unwrap();
+
+        let report = status_report_with(&docker).await.unwrap();
+        assert_eq!(report.len(), 1);
+        let status = &report[0];
+        assert_eq!(status.name, "status-mongo");
+        assert_eq!(status.image, "mongo");
+        assert!(status.running);
+        assert_eq!(
+            status.ports,
+            vec![(ContainerPort::tcp(27017), handle.default_host_port.unwrap())]
+        );
+        assert_eq!(status.health, Health::None);
+        assert!(status.to_string().starts_with("status-mongo (mongo) up"));
+    }
+}
@@ -0,0 +1,256 @@
+//! Opt-in collection of what the containers of a test run looked like when they were torn down,
+//! so that a suite can assert that no fixture crashed mid-test unnoticed.
+//!
+//! `ContainerHandle::stop` always returns its report. Reports of dropped handles are only
+//! produced, and all the reports only collected, once `enable` is called or
+//! `TEST_UTILITIES_TEARDOWN` is set:
+//!
+//! ```no_run
+//! # async fn run() {
+//! use test_utilities::docker::{teardown, Builder};
+//!
+//! teardown::enable();
+//! let handle = Builder::new("mongo").build_disposable().await;
+//! drop(handle);
+//! teardown::assert_no_crashes();
+//! # }
+//! ```
+//!
+//! A test can rather open a `scope`, which collects the reports of the handles its thread tears
+//! down while it is open, apart from the ones of tests running in parallel.
+
+use std::cell::RefCell;
+use std::fmt;
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use bollard::models::{ContainerInspectResponse, MountPointTypeEnum};
+
+/// Environment variable enabling the collection when set to anything but `0`
+pub const TEARDOWN_ENV: &str = "TEST_UTILITIES_TEARDOWN";
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static REPORTS: Mutex<Vec<TeardownReport>> = Mutex::new(Vec::new());
+
+thread_local! {
+    /// Reports recorded by the thread, for each of its open scopes from the outermost
+    static SCOPES: RefCell<Vec<Vec<TeardownReport>>> = const { RefCell::new(Vec::new()) };
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TeardownReport {
+    pub container_id: String,
+    pub name: Option<String>,
+    pub image: Option<String>,
+    /// State the container was found in when the teardown began, e.g. `running` or `exited`,
+    /// or `None` if it was gone already
+    pub state: Option<String>,
+    /// Exit code of a container which had stopped on its own
+    pub exit_code: Option<i64>,
+    /// How long the container lived under its handle
+    pub duration: Duration,
+    /// Anonymous volumes removed along with the container
+    pub volumes_removed: Vec<String>,
+}
+
+impl TeardownReport {
+    /// Whether the container had exited with a non-zero code before its teardown.
+    pub fn crashed(&self) -> bool {
+        self.exit_code.is_some_and(|code| code != 0)
+    }
+
+    /// Whether the container was gone before its teardown, e.g. removed by someone else, so
+    /// that how it ended is unknown.
+    pub fn is_unknown(&self) -> bool {
+        self.state.is_none()
+    }
+}
+
+impl fmt::Display for TeardownReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = self.name.as_deref().unwrap_or(&self.container_id);
+        write!(f, "container {name}")?;
+        if let Some(image) = &self.image {
+            write!(f, " ({image})")?;
+        }
+        match (&self.state, self.exit_code) {
+            (Some(state), Some(code)) => write!(f, " was {state} with code {code}")?,
+            (Some(state), None) => write!(f, " was {state}")?,
+            (None, _) => write!(f, " was gone, its outcome unknown")?,
+        }
+        write!(f, " after {:.3}s", self.duration.as_secs_f64())?;
+        if !self.volumes_removed.is_empty() {
+            write!(f, ", removed volumes {}", self.volumes_removed.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+/// Whether reports are collected, process-wide or by a scope open on the current thread.
+pub fn is_enabled() -> bool {
+    is_enabled_globally() || SCOPES.with(|scopes| !scopes.borrow().is_empty())
+}
+
+fn is_enabled_globally() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+        || std::env::var(TEARDOWN_ENV).is_ok_and(|value| !value.is_empty() && value != "0")
+}
+
+/// Log `report`, and keep it in the open scopes of the current thread, and process-wide if the
+/// collection is enabled.
+pub fn record(report: TeardownReport) {
+    if report.crashed() || report.is_unknown() {
+        log::warn!("{report}");
+    } else {
+        log::info!("{report}");
+    }
+    SCOPES.with(|scopes| {
+        for reports in scopes.borrow_mut().iter_mut() {
+            reports.push(report.clone());
+        }
+    });
+    if is_enabled_globally() {
+        REPORTS.lock().unwrap().push(report);
+    }
+}
+
+/// All the reports recorded so far.
+pub fn reports() -> Vec<TeardownReport> {
+    REPORTS.lock().unwrap().clone()
+}
+
+/// Reports recorded so far of the containers which crashed.
+pub fn crashed() -> Vec<TeardownReport> {
+    reports()
+        .into_iter()
+        .filter(TeardownReport::crashed)
+        .collect()
+}
+
+/// Panic, listing them, if any of the containers torn down so far crashed.
+pub fn assert_no_crashes() {
+    panic_on_crashes(&crashed());
+}
+
+fn panic_on_crashes(crashed: &[TeardownReport]) {
+    if !crashed.is_empty() {
+        let lines: Vec<_> = crashed.iter().map(ToString::to_string).collect();
+        panic!("containers crashed:\n{}", lines.join("\n"));
+    }
+}
+
+/// Start collecting the reports of the handles the current thread tears down, until the
+/// returned scope is dropped. Scopes may be nested, each collecting while it is open.
+pub fn scope() -> Scope {
+    SCOPES.with(|scopes| {
+        let mut scopes = scopes.borrow_mut();
+        scopes.push(Vec::new());
+        Scope {
+            depth: scopes.len() - 1,
+            _thread: PhantomData,
+        }
+    })
+}
+
+/// Reports collected on a thread while open, see `scope`.
+pub struct Scope {
+    depth: usize,
+    /// Bound to the thread whose reports it collects
+    _thread: PhantomData<*const ()>,
+}
+
+impl Scope {
+    /// The reports collected so far.
+    pub fn reports(&self) -> Vec<TeardownReport> {
+        SCOPES.with(|scopes| scopes.borrow().get(self.depth).cloned().unwrap_or_default())
+    }
+
+    /// The reports collected so far of the containers which crashed.
+    pub fn crashed(&self) -> Vec<TeardownReport> {
+        self.reports()
+            .into_iter()
+            .filter(TeardownReport::crashed)
+            .collect()
+    }
+
+    /// Panic, listing them, if any of the containers torn down in the scope crashed.
+    pub fn assert_no_crashes(&self) {
+        panic_on_crashes(&self.crashed());
+    }
+}
+
+impl Drop for Scope {
+    fn drop(&mut self) {
+        SCOPES.with(|scopes| scopes.borrow_mut().truncate(self.depth));
+    }
+}
+
+/// Names of the volumes of `info` created for the container alone, which docker removes along
+/// with it, unlike named volumes and bind mounts.
+pub(crate) fn anonymous_volumes(info: &ContainerInspectResponse) -> Vec<String> {
+    let host_config = info.host_config.as_ref();
+    let mut named: Vec<&str> = host_config
+        .and_then(|host_config| host_config.binds.as_ref())
+        .into_iter()
+        .flatten()
+        .filter_map(|bind| bind.split(':').next())
+        .collect();
+    named.extend(
+        host_config
+            .and_then(|host_config| host_config.mounts.as_ref())
+            .into_iter()
+            .flatten()
+            .filter_map(|mount| mount.source.as_deref()),
+    );
+
+    info.mounts
+        .iter()
+        .flatten()
+        .filter(|mount| mount.typ == Some(MountPointTypeEnum::VOLUME))
+        .filter_map(|mount| mount.name.clone())
+        .filter(|name| !named.contains(&name.as_str()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use bollard::models::{HostConfig, MountPoint};
+
+    use super::*;
+
+    #[test]
+    fn test_anonymous_volumes() {
+        let volume = |name: &str| MountPoint {
+            typ: Some(MountPointTypeEnum::VOLUME),
+            name: Some(name.to_string()),
+            ..Default::default()
+        };
+        let info = ContainerInspectResponse {
+            host_config: Some(HostConfig {
+                binds: Some(vec![
+                    "data:/data".to_string(),
+                    "/tmp/seed:/seed".to_string(),
+                ]),
+                ..Default::default()
+            }),
+            mounts: Some(vec![
+                volume("data"),
+                volume("3f1c9e"),
+                MountPoint {
+                    typ: Some(MountPointTypeEnum::BIND),
+                    source: Some("/tmp/seed".to_string()),
+                    ..Default::default()
+                },
+            ]),
+            ..Default::default()
+        };
+
+        assert_eq!(anonymous_volumes(&info), ["3f1c9e"]);
+    }
+}
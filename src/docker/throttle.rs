@@ -0,0 +1,88 @@
+use std::sync::{Arc, Mutex, OnceLock};
+
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// Environment variable capping the number of containers alive at once, taking precedence over
+/// `set_max_concurrent_containers`
+pub const MAX_CONTAINERS_ENV: &str = "TEST_UTILITIES_MAX_CONTAINERS";
+
+static GLOBAL: OnceLock<Mutex<Throttle>> = OnceLock::new();
+
+/// Cap the number of containers alive at once across the process, so that suites run with many
+/// test threads do not start more containers than the runner can hold.
+///
+/// Building a container past the cap waits until another handle is dropped. Containers already
+/// alive keep counting against the previous cap.
+pub fn set_max_concurrent_containers(n: usize) {
+    if let Some(limit) = env_limit() {
+        log::debug!("ignoring container limit {n} in favor of {MAX_CONTAINERS_ENV}={limit}");
+        return;
+    }
+    *global().lock().unwrap() = Throttle::new(Some(n));
+}
+
+/// Wait for a slot for a new container, if the number of containers is capped.
+pub(crate) async fn acquire() -> Option<OwnedSemaphorePermit> {
+    let throttle = global().lock().unwrap().clone();
+    throttle.acquire().await
+}
+
+fn global() -> &'static Mutex<Throttle> {
+    GLOBAL.get_or_init(|| Mutex::new(Throttle::new(env_limit())))
+}
+
+fn env_limit() -> Option<usize> {
+    let value = std::env::var(MAX_CONTAINERS_ENV).ok()?;
+    match value.parse() {
+        Ok(limit) => Some(limit),
+        Err(err) => {
+            log::warn!("ignoring invalid {MAX_CONTAINERS_ENV}={value}: {err}");
+            None
+        }
+    }
+}
+
+#[derive(Clone)]
+struct Throttle {
+    semaphore: Option<Arc<Semaphore>>,
+}
+
+impl Throttle {
+    fn new(limit: Option<usize>) -> Self {
+        Throttle {
+            // a cap of zero would block forever
+            semaphore: limit.map(|limit| Arc::new(Semaphore::new(limit.max(1)))),
+        }
+    }
+
+    async fn acquire(&self) -> Option<OwnedSemaphorePermit> {
+        let semaphore = self.semaphore.clone()?;
+        if semaphore.available_permits() == 0 {
+            log::debug!("waiting for a container slot");
+        }
+        Some(semaphore.acquire_owned().await.unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_throttle() {
+        assert!(Throttle::new(None).acquire().await.is_none());
+
+        let throttle = Throttle::new(Some(1));
+        let permit = throttle.acquire().await;
+        assert!(permit.is_some());
+
+        let blocked = tokio::time::timeout(Duration::from_millis(50), throttle.acquire()).await;
+        assert!(blocked.is_err());
+
+        drop(permit);
+        let unblocked = tokio::time::timeout(Duration::from_millis(50), throttle.acquire()).await;
+        assert!(unblocked.unwrap().is_some());
+    }
+}
@@ -0,0 +1,189 @@
+//! Opt-in collection of how long fixtures take to get ready, to find the ones which dominate
+//! the duration of a test run.
+//!
+//! Timings are only collected once `enable` is called or `TEST_UTILITIES_TIMING` is set:
+//!
+//! ```no_run
+//! # async fn run() {
+//! use test_utilities::docker::{timing, Builder};
+//!
+//! timing::enable();
+//! let handle = Builder::new("mongo").build_disposable().await;
+//! timing::measure("mongo", timing::Phase::Seed, async { /* insert fixtures */ }).await;
+//! eprintln!("{}", timing::summary());
+//! # }
+//! ```
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::future::Future;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Environment variable enabling the collection when set to anything but `0`
+pub const TIMING_ENV: &str = "TEST_UTILITIES_TIMING";
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static TIMINGS: Mutex<Vec<Timing>> = Mutex::new(Vec::new());
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Phase {
+    Pull,
+    Start,
+    Ready,
+    Seed,
+}
+
+impl fmt::Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let phase = match self {
+            Phase::Pull => "pull",
+            Phase::Start => "start",
+            Phase::Ready => "ready",
+            Phase::Seed => "seed",
+        };
+        f.write_str(phase)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Timing {
+    pub fixture: String,
+    pub phase: Phase,
+    pub duration: Duration,
+}
+
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+        || std::env::var(TIMING_ENV).is_ok_and(|value| !value.is_empty() && value != "0")
+}
+
+/// Record that `phase` of `fixture` took `duration`, if the collection is enabled.
+pub fn record<S: Into<String>>(fixture: S, phase: Phase, duration: Duration) {
+    if is_enabled() {
+        TIMINGS.lock().unwrap().push(Timing {
+            fixture: fixture.into(),
+            phase,
+            duration,
+        });
+    }
+}
+
+/// Await `fut`, recording how long it took as `phase` of `fixture`.
+pub async fn measure<S: Into<String>, F: Future>(fixture: S, phase: Phase, fut: F) -> F::Output {
+    let started = Instant::now();
+    let output = fut.await;
+    record(fixture, phase, started.elapsed());
+    output
+}
+
+/// All the timings recorded so far.
+pub fn timings() -> Vec<Timing> {
+    TIMINGS.lock().unwrap().clone()
+}
+
+/// Timings recorded so far, aggregated by fixture and phase.
+pub fn summary() -> Summary {
+    let mut entries = BTreeMap::<_, SummaryEntry>::new();
+    for timing in timings() {
+        let entry = entries
+            .entry((timing.fixture.clone(), timing.phase))
+            .or_insert_with(|| SummaryEntry {
+                fixture: timing.fixture,
+                phase: timing.phase,
+                count: 0,
+                total: Duration::ZERO,
+                max: Duration::ZERO,
+            });
+        entry.count += 1;
+        entry.total += timing.duration;
+        entry.max = entry.max.max(timing.duration);
+    }
+
+    let mut entries: Vec<_> = entries.into_values().collect();
+    entries.sort_by_key(|entry| std::cmp::Reverse(entry.total));
+    Summary { entries }
+}
+
+/// Write the summary to `path`, e.g. to keep it as a CI artifact.
+pub fn write_summary<P: AsRef<Path>>(path: P) -> std::io::Result<()> {
+    std::fs::write(path, summary().to_string())
+}
+
+/// Aggregated timings, the most expensive first.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Summary {
+    pub entries: Vec<SummaryEntry>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SummaryEntry {
+    pub fixture: String,
+    pub phase: Phase,
+    pub count: usize,
+    pub total: Duration,
+    pub max: Duration,
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = self
+            .entries
+            .iter()
+            .map(|entry| entry.fixture.len())
+            .max()
+            .unwrap_or(0)
+            .max("fixture".len());
+        writeln!(
+            f,
+            "{:width$}  {:6}  {:>5}  {:>10}  {:>10}",
+            "fixture", "phase", "count", "total", "max"
+        )?;
+        for entry in &self.entries {
+            writeln!(
+                f,
+                "{:width$}  {:6}  {:>5}  {:>9.3}s  {:>9.3}s",
+                entry.fixture,
+                entry.phase.to_string(),
+                entry.count,
+                entry.total.as_secs_f64(),
+                entry.max.as_secs_f64()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_summary() {
+        enable();
+        record("timing-test-a", Phase::Start, Duration::from_millis(300));
+        record("timing-test-a", Phase::Start, Duration::from_millis(100));
+        measure("timing-test-b", Phase::Seed, async {}).await;
+
+        let summary = summary();
+        let a = summary
+            .entries
+            .iter()
+            .find(|entry| entry.fixture == "timing-test-a")
+            .unwrap();
+        assert_eq!(a.count, 2);
+        assert_eq!(a.total, Duration::from_millis(400));
+        assert_eq!(a.max, Duration::from_millis(300));
+        assert!(summary
+            .entries
+            .iter()
+            .any(|entry| entry.fixture == "timing-test-b" && entry.phase == Phase::Seed));
+        assert!(summary.to_string().contains("timing-test-a  start"));
+    }
+}
@@ -0,0 +1,147 @@
+use std::io;
+use std::path::Path;
+
+/// Options of a bind mount, see `Builder::bind_volume_opts`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BindOpts {
+    /// Relabel the host path so that SELinux lets the container access it (`:z`)
+    pub selinux_relabel: bool,
+    /// Mount the host path read-only (`:ro`)
+    pub read_only: bool,
+    /// Make the host path readable and writable by every user (`a+rwX`), so that the container
+    /// user can write to it no matter which uid it runs as or how user namespaces remap it
+    pub open_permissions: bool,
+}
+
+impl BindOpts {
+    /// Append the mount options to a `host:container` bind.
+    pub(crate) fn apply(&self, bind: &str) -> String {
+        let mut options = Vec::new();
+        if self.read_only {
+            options.push("ro");
+        }
+        if self.selinux_relabel {
+            options.push("z");
+        }
+
+        if options.is_empty() {
+            bind.to_string()
+        } else {
+            format!("{bind}:{}", options.join(","))
+        }
+    }
+}
+
+/// Host path of a `host:container` bind, split at the last colon as the host path may be a
+/// Windows one like `C:\data`.
+pub(crate) fn bind_source(bind: &str) -> Option<&str> {
+    bind.rsplit_once(':').map(|(host, _)| host)
+}
+
+/// `path` in the form the daemon expects as the host path of a bind, e.g. `/c/Users/me` for
+/// `C:\Users\me`, which would otherwise be split at the colon of the drive.
+pub(crate) fn bind_host_path(path: &Path) -> String {
+    let path = path.to_string_lossy();
+    // canonical paths on Windows carry the verbatim prefix
+    if let Some(share) = path.strip_prefix(r"\\?\UNC\") {
+        return format!("//{}", share.replace('\\', "/"));
+    }
+    let path = path.strip_prefix(r"\\?\").unwrap_or(&path);
+    let mut chars = path.chars();
+    match (chars.next(), chars.next()) {
+        (Some(drive), Some(':')) if drive.is_ascii_alphabetic() => format!(
+            "/{}{}",
+            drive.to_ascii_lowercase(),
+            chars.as_str().replace('\\', "/")
+        ),
+        _ => path.to_string(),
+    }
+}
+
+/// Make `path` and everything below it readable and writable by any user.
+#[cfg(unix)]
+pub(crate) fn open_up_permissions(path: &Path) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let metadata = std::fs::symlink_metadata(path)?;
+    if metadata.file_type().is_symlink() {
+        return Ok(());
+    }
+
+    let mode = metadata.permissions().mode();
+    if metadata.is_dir() {
+        std::fs::set_permissions(path, PermissionsExt::from_mode(mode | 0o777))?;
+        for entry in std::fs::read_dir(path)? {
+            open_up_permissions(&entry?.path())?;
+        }
+    } else {
+        std::fs::set_permissions(path, PermissionsExt::from_mode(mode | 0o666))?;
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub(crate) fn open_up_permissions(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bind_opts_apply() {
+        let bind = "/tmp/data:/data";
+
+        assert_eq!(BindOpts::default().apply(bind), bind);
+        assert_eq!(
+            BindOpts {
+                selinux_relabel: true,
+                read_only: true,
+                ..Default::default()
+            }
+            .apply(bind),
+            "/tmp/data:/data:ro,z"
+        );
+    }
+
+    #[test]
+    fn test_bind_source() {
+        assert_eq!(bind_source("/tmp/data:/data"), Some("/tmp/data"));
+        assert_eq!(bind_source(r"C:\data:/data"), Some(r"C:\data"));
+        assert_eq!(bind_source("/data"), None);
+    }
+
+    #[cfg(all(unix, feature = "fs"))]
+    #[test]
+    fn test_open_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        use crate::docker::Builder;
+
+        let dir = crate::fs::temp_dir();
+        let file = dir.path().join("a.txt");
+        std::fs::write(&file, "a").unwrap();
+        std::fs::set_permissions(&file, PermissionsExt::from_mode(0o600)).unwrap();
+        let opts = BindOpts {
+            open_permissions: true,
+            ..Default::default()
+        };
+        Builder::new("redis").bind_volume_opts(format!("{}:/data", dir.path().display()), opts);
+
+        let mode = |path: &Path| std::fs::metadata(path).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode(dir.path()), 0o777);
+        assert_eq!(mode(&file), 0o666);
+    }
+
+    #[test]
+    fn test_bind_host_path() {
+        assert_eq!(bind_host_path(Path::new("/tmp/data")), "/tmp/data");
+        assert_eq!(bind_host_path(Path::new(r"C:\Users\me")), "/c/Users/me");
+        assert_eq!(bind_host_path(Path::new(r"\\?\D:\data")), "/d/data");
+        assert_eq!(
+            bind_host_path(Path::new(r"\\?\UNC\server\share\data")),
+            "//server/share/data"
+        );
+    }
+}
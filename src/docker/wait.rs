@@ -0,0 +1,95 @@
+use std::time::Duration;
+
+use bollard::models::HealthStatusEnum;
+
+use super::host::BIND_IP;
+use super::{ContainerHandle, ContainerInspectResponseExt, ContainerPort, WaitError};
+
+/// Time `build_disposable` waits for a container to be ready, unless set by
+/// `Builder::wait_timeout`.
+pub const DEFAULT_WAIT_TIMEOUT: Duration = Duration::from_secs(60);
+
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How to tell that the service in a container is ready to be used. Variants depend on the
+/// features enabled, so matches on it need a wildcard arm.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum WaitStrategy {
+    /// A line of the output, stdout or stderr, contains the text
+    LogLine(String),
+    /// The container port, published on the host, accepts TCP connections
+    PortOpen(ContainerPort),
+    /// The healthcheck of the container passes
+    Healthy,
+    /// A fixed delay elapsed
+    Delay(Duration),
+    /// The gRPC health service on the default port reports the service serving, the empty
+    /// name standing for the whole server
+    #[cfg(feature = "tonic")]
+    GrpcHealth(String),
+}
+
+type BoxError = Box<dyn std::error::Error + Send + Sync + 'static>;
+
+/// Poll `handle` until `strategy` is satisfied, for at most `timeout`.
+pub(crate) async fn wait_until_ready(
+    handle: &ContainerHandle,
+    strategy: &WaitStrategy,
+    timeout: Duration,
+) -> Result<(), BoxError> {
+    match tokio::time::timeout(timeout, poll(handle, strategy)).await {
+        Ok(result) => result,
+        Err(_) => Err(WaitError::TimedOut(timeout).into()),
+    }
+}
+
+async fn poll(handle: &ContainerHandle, strategy: &WaitStrategy) -> Result<(), BoxError> {
+    if let WaitStrategy::Delay(delay) = strategy {
+        tokio::time::sleep(*delay).await;
+        return Ok(());
+    }
+
+    let backend = handle.backend.as_ref();
+    let id = handle.container_id.as_str();
+    loop {
+        let info = backend.inspect_container(id).await?;
+        let state = info.state.as_ref();
+        if !state.and_then(|state| state.running).unwrap_or(false) {
+            return Err(WaitError::Exited.into());
+        }
+
+        let ready = match strategy {
+            WaitStrategy::LogLine(text) => {
+                let logs = backend.logs(id).await?;
+                logs.lines().any(|line| line.contains(text.as_str()))
+            }
+            WaitStrategy::PortOpen(port) => {
+                let host_port = info
+                    .get_host_port(Some(BIND_IP), *port)
+                    .ok_or(WaitError::UnboundPort(*port))?;
+                tokio::net::TcpStream::connect((handle.host_ip.as_str(), host_port.0))
+                    .await
+                    .is_ok()
+            }
+            WaitStrategy::Healthy => {
+                let health = state.and_then(|state| state.health.as_ref());
+                match health.and_then(|health| health.status) {
+                    Some(HealthStatusEnum::HEALTHY) => true,
+                    Some(HealthStatusEnum::UNHEALTHY) => return Err(WaitError::Unhealthy.into()),
+                    Some(HealthStatusEnum::STARTING) => false,
+                    _ => return Err(WaitError::NoHealthcheck.into()),
+                }
+            }
+            #[cfg(feature = "tonic")]
+            WaitStrategy::GrpcHealth(service) => {
+                super::grpc::is_serving(&super::grpc::endpoint(handle)?, service).await
+            }
+            WaitStrategy::Delay(_) => unreachable!(),
+        };
+        if ready {
+            return Ok(());
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
@@ -0,0 +1,309 @@
+//! Sampling of the memory used by the test process and by containers while a test runs, to
+//! catch leaks in the code paths it exercises.
+//!
+//! ```no_run
+//! # async fn run() {
+//! use test_utilities::docker::{watchdog::Watchdog, Builder};
+//!
+//! let handle = Builder::new("mongo").build_disposable().await;
+//! let watchdog = Watchdog::new()
+//!     .max_process_memory(512 << 20)
+//!     .max_container_memory(256 << 20)
+//!     .watch(&handle)
+//!     .start();
+//! // exercise the code under test
+//! let report = watchdog.finish().await;
+//! eprintln!("peak memory of the process: {:?}", report.peak_process_memory);
+//! # }
+//! ```
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use super::{Backend, ContainerHandle};
+
+const DEFAULT_INTERVAL: Duration = Duration::from_millis(500);
+
+/// What to do once a threshold is exceeded.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OnBreach {
+    /// Log a warning as soon as it happens
+    Warn,
+    /// Log a warning as soon as it happens, and panic in `WatchdogGuard::finish`
+    #[default]
+    Fail,
+}
+
+#[derive(Default)]
+pub struct Watchdog {
+    interval: Option<Duration>,
+    max_process_memory: Option<u64>,
+    max_container_memory: Option<u64>,
+    on_breach: OnBreach,
+    containers: Vec<Watched>,
+}
+
+/// A container sampled by the watchdog.
+struct Watched {
+    name: String,
+    container_id: String,
+    backend: Arc<dyn Backend>,
+}
+
+impl Watchdog {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Time between two samples, 500ms by default.
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = Some(interval);
+        self
+    }
+
+    /// Resident memory, in bytes, the test process may use. It is only sampled on Linux.
+    pub fn max_process_memory(mut self, bytes: u64) -> Self {
+        self.max_process_memory = Some(bytes);
+        self
+    }
+
+    /// Memory, in bytes, each watched container may use.
+    pub fn max_container_memory(mut self, bytes: u64) -> Self {
+        self.max_container_memory = Some(bytes);
+        self
+    }
+
+    pub fn on_breach(mut self, on_breach: OnBreach) -> Self {
+        self.on_breach = on_breach;
+        self
+    }
+
+    /// Sample the memory used by the container of `handle` as well.
+    pub fn watch(mut self, handle: &ContainerHandle) -> Self {
+        self.containers.push(Watched {
+            name: handle
+                .name
+                .clone()
+                .unwrap_or_else(|| handle.container_id.clone()),
+            container_id: handle.container_id.clone(),
+            backend: handle.backend.clone(),
+        });
+        self
+    }
+
+    /// Start sampling in the background, until the guard is finished or dropped.
+    pub fn start(self) -> WatchdogGuard {
+        let interval = self.interval.unwrap_or(DEFAULT_INTERVAL);
+        let on_breach = self.on_breach;
+        let sampler = Arc::new(Sampler {
+            watchdog: self,
+            started: Instant::now(),
+            report: Mutex::new(WatchdogReport::default()),
+        });
+        let task = tokio::spawn({
+            let sampler = sampler.clone();
+            async move {
+                let mut ticks = tokio::time::interval(interval);
+                loop {
+                    ticks.tick().await;
+                    sampler.sample().await;
+                }
+            }
+        });
+        WatchdogGuard {
+            sampler,
+            task,
+            on_breach,
+        }
+    }
+}
+
+/// Samples taken since the watchdog started.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct WatchdogReport {
+    pub samples: usize,
+    /// Highest resident memory of the process, if it could be sampled
+    pub peak_process_memory: Option<u64>,
+    /// Highest memory used by each watched container, by name
+    pub peak_container_memory: BTreeMap<String, u64>,
+    /// First sample exceeding its threshold, of the process and of each container
+    pub breaches: Vec<Breach>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Breach {
+    /// `process`, or the name of the container
+    pub subject: String,
+    pub memory: u64,
+    pub limit: u64,
+    /// Time since the watchdog started
+    pub after: Duration,
+}
+
+impl fmt::Display for Breach {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} used {} bytes of memory, above the limit of {}, after {:.3}s",
+            self.subject,
+            self.memory,
+            self.limit,
+            self.after.as_secs_f64()
+        )
+    }
+}
+
+struct Sampler {
+    watchdog: Watchdog,
+    started: Instant,
+    report: Mutex<WatchdogReport>,
+}
+
+impl Sampler {
+    async fn sample(&self) {
+        let process_memory = process_memory();
+        let mut container_memory = Vec::new();
+        for watched in &self.watchdog.containers {
+            match watched.backend.memory_usage(&watched.container_id).await {
+                Ok(memory) => container_memory.push((watched.name.as_str(), memory)),
+                Err(err) => log::debug!("failed to sample memory of {}: {err}", watched.name),
+            }
+        }
+
+        let mut report = self.report.lock().unwrap();
+        report.samples += 1;
+        if let Some(memory) = process_memory {
+            let peak = report.peak_process_memory.get_or_insert(0);
+            *peak = (*peak).max(memory);
+            self.check(
+                &mut report,
+                "process",
+                memory,
+                self.watchdog.max_process_memory,
+            );
+        }
+        for (name, memory) in container_memory {
+            let peak = report
+                .peak_container_memory
+                .entry(name.to_string())
+                .or_insert(0);
+            *peak = (*peak).max(memory);
+            self.check(
+                &mut report,
+                name,
+                memory,
+                self.watchdog.max_container_memory,
+            );
+        }
+    }
+
+    fn check(&self, report: &mut WatchdogReport, subject: &str, memory: u64, limit: Option<u64>) {
+        let Some(limit) = limit.filter(|limit| memory > *limit) else {
+            return;
+        };
+        if report
+            .breaches
+            .iter()
+            .all(|breach| breach.subject != subject)
+        {
+            let breach = Breach {
+                subject: subject.to_string(),
+                memory,
+                limit,
+                after: self.started.elapsed(),
+            };
+            log::warn!("{breach}");
+            report.breaches.push(breach);
+        }
+    }
+}
+
+/// The running watchdog, which stops sampling when dropped.
+pub struct WatchdogGuard {
+    sampler: Arc<Sampler>,
+    task: tokio::task::JoinHandle<()>,
+    on_breach: OnBreach,
+}
+
+impl WatchdogGuard {
+    /// The samples taken so far.
+    pub fn report(&self) -> WatchdogReport {
+        self.sampler.report.lock().unwrap().clone()
+    }
+
+    /// Stop sampling after a last sample, panicking if a threshold was exceeded unless the
+    /// watchdog only warns.
+    pub async fn finish(self) -> WatchdogReport {
+        self.task.abort();
+        self.sampler.sample().await;
+        let report = self.report();
+        if self.on_breach == OnBreach::Fail && !report.breaches.is_empty() {
+            let lines: Vec<_> = report.breaches.iter().map(ToString::to_string).collect();
+            panic!("memory thresholds exceeded:\n{}", lines.join("\n"));
+        }
+        report
+    }
+}
+
+impl Drop for WatchdogGuard {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Resident memory of the process, in bytes.
+#[cfg(target_os = "linux")]
+fn process_memory() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+    let kib: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kib * 1024)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn process_memory() -> Option<u64> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::docker::mock::MockDocker;
+    use crate::docker::Builder;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_container_memory_breach() {
+        let docker = MockDocker::new();
+        let handle = Builder::new("redis")
+            .name("watched-redis")
+            .backend(docker.clone())
+            .build_disposable()
+            .await;
+        docker.set_memory_usage(&handle.container_id, 1 << 20);
+
+        let watchdog = Watchdog::new()
+            .interval(Duration::from_millis(10))
+            .max_container_memory(2 << 20)
+            .on_breach(OnBreach::Warn)
+            .watch(&handle)
+            .start();
+        docker.set_memory_usage(&handle.container_id, 3 << 20);
+        let report = watchdog.finish().await;
+
+        assert!(report.samples >= 1);
+        assert_eq!(report.peak_container_memory["watched-redis"], 3 << 20);
+        assert_eq!(report.breaches.len(), 1);
+        assert_eq!(report.breaches[0].subject, "watched-redis");
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    #[should_panic(expected = "memory thresholds exceeded")]
+    async fn test_process_memory_breach_fails() {
+        let watchdog = Watchdog::new().max_process_memory(1).start();
+        watchdog.finish().await;
+    }
+}
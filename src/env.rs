@@ -0,0 +1,86 @@
+//! Scoped changes to the environment of the test process.
+
+use std::ffi::{OsStr, OsString};
+
+use crate::fixture::Fixture;
+
+/// Environment variables set for the lifetime of the guard, restored to their previous values,
+/// or removed, when it is dropped.
+///
+/// The environment is shared by all the threads of the process, so tests setting the same
+/// variables should not run concurrently.
+#[derive(Debug, Default)]
+#[must_use = "the variables are restored as soon as the guard is dropped"]
+pub struct EnvGuard {
+    /// Variables set so far with their previous values, in the order they were set
+    saved: Vec<(String, Option<OsString>)>,
+}
+
+impl EnvGuard {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn set<K: Into<String>, V: AsRef<OsStr>>(mut self, key: K, value: V) -> Self {
+        let key = key.into();
+        self.saved.push((key.clone(), std::env::var_os(&key)));
+        std::env::set_var(key, value);
+        self
+    }
+
+    pub fn remove<K: Into<String>>(mut self, key: K) -> Self {
+        let key = key.into();
+        self.saved.push((key.clone(), std::env::var_os(&key)));
+        std::env::remove_var(key);
+        self
+    }
+
+    /// Names of the variables managed by the guard.
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.saved.iter().map(|(key, _)| key.as_str())
+    }
+}
+
+impl Drop for EnvGuard {
+    fn drop(&mut self) {
+        // in reverse, so that a variable set twice gets its original value back
+        for (key, value) in self.saved.drain(..).rev() {
+            match value {
+                Some(value) => std::env::set_var(key, value),
+                None => std::env::remove_var(key),
+            }
+        }
+    }
+}
+
+/// Restores the variables when torn down or dropped.
+impl Fixture for EnvGuard {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_env_guard_restores_variables() {
+        std::env::set_var("TEST_UTILITIES_ENV_GUARD_A", "original");
+        std::env::remove_var("TEST_UTILITIES_ENV_GUARD_B");
+        {
+            let guard = EnvGuard::new()
+                .set("TEST_UTILITIES_ENV_GUARD_A", "first")
+                .set("TEST_UTILITIES_ENV_GUARD_A", "second")
+                .set("TEST_UTILITIES_ENV_GUARD_B", "new");
+
+            assert_eq!(
+                std::env::var("TEST_UTILITIES_ENV_GUARD_A").unwrap(),
+                "second"
+            );
+            assert_eq!(std::env::var("TEST_UTILITIES_ENV_GUARD_B").unwrap(), "new");
+            assert_eq!(guard.keys().count(), 3);
+        }
+        assert_eq!(
+            std::env::var("TEST_UTILITIES_ENV_GUARD_A").unwrap(),
+            "original"
+        );
+        assert!(std::env::var_os("TEST_UTILITIES_ENV_GUARD_B").is_none());
+    }
+}
@@ -0,0 +1,73 @@
+use std::fmt;
+use std::io;
+
+/// Errors of the fallible APIs across the modules of the crate, for callers which handle them
+/// alike. Each module returns its own error type, which converts into this one.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    #[cfg(feature = "docker")]
+    Docker(crate::docker::Error),
+    #[cfg(any(feature = "fs", feature = "no-fs-write"))]
+    Quota(crate::fs::QuotaExceeded),
+    #[cfg(feature = "mongodb")]
+    Mongo(mongodb::error::Error),
+    Io(io::Error),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            #[cfg(feature = "docker")]
+            Error::Docker(err) => err.fmt(f),
+            #[cfg(any(feature = "fs", feature = "no-fs-write"))]
+            Error::Quota(err) => err.fmt(f),
+            #[cfg(feature = "mongodb")]
+            Error::Mongo(err) => err.fmt(f),
+            Error::Io(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            #[cfg(feature = "docker")]
+            Error::Docker(err) => err.source(),
+            #[cfg(any(feature = "fs", feature = "no-fs-write"))]
+            Error::Quota(err) => err.source(),
+            #[cfg(feature = "mongodb")]
+            Error::Mongo(err) => err.source(),
+            Error::Io(err) => err.source(),
+        }
+    }
+}
+
+#[cfg(feature = "docker")]
+impl From<crate::docker::Error> for Error {
+    fn from(err: crate::docker::Error) -> Self {
+        Error::Docker(err)
+    }
+}
+
+#[cfg(any(feature = "fs", feature = "no-fs-write"))]
+impl From<crate::fs::QuotaExceeded> for Error {
+    fn from(err: crate::fs::QuotaExceeded) -> Self {
+        Error::Quota(err)
+    }
+}
+
+#[cfg(feature = "mongodb")]
+impl From<mongodb::error::Error> for Error {
+    fn from(err: mongodb::error::Error) -> Self {
+        Error::Mongo(err)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
@@ -0,0 +1,222 @@
+//! A common interface to what tests set up and tear down, across the modules of the crate.
+//!
+//! ```no_run
+//! # async fn run() -> test_utilities::Result<()> {
+//! use test_utilities::docker::Builder;
+//! use test_utilities::env::EnvGuard;
+//! use test_utilities::fixture::FixtureStack;
+//!
+//! let mut fixtures = FixtureStack::new();
+//! let mongo = Builder::new("mongo").try_build_disposable().await?;
+//! let url = mongo.url()?;
+//! fixtures.push(mongo).await?;
+//! fixtures.push(EnvGuard::new().set("MONGO_URL", url)).await?;
+//! // the variable is restored before the container is stopped
+//! fixtures.teardown().await
+//! # }
+//! ```
+
+use std::any::Any;
+use std::panic::AssertUnwindSafe;
+
+use futures::future::BoxFuture;
+use futures::FutureExt;
+
+use crate::Result;
+
+/// Something a test sets up and tears down.
+///
+/// Fixtures still release what they hold when dropped without being torn down, e.g. by a
+/// panicking test, so tearing down only adds a chance to report failures.
+pub trait Fixture: Send + 'static {
+    /// Prepare the fixture, which does nothing for fixtures ready once constructed.
+    fn setup(&mut self) -> BoxFuture<'_, Result<()>> {
+        Box::pin(futures::future::ready(Ok(())))
+    }
+
+    /// Release what the fixture holds, which only drops it by default.
+    fn teardown(self: Box<Self>) -> BoxFuture<'static, Result<()>> {
+        drop(self);
+        Box::pin(futures::future::ready(Ok(())))
+    }
+}
+
+/// Fixtures torn down in the reverse order they were set up in, as later ones may depend on
+/// earlier ones.
+///
+/// Dropping the stack, e.g. while a test panics, drops the fixtures left in the same order.
+#[derive(Default)]
+pub struct FixtureStack {
+    fixtures: Vec<Entry>,
+}
+
+struct Entry {
+    fixture: Box<dyn Any + Send>,
+    teardown: fn(Box<dyn Any + Send>) -> BoxFuture<'static, Result<()>>,
+}
+
+impl FixtureStack {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Set up `fixture` and keep it, to be torn down before the fixtures kept so far. A fixture
+    /// failing to set up is dropped.
+    pub async fn push<F: Fixture>(&mut self, mut fixture: F) -> Result<&mut F> {
+        fixture.setup().await?;
+        self.fixtures.push(Entry {
+            fixture: Box::new(fixture),
+            teardown: teardown_any::<F>,
+        });
+        let fixture = self.fixtures.last_mut().unwrap().fixture.as_mut();
+        Ok(fixture.downcast_mut().unwrap())
+    }
+
+    /// The latest fixture of type `F` kept.
+    pub fn get<F: Fixture>(&self) -> Option<&F> {
+        self.fixtures
+            .iter()
+            .rev()
+            .find_map(|entry| entry.fixture.downcast_ref())
+    }
+
+    pub fn len(&self) -> usize {
+        self.fixtures.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fixtures.is_empty()
+    }
+
+    /// Tear down every fixture, even if some fail or panic, returning the first failure or
+    /// resuming the first panic afterwards.
+    pub async fn teardown(mut self) -> Result<()> {
+        let mut result = Ok(());
+        let mut panic = None;
+        while let Some(entry) = self.fixtures.pop() {
+            let teardown = (entry.teardown)(entry.fixture);
+            match AssertUnwindSafe(teardown).catch_unwind().await {
+                Ok(Ok(())) => {}
+                Ok(Err(err)) => {
+                    log::warn!("failed to tear down fixture: {err}");
+                    if result.is_ok() {
+                        result = Err(err);
+                    }
+                }
+                Err(payload) => {
+                    panic.get_or_insert(payload);
+                }
+            }
+        }
+        if let Some(payload) = panic {
+            std::panic::resume_unwind(payload);
+        }
+        result
+    }
+}
+
+impl Drop for FixtureStack {
+    fn drop(&mut self) {
+        while let Some(entry) = self.fixtures.pop() {
+            drop(entry);
+        }
+    }
+}
+
+fn teardown_any<F: Fixture>(fixture: Box<dyn Any + Send>) -> BoxFuture<'static, Result<()>> {
+    fixture.downcast::<F>().unwrap().teardown()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+
+    struct Recorded {
+        name: &'static str,
+        events: Arc<Mutex<Vec<String>>>,
+        fail: bool,
+    }
+
+    impl Fixture for Recorded {
+        fn setup(&mut self) -> BoxFuture<'_, Result<()>> {
+            self.events
+                .lock()
+                .unwrap()
+                .push(format!("setup {}", self.name));
+            Box::pin(futures::future::ready(Ok(())))
+        }
+
+        fn teardown(self: Box<Self>) -> BoxFuture<'static, Result<()>> {
+            Box::pin(async move {
+                self.events
+                    .lock()
+                    .unwrap()
+                    .push(format!("teardown {}", self.name));
+                if self.fail {
+                    panic!("{} failed to tear down", self.name);
+                }
+                Ok(())
+            })
+        }
+    }
+
+    impl Drop for Recorded {
+        fn drop(&mut self) {
+            self.events
+                .lock()
+                .unwrap()
+                .push(format!("drop {}", self.name));
+        }
+    }
+
+    fn recorded(name: &'static str, events: &Arc<Mutex<Vec<String>>>) -> Recorded {
+        Recorded {
+            name,
+            events: events.clone(),
+            fail: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_teardown_in_reverse_order() {
+        let events = Arc::default();
+        let mut fixtures = FixtureStack::new();
+        fixtures.push(recorded("a", &events)).await.unwrap();
+        fixtures.push(recorded("b", &events)).await.unwrap().fail = true;
+        fixtures.push(recorded("c", &events)).await.unwrap();
+        assert_eq!(fixtures.get::<Recorded>().unwrap().name, "c");
+
+        let result = AssertUnwindSafe(fixtures.teardown()).catch_unwind().await;
+        assert!(result.is_err());
+        assert_eq!(
+            *events.lock().unwrap(),
+            [
+                "setup a",
+                "setup b",
+                "setup c",
+                "teardown c",
+                "drop c",
+                "teardown b",
+                "drop b",
+                "teardown a",
+                "drop a"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_drop_in_reverse_order() {
+        let events = Arc::default();
+        let mut fixtures = FixtureStack::new();
+        fixtures.push(recorded("a", &events)).await.unwrap();
+        fixtures.push(recorded("b", &events)).await.unwrap();
+        drop(fixtures);
+
+        assert_eq!(
+            *events.lock().unwrap(),
+            ["setup a", "setup b", "drop b", "drop a"]
+        );
+    }
+}
@@ -1,16 +1,115 @@
+//! Fake files and directories.
+//!
+//! With the `no-fs-write` feature alone, only the content generation is available, writing
+//! into caller-supplied writers, for environments such as wasm where temp files are not.
+
+use std::io::{self, Cursor, Write};
+#[cfg(feature = "fs")]
+use std::path::Path;
+
 use fake::faker::lorem::en::Words;
 use fake::{Dummy, Fake, Faker};
+#[cfg(feature = "fs")]
+use futures::future::BoxFuture;
 use rand::Rng;
-use tempfile::{NamedTempFile, TempPath};
+#[cfg(feature = "fs")]
+use tempfile::{NamedTempFile, TempDir, TempPath};
+
+#[cfg(feature = "fs")]
+use crate::fixture::Fixture;
+use crate::rng::TestRng;
+
+#[cfg(feature = "fs")]
+pub use chunked::{ChunkedFile, ChunkedFileFaker};
+#[cfg(feature = "fs")]
+pub use corpus::cached_corpus;
+#[cfg(feature = "fs")]
+pub use diff::{assert_dir_eq, Difference, DirComparison};
+pub use filename::{fake_filename, fake_filename_with_rng, Charset};
+#[cfg(feature = "dataset-extensions")]
+pub use filename::{fake_realistic_filename, fake_realistic_filename_with_rng};
+#[cfg(feature = "fs")]
+pub use growing::{GrowingFile, GrowingFileFaker};
+#[cfg(feature = "fs")]
+pub use pair::{TempFilePair, TempFilePairFaker};
+pub use path::{assert_path_eq, normalize_for_comparison};
+pub use quota::{set_max_total_bytes, total_generated_bytes, QuotaExceeded, MAX_TOTAL_BYTES_ENV};
+#[cfg(feature = "fs")]
+pub use random_access::{RandomAccessFile, RandomAccessFileFaker};
+#[cfg(feature = "fs")]
+pub use small_files::{SmallFiles, SmallFilesFaker};
+#[cfg(feature = "fs")]
+pub use storage::DirStorage;
+pub use storage::Storage;
+#[cfg(feature = "fs")]
+pub use temp_root::{set_temp_root, temp_root, TEMP_ROOT_ENV};
+#[cfg(feature = "fs")]
+pub use tree::{PatternError, TempTree, TempTreeFaker};
+
+#[cfg(feature = "gridfs")]
+pub(crate) use stream::FakeContentReader;
+
+pub(crate) use quota::Budget;
 
+use metadata::FileMetadata;
+
+#[cfg(feature = "fs")]
+mod chunked;
+#[cfg(feature = "fs")]
+mod corpus;
+#[cfg(feature = "fs")]
+mod diff;
+mod entropy;
+mod filename;
+#[cfg(feature = "fs")]
+mod growing;
+mod markup;
+mod metadata;
+#[cfg(feature = "fs")]
+mod pair;
+mod path;
+mod quota;
+#[cfg(feature = "fs")]
+mod random_access;
+#[cfg(feature = "fs")]
+mod small_files;
+mod storage;
+#[cfg(feature = "gridfs")]
+mod stream;
+#[cfg(feature = "fs")]
+mod temp_root;
+#[cfg(feature = "fs")]
+mod tree;
+
+/// Kind of the content of fake files. Variants depend on the features enabled, so matches on
+/// it need a wildcard arm.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum TempFileKind {
     Text,
+    /// A well-formed XML document, whose length is its number of elements
+    Xml {
+        depth: usize,
+    },
+    /// A well-formed HTML page, whose length is the number of elements in its body
+    Html {
+        depth: usize,
+    },
+    /// ASCII text of 64-byte lines, whose length is its number of lines, with a percentage of
+    /// random characters from 0, the same line repeated, to 100, no repetition at all
+    Entropy(u8),
+    /// Email addresses drawn from `datasets`, one per line, whose length is its number of lines
+    #[cfg(all(feature = "dataset-usernames", feature = "dataset-domains"))]
+    Emails,
 }
 
 pub struct TempFileFaker<L = Faker> {
     kind: TempFileKind,
     len: L,
     include_content: bool,
+    metadata: FileMetadata,
+    /// Bytes a single fake call may generate
+    max_total_bytes: Option<usize>,
 }
 
 impl TempFileFaker<Faker> {
@@ -19,6 +118,8 @@ impl TempFileFaker<Faker> {
             kind: TempFileKind::Text,
             len: Faker,
             include_content: true,
+            metadata: Default::default(),
+            max_total_bytes: None,
         }
     }
 }
@@ -29,6 +130,8 @@ impl<T> TempFileFaker<T> {
             kind: TempFileKind::Text,
             len,
             include_content: true,
+            metadata: Default::default(),
+            max_total_bytes: None,
         }
     }
 
@@ -42,30 +145,92 @@ impl<T> TempFileFaker<T> {
         self
     }
 
+    /// Fail instead of generating more than `max` bytes in a single fake call, on top of the
+    /// process-wide cap of `set_max_total_bytes`.
+    pub fn max_total_bytes(mut self, max: usize) -> Self {
+        self.max_total_bytes = Some(max);
+        self
+    }
+
+    /// Set an extended attribute, e.g. `user.origin`, on the generated files.
+    #[cfg(feature = "xattr")]
+    pub fn xattr<S: Into<String>, V: Into<Vec<u8>>>(mut self, name: S, value: V) -> Self {
+        self.metadata.xattrs.push((name.into(), value.into()));
+        self
+    }
+
+    /// Add an ACL entry to the generated files, in the syntax of `setfacl -m` on Linux (e.g.
+    /// `u:nobody:r`) or of `chmod +a` on macOS (e.g. `nobody allow read`).
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    pub fn acl<S: Into<String>>(mut self, entry: S) -> Self {
+        self.metadata.acl.push(entry.into());
+        self
+    }
+
+    /// Generate the content into memory instead of a file on disk, for parsing tests which
+    /// only need something to `Read` and `Seek`.
+    pub fn in_memory(&self) -> Cursor<Vec<u8>>
+    where
+        u8: Dummy<T>,
+    {
+        self.fake_with_rng(&mut TestRng)
+    }
+
+    /// Generate the content into `writer` rather than a file, returning the number of bytes
+    /// written.
+    pub fn write_to<W: Write>(&self, writer: W) -> io::Result<usize>
+    where
+        u8: Dummy<T>,
+    {
+        self.write_to_with_rng(writer, &mut TestRng)
+    }
+
+    pub fn write_to_with_rng<W: Write, R: Rng + ?Sized>(
+        &self,
+        mut writer: W,
+        rng: &mut R,
+    ) -> io::Result<usize>
+    where
+        u8: Dummy<T>,
+    {
+        let len = self.len.fake_with_rng::<u8, R>(rng) as usize;
+        let mut budget = Budget::new(self.max_total_bytes);
+        let content = try_fake_content(&self.kind, len, &mut budget, rng)?;
+        writer.write_all(&content)?;
+        Ok(content.len())
+    }
+
     pub fn len<U>(self, len: U) -> TempFileFaker<U> {
         TempFileFaker::<U> {
             kind: self.kind,
             len,
             include_content: self.include_content,
+            metadata: self.metadata,
+            max_total_bytes: self.max_total_bytes,
         }
     }
 }
 
+#[cfg(feature = "fs")]
 pub struct TempFile {
     pub path: TempPath,
     pub content: Option<Vec<u8>>,
 }
 
+#[cfg(feature = "fs")]
 impl<L> Dummy<TempFileFaker<L>> for TempFile
 where
     u8: Dummy<L>,
 {
     fn dummy_with_rng<R: Rng + ?Sized>(config: &TempFileFaker<L>, mut rng: &mut R) -> Self {
         let len = config.len.fake_with_rng::<u8, R>(rng) as usize;
-        let content = fake_content(&config.kind, len, &mut rng);
+        let mut budget = Budget::new(config.max_total_bytes);
+        let content = try_fake_content(&config.kind, len, &mut budget, &mut rng)
+            .unwrap_or_else(|err| panic!("{err}"));
 
-        let path = NamedTempFile::new().unwrap().into_temp_path();
+        let path = temp_file().into_temp_path();
         std::fs::write(&path, &content).unwrap();
+        config.metadata.apply(&path).unwrap();
 
         TempFile {
             path,
@@ -78,6 +243,7 @@ where
     }
 }
 
+#[cfg(feature = "fs")]
 impl<L> Dummy<TempFileFaker<L>> for TempPath
 where
     u8: Dummy<L>,
@@ -87,6 +253,95 @@ where
     }
 }
 
+/// Removes the file when torn down, returning a failure to the caller where dropping the file
+/// would ignore it.
+#[cfg(feature = "fs")]
+impl Fixture for TempFile {
+    fn teardown(self: Box<Self>) -> BoxFuture<'static, crate::Result<()>> {
+        Box::pin(futures::future::ready(
+            self.path.close().map_err(Into::into),
+        ))
+    }
+}
+
+/// Removes the directory and everything in it when torn down, failing if any of it is left,
+/// which dropping the directory would silently leave behind.
+#[cfg(feature = "fs")]
+impl Fixture for TempDir {
+    fn teardown(self: Box<Self>) -> BoxFuture<'static, crate::Result<()>> {
+        Box::pin(futures::future::ready((*self).close().map_err(Into::into)))
+    }
+}
+
+impl<L> Dummy<TempFileFaker<L>> for Cursor<Vec<u8>>
+where
+    u8: Dummy<L>,
+{
+    fn dummy_with_rng<R: Rng + ?Sized>(config: &TempFileFaker<L>, mut rng: &mut R) -> Self {
+        let len = config.len.fake_with_rng::<u8, R>(rng) as usize;
+        let mut budget = Budget::new(config.max_total_bytes);
+        let content = try_fake_content(&config.kind, len, &mut budget, &mut rng)
+            .unwrap_or_else(|err| panic!("{err}"));
+        Cursor::new(content)
+    }
+}
+
+#[cfg(feature = "fs")]
+/// Create an empty temporary directory which is removed on drop.
+pub fn temp_dir() -> TempDir {
+    let dir = tempfile::Builder::new()
+        .prefix("test-utilities-")
+        .tempdir_in(temp_root().unwrap())
+        .unwrap();
+    track(dir.path());
+    dir
+}
+
+/// Create an empty temporary file which is removed on drop.
+#[cfg(feature = "fs")]
+pub(crate) fn temp_file() -> NamedTempFile {
+    let file = NamedTempFile::new_in(temp_root().unwrap()).unwrap();
+    track(file.path());
+    file
+}
+
+/// Record `path` for `leakcheck` to find it if left behind.
+#[cfg(feature = "fs")]
+fn track(path: &Path) {
+    crate::leakcheck::record(crate::leakcheck::Leak::TempPath(path.to_path_buf()));
+}
+
+/// Generate content like `fake_content` under `budget`, charging the least bytes `len` units of
+/// `kind` take before generating, so that a misconfigured length fails without allocating.
+pub(crate) fn try_fake_content<R: Rng + ?Sized>(
+    kind: &TempFileKind,
+    len: usize,
+    budget: &mut Budget,
+    rng: &mut R,
+) -> Result<Vec<u8>, QuotaExceeded> {
+    let least = min_content_len(kind, len);
+    budget.try_spend(least)?;
+    let content = fake_content(kind, len, rng);
+    budget.try_spend(content.len().saturating_sub(least))?;
+    Ok(content)
+}
+
+/// A lower bound of the bytes `fake_content` generates for `len` units of `kind`.
+fn min_content_len(kind: &TempFileKind, len: usize) -> usize {
+    match kind {
+        // words of a letter at least, separated by spaces
+        TempFileKind::Text => len.saturating_mul(2).saturating_sub(1),
+        // `<a id="0"></a>`
+        TempFileKind::Xml { .. } => len.saturating_mul(14),
+        // `<p></p>`
+        TempFileKind::Html { .. } => len.saturating_mul(7),
+        TempFileKind::Entropy(_) => len.saturating_mul(entropy::LINE_LEN),
+        // `a@b.c` and a newline
+        #[cfg(all(feature = "dataset-usernames", feature = "dataset-domains"))]
+        TempFileKind::Emails => len.saturating_mul(6),
+    }
+}
+
 pub(crate) fn fake_content<R: Rng + ?Sized>(
     kind: &TempFileKind,
     len: usize,
@@ -97,6 +352,14 @@ pub(crate) fn fake_content<R: Rng + ?Sized>(
             .fake_with_rng::<Vec<String>, R>(rng)
             .join(" ")
             .into_bytes(),
+        TempFileKind::Xml { depth } => markup::fake_xml(len, *depth, rng).into_bytes(),
+        TempFileKind::Html { depth } => markup::fake_html(len, *depth, rng).into_bytes(),
+        TempFileKind::Entropy(level) => entropy::fake_entropic_text(len, *level, rng),
+        #[cfg(all(feature = "dataset-usernames", feature = "dataset-domains"))]
+        TempFileKind::Emails => (0..len)
+            .map(|_| crate::datasets::fake_email_with_rng(rng) + "\n")
+            .collect::<String>()
+            .into_bytes(),
     }
 }
 
@@ -104,6 +367,7 @@ pub(crate) fn fake_content<R: Rng + ?Sized>(
 mod tests {
     use super::*;
 
+    #[cfg(feature = "fs")]
     #[test]
     fn test_fake_temp_file_new_with_content() {
         let temp_path: std::path::PathBuf;
@@ -130,6 +394,7 @@ mod tests {
         assert!(!temp_path.exists());
     }
 
+    #[cfg(feature = "fs")]
     #[test]
     fn test_fake_temp_file_new_without_content() {
         let temp_path: std::path::PathBuf;
@@ -153,6 +418,7 @@ mod tests {
         assert!(!temp_path.exists());
     }
 
+    #[cfg(feature = "fs")]
     #[test]
     fn test_fake_temp_file_with() {
         let temp_path: std::path::PathBuf;
@@ -173,6 +439,119 @@ mod tests {
         assert!(!temp_path.exists());
     }
 
+    #[cfg(all(feature = "fs", feature = "xattr"))]
+    #[test]
+    fn test_fake_temp_file_with_xattr() {
+        let temp_file = TempFileFaker::with_len(1..5)
+            .xattr("user.origin", "fixture")
+            .fake::<TempFile>();
+
+        let value = xattr::get(&temp_file.path, "user.origin").unwrap();
+        assert_eq!(value, Some(b"fixture".to_vec()));
+    }
+
+    #[test]
+    fn test_write_to() {
+        let mut content = Vec::new();
+        let written = TempFileFaker::with_len(20..40)
+            .kind(TempFileKind::Html { depth: 2 })
+            .write_to(&mut content)
+            .unwrap();
+
+        assert_eq!(written, content.len());
+        assert!(String::from_utf8(content)
+            .unwrap()
+            .starts_with("<!DOCTYPE html>"));
+    }
+
+    #[test]
+    fn test_max_total_bytes() {
+        let err = TempFileFaker::with_len(20..40)
+            .max_total_bytes(10)
+            .write_to(io::sink())
+            .unwrap_err();
+        let err = err
+            .into_inner()
+            .unwrap()
+            .downcast::<QuotaExceeded>()
+            .unwrap();
+        assert_eq!(err.limit, 10);
+        assert!(!err.global);
+
+        let result = std::panic::catch_unwind(|| {
+            TempFileFaker::with_len(20..40)
+                .max_total_bytes(10)
+                .in_memory()
+        });
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_budget_checked_before_generating() {
+        let err = TempFileFaker::with_len(100..101)
+            .kind(TempFileKind::Entropy(50))
+            .max_total_bytes(100 * 64 - 1)
+            .write_to(io::sink())
+            .unwrap_err()
+            .into_inner()
+            .unwrap()
+            .downcast::<QuotaExceeded>()
+            .unwrap();
+        assert_eq!(err.requested, 100 * 64);
+        assert_eq!(err.used, 0);
+
+        let kinds = [
+            TempFileKind::Text,
+            TempFileKind::Xml { depth: 3 },
+            TempFileKind::Html { depth: 3 },
+            TempFileKind::Entropy(0),
+        ];
+        for kind in kinds {
+            for len in [0, 1, 10] {
+                let content = fake_content(&kind, len, &mut rand::thread_rng());
+                assert!(
+                    content.len() >= min_content_len(&kind, len),
+                    "{kind:?} {len}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_fake_in_memory_file() {
+        use std::io::{Read, Seek, SeekFrom};
+
+        let range = 20..40;
+        let mut file = TempFileFaker::with_len(range.clone())
+            .kind(TempFileKind::Text)
+            .in_memory();
+
+        let mut content = String::new();
+        file.read_to_string(&mut content).unwrap();
+        let words = content.split(' ').count() as u8;
+        assert!(range.contains(&words));
+
+        file.seek(SeekFrom::Start(0)).unwrap();
+        let mut first = [0u8; 1];
+        file.read_exact(&mut first).unwrap();
+        assert_eq!(first[0], content.as_bytes()[0]);
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_temp_dir() {
+        let dir_path: std::path::PathBuf;
+        {
+            let dir = temp_dir();
+            dir_path = dir.path().to_path_buf();
+
+            assert!(dir_path.is_dir());
+            assert_eq!(std::fs::read_dir(&dir_path).unwrap().count(), 0);
+        }
+        assert!(!dir_path.exists());
+    }
+
+    #[cfg(feature = "fs")]
     #[test]
     fn test_fake_temp_path() {
         let temp_path: std::path::PathBuf;
@@ -0,0 +1,174 @@
+use std::io::Write;
+use std::ops::Range;
+
+use fake::{Dummy, Fake, Faker};
+use rand::Rng;
+use tempfile::TempPath;
+
+use super::{temp_file, try_fake_content, Budget, TempFileKind};
+
+/// Faker of temp files written in chunks of random sizes, optionally "crashing" part way
+/// through, for testing readers which must cope with partially written files.
+pub struct ChunkedFileFaker<L = Faker> {
+    kind: TempFileKind,
+    len: L,
+    chunk_size: Range<usize>,
+    fsync: bool,
+    crash: bool,
+    max_total_bytes: Option<usize>,
+}
+
+impl ChunkedFileFaker<Faker> {
+    pub fn new() -> ChunkedFileFaker<Faker> {
+        ChunkedFileFaker {
+            kind: TempFileKind::Text,
+            len: Faker,
+            chunk_size: 1..64,
+            fsync: false,
+            crash: false,
+            max_total_bytes: None,
+        }
+    }
+}
+
+impl Default for ChunkedFileFaker<Faker> {
+    fn default() -> Self {
+        ChunkedFileFaker::new()
+    }
+}
+
+impl<L> ChunkedFileFaker<L> {
+    pub fn kind(mut self, kind: TempFileKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    /// Range of the size of each written chunk, in bytes.
+    pub fn chunk_size(mut self, chunk_size: Range<usize>) -> Self {
+        let start = chunk_size.start.max(1);
+        self.chunk_size = start..chunk_size.end.max(start + 1);
+        self
+    }
+
+    /// Whether to sync the file to disk after each chunk.
+    pub fn fsync(mut self, fsync: bool) -> Self {
+        self.fsync = fsync;
+        self
+    }
+
+    /// Whether to stop writing at a random offset before the content is complete.
+    pub fn crash(mut self, crash: bool) -> Self {
+        self.crash = crash;
+        self
+    }
+
+    /// Fail instead of generating files of more than `max` bytes.
+    pub fn max_total_bytes(mut self, max: usize) -> Self {
+        self.max_total_bytes = Some(max);
+        self
+    }
+
+    pub fn len<U>(self, len: U) -> ChunkedFileFaker<U> {
+        ChunkedFileFaker {
+            kind: self.kind,
+            len,
+            chunk_size: self.chunk_size,
+            fsync: self.fsync,
+            crash: self.crash,
+            max_total_bytes: self.max_total_bytes,
+        }
+    }
+}
+
+pub struct ChunkedFile {
+    pub path: TempPath,
+    /// The complete content the file would have had without a crash
+    pub content: Vec<u8>,
+    /// Sizes of the chunks in the order they were written
+    pub chunks: Vec<usize>,
+}
+
+impl ChunkedFile {
+    /// Number of bytes which made it into the file.
+    pub fn written(&self) -> usize {
+        self.chunks.iter().sum()
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.written() == self.content.len()
+    }
+}
+
+impl<L> Dummy<ChunkedFileFaker<L>> for ChunkedFile
+where
+    u8: Dummy<L>,
+{
+    fn dummy_with_rng<R: Rng + ?Sized>(config: &ChunkedFileFaker<L>, mut rng: &mut R) -> Self {
+        let len = config.len.fake_with_rng::<u8, R>(rng) as usize;
+        let mut budget = Budget::new(config.max_total_bytes);
+        let content = try_fake_content(&config.kind, len, &mut budget, &mut rng)
+            .unwrap_or_else(|err| panic!("{err}"));
+        let stop_at = if config.crash && !content.is_empty() {
+            rng.gen_range(0..content.len())
+        } else {
+            content.len()
+        };
+
+        let (mut file, path) = temp_file().into_parts();
+        let mut chunks = Vec::new();
+        let mut offset = 0;
+        while offset < stop_at {
+            let size = rng
+                .gen_range(config.chunk_size.clone())
+                .min(stop_at - offset);
+            file.write_all(&content[offset..offset + size]).unwrap();
+            if config.fsync {
+                file.sync_data().unwrap();
+            }
+            chunks.push(size);
+            offset += size;
+        }
+
+        ChunkedFile {
+            path,
+            content,
+            chunks,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fake_chunked_file() {
+        let chunked_file = ChunkedFileFaker::new()
+            .len(20..40)
+            .chunk_size(4..8)
+            .fsync(true)
+            .fake::<ChunkedFile>();
+
+        assert!(chunked_file.is_complete());
+        assert!(chunked_file.chunks[..chunked_file.chunks.len() - 1]
+            .iter()
+            .all(|size| (4..8).contains(size)));
+        assert_eq!(
+            std::fs::read(&chunked_file.path).unwrap(),
+            chunked_file.content
+        );
+    }
+
+    #[test]
+    fn test_fake_crashed_chunked_file() {
+        let chunked_file = ChunkedFileFaker::new()
+            .len(20..40)
+            .crash(true)
+            .fake::<ChunkedFile>();
+
+        let written = std::fs::read(&chunked_file.path).unwrap();
+        assert!(!chunked_file.is_complete());
+        assert_eq!(written.len(), chunked_file.written());
+        assert_eq!(written, chunked_file.content[..written.len()]);
+    }
+}
@@ -0,0 +1,177 @@
+use std::fmt::Write as _;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+/// Materialize the corpus `name` generated by `generate` from `seed` under
+/// `test-fixtures/<seed>/<name>/` of the target dir, or reuse it if a previous run already did
+/// so and its files are intact.
+///
+/// `generate` must write the corpus into the given directory and derive all of its randomness
+/// from the given rng, so that the corpus is reproducible from the seed alone.
+pub fn cached_corpus<S, F>(name: S, seed: u64, generate: F) -> io::Result<PathBuf>
+where
+    S: AsRef<str>,
+    F: FnOnce(&Path, &mut StdRng) -> io::Result<()>,
+{
+    let seed_dir = fixtures_dir().join(seed.to_string());
+    let root = seed_dir.join(name.as_ref());
+    let manifest_path = seed_dir.join(format!("{}.manifest", name.as_ref()));
+
+    if let Ok(manifest) = std::fs::read_to_string(&manifest_path) {
+        if root.is_dir() && manifest == build_manifest(&root)? {
+            return Ok(root);
+        }
+        log::warn!(
+            "cached corpus {} is corrupted, regenerating",
+            root.display()
+        );
+    }
+
+    // generate aside and move into place, so no half-written corpus is ever observed
+    std::fs::create_dir_all(&seed_dir)?;
+    let staging = tempfile::Builder::new()
+        .prefix(".staging-")
+        .tempdir_in(&seed_dir)?;
+    generate(staging.path(), &mut StdRng::seed_from_u64(seed))?;
+    let manifest = build_manifest(staging.path())?;
+
+    let _ = std::fs::remove_file(&manifest_path);
+    if root.exists() {
+        std::fs::remove_dir_all(&root)?;
+    }
+    // the staging dir is gone after the rename, so dropping it afterwards is a no-op
+    std::fs::rename(staging.path(), &root)?;
+    std::fs::write(&manifest_path, manifest)?;
+    Ok(root)
+}
+
+/// `test-fixtures` in the target dir of cargo, which is shared by the whole workspace unlike the
+/// dir of the manifest, or in the temp dir when run outside of cargo.
+fn fixtures_dir() -> PathBuf {
+    std::env::var_os("CARGO_TARGET_DIR")
+        .map(PathBuf::from)
+        .or_else(|| target_dir_of(&std::env::current_exe().ok()?))
+        .unwrap_or_else(|| std::env::temp_dir().join("test-utilities"))
+        .join("test-fixtures")
+}
+
+/// Target dir of the test binary at `exe`, built by cargo into `<target>/<profile>/deps/`, or
+/// `<target>/<triple>/<profile>/deps/` when cross compiling.
+fn target_dir_of(exe: &Path) -> Option<PathBuf> {
+    let deps = exe.parent().filter(|dir| dir.ends_with("deps"))?;
+    let profile = deps.parent()?;
+    let target = profile.parent()?;
+    // a triple dir holds no `CACHEDIR.TAG`, which cargo writes at the root of the target dir
+    match target.parent() {
+        Some(root)
+            if !target.join("CACHEDIR.TAG").exists() && root.join("CACHEDIR.TAG").exists() =>
+        {
+            Some(root.to_path_buf())
+        }
+        _ => Some(target.to_path_buf()),
+    }
+}
+
+/// List every file below `root` with its size and content hash, one per line.
+fn build_manifest(root: &Path) -> io::Result<String> {
+    let mut files = Vec::new();
+    let mut pending = vec![root.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                pending.push(path);
+            } else {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+
+    let mut manifest = String::new();
+    for path in files {
+        let relative = path.strip_prefix(root).unwrap();
+        let len = std::fs::metadata(&path)?.len();
+        let hash = fnv1a(std::fs::File::open(&path)?)?;
+        writeln!(manifest, "{}\t{len}\t{hash:016x}", relative.display()).unwrap();
+    }
+    Ok(manifest)
+}
+
+/// 64-bit FNV-1a, which unlike the std hasher is stable across toolchains.
+fn fnv1a<R: Read>(mut reader: R) -> io::Result<u64> {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    let mut buf = [0u8; 8192];
+    loop {
+        let n = reader.read(&mut buf)?;
+        if n == 0 {
+            return Ok(hash);
+        }
+        for byte in &buf[..n] {
+            hash = (hash ^ *byte as u64).wrapping_mul(0x100000001b3);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use fake::Fake;
+
+    use super::super::TempFileFaker;
+    use super::*;
+
+    fn generate(root: &Path, rng: &mut StdRng) -> io::Result<()> {
+        let faker = TempFileFaker::with_len(5..50);
+        for i in 0..10 {
+            let content = faker.fake_with_rng::<Cursor<Vec<u8>>, _>(rng).into_inner();
+            std::fs::write(root.join(format!("doc-{i}.txt")), content)?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_target_dir_of() {
+        let target = tempfile::tempdir().unwrap();
+        std::fs::write(target.path().join("CACHEDIR.TAG"), "").unwrap();
+        let exe = |dir: &str| target.path().join(dir).join("deps/tests-0123");
+
+        assert_eq!(target_dir_of(&exe("debug")).unwrap(), target.path());
+        assert_eq!(
+            target_dir_of(&exe("x86_64-unknown-linux-gnu/release")).unwrap(),
+            target.path()
+        );
+        assert_eq!(target_dir_of(Path::new("/usr/bin/tests")), None);
+    }
+
+    #[test]
+    fn test_cached_corpus_is_reused() {
+        let name = format!("corpus-{}", std::process::id());
+        let generated = std::cell::Cell::new(0);
+        let count_generate = |root: &Path, rng: &mut StdRng| {
+            generated.set(generated.get() + 1);
+            generate(root, rng)
+        };
+
+        let root = cached_corpus(&name, 42, count_generate).unwrap();
+        let manifest = build_manifest(&root).unwrap();
+        let reused = cached_corpus(&name, 42, count_generate).unwrap();
+
+        assert_eq!(root, reused);
+        assert_eq!(generated.get(), 1);
+        assert_eq!(manifest.lines().count(), 10);
+
+        // a corrupted corpus is regenerated into the very same content
+        std::fs::write(root.join("doc-0.txt"), "tampered").unwrap();
+        let regenerated = cached_corpus(&name, 42, count_generate).unwrap();
+        assert_eq!(generated.get(), 2);
+        assert_eq!(build_manifest(&regenerated).unwrap(), manifest);
+
+        std::fs::remove_dir_all(&root).unwrap();
+        std::fs::remove_file(root.with_extension("manifest")).unwrap();
+    }
+}
@@ -0,0 +1,274 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::hash::Hasher;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Assert that the two directory trees have the same entries with the same contents, panicking
+/// with a readable report of every difference otherwise.
+pub fn assert_dir_eq<P: AsRef<Path>, Q: AsRef<Path>>(expected_root: P, actual_root: Q) {
+    DirComparison::new().assert_eq(expected_root, actual_root)
+}
+
+/// Recursive comparison of two directory trees by names, sizes and content hashes, and
+/// optionally by modification times and permissions.
+#[derive(Clone, Debug, Default)]
+pub struct DirComparison {
+    mtime: bool,
+    permissions: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Difference {
+    Missing(PathBuf),
+    Unexpected(PathBuf),
+    KindMismatch(PathBuf),
+    SizeMismatch {
+        path: PathBuf,
+        expected: u64,
+        actual: u64,
+    },
+    ContentMismatch(PathBuf),
+    MtimeMismatch(PathBuf),
+    PermissionsMismatch(PathBuf),
+}
+
+impl fmt::Display for Difference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Difference::Missing(path) => write!(f, "missing:     {}", path.display()),
+            Difference::Unexpected(path) => write!(f, "unexpected:  {}", path.display()),
+            Difference::KindMismatch(path) => {
+                write!(f, "kind:        {} (file vs directory)", path.display())
+            }
+            Difference::SizeMismatch {
+                path,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "size:        {} (expected {expected} bytes, got {actual})",
+                path.display()
+            ),
+            Difference::ContentMismatch(path) => write!(f, "content:     {}", path.display()),
+            Difference::MtimeMismatch(path) => write!(f, "mtime:       {}", path.display()),
+            Difference::PermissionsMismatch(path) => {
+                write!(f, "permissions: {}", path.display())
+            }
+        }
+    }
+}
+
+#[derive(PartialEq)]
+enum EntryKind {
+    Dir,
+    File { size: u64, hash: u64 },
+}
+
+struct Entry {
+    kind: EntryKind,
+    mtime: Option<SystemTime>,
+    permissions: std::fs::Permissions,
+}
+
+impl DirComparison {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn compare_mtime(mut self, mtime: bool) -> Self {
+        self.mtime = mtime;
+        self
+    }
+
+    pub fn compare_permissions(mut self, permissions: bool) -> Self {
+        self.permissions = permissions;
+        self
+    }
+
+    /// List the differences of `actual_root` against `expected_root`, ordered by path.
+    pub fn diff<P: AsRef<Path>, Q: AsRef<Path>>(
+        &self,
+        expected_root: P,
+        actual_root: Q,
+    ) -> io::Result<Vec<Difference>> {
+        let expected = scan(expected_root.as_ref())?;
+        let mut actual = scan(actual_root.as_ref())?;
+
+        let mut differences = Vec::new();
+        for (path, expected) in expected {
+            let actual = match actual.remove(&path) {
+                Some(actual) => actual,
+                None => {
+                    differences.push(Difference::Missing(path));
+                    continue;
+                }
+            };
+
+            match (&expected.kind, &actual.kind) {
+                (EntryKind::Dir, EntryKind::Dir) => {}
+                (
+                    EntryKind::File {
+                        size: expected_size,
+                        hash: expected_hash,
+                    },
+                    EntryKind::File { size, hash },
+                ) => {
+                    if expected_size != size {
+                        differences.push(Difference::SizeMismatch {
+                            path: path.clone(),
+                            expected: *expected_size,
+                            actual: *size,
+                        });
+                    } else if expected_hash != hash {
+                        differences.push(Difference::ContentMismatch(path.clone()));
+                    }
+                }
+                _ => {
+                    differences.push(Difference::KindMismatch(path));
+                    continue;
+                }
+            }
+            if self.mtime && expected.mtime != actual.mtime {
+                differences.push(Difference::MtimeMismatch(path.clone()));
+            }
+            if self.permissions && expected.permissions != actual.permissions {
+                differences.push(Difference::PermissionsMismatch(path));
+            }
+        }
+        differences.extend(actual.into_keys().map(Difference::Unexpected));
+        differences.sort_by(|a, b| a.path().cmp(b.path()));
+        Ok(differences)
+    }
+
+    pub fn assert_eq<P: AsRef<Path>, Q: AsRef<Path>>(&self, expected_root: P, actual_root: Q) {
+        let (expected_root, actual_root) = (expected_root.as_ref(), actual_root.as_ref());
+        let differences = self.diff(expected_root, actual_root).unwrap();
+        if !differences.is_empty() {
+            let report = differences
+                .iter()
+                .map(|difference| format!("  {difference}"))
+                .collect::<Vec<_>>()
+                .join("\n");
+            panic!(
+                "directory {} differs from {}:\n{report}",
+                actual_root.display(),
+                expected_root.display()
+            );
+        }
+    }
+}
+
+impl Difference {
+    pub fn path(&self) -> &Path {
+        match self {
+            Difference::Missing(path)
+            | Difference::Unexpected(path)
+            | Difference::KindMismatch(path)
+            | Difference::SizeMismatch { path, .. }
+            | Difference::ContentMismatch(path)
+            | Difference::MtimeMismatch(path)
+            | Difference::PermissionsMismatch(path) => path,
+        }
+    }
+}
+
+/// Collect the entries below `root`, keyed by their paths relative to `root`.
+fn scan(root: &Path) -> io::Result<BTreeMap<PathBuf, Entry>> {
+    let mut entries = BTreeMap::new();
+    let mut pending = vec![root.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        for dir_entry in std::fs::read_dir(&dir)? {
+            let path = dir_entry?.path();
+            let metadata = std::fs::metadata(&path)?;
+            let kind = if metadata.is_dir() {
+                pending.push(path.clone());
+                EntryKind::Dir
+            } else {
+                EntryKind::File {
+                    size: metadata.len(),
+                    hash: hash_file(&path)?,
+                }
+            };
+
+            let entry = Entry {
+                kind,
+                mtime: metadata.modified().ok(),
+                permissions: metadata.permissions(),
+            };
+            entries.insert(path.strip_prefix(root).unwrap().to_path_buf(), entry);
+        }
+    }
+    Ok(entries)
+}
+
+fn hash_file(path: &Path) -> io::Result<u64> {
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = DefaultHasher::new();
+    let mut buf = [0u8; 8192];
+    loop {
+        match file.read(&mut buf)? {
+            0 => return Ok(hasher.finish()),
+            n => hasher.write(&buf[..n]),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::temp_dir;
+    use super::*;
+
+    fn populate(root: &Path) {
+        std::fs::create_dir_all(root.join("logs/2024")).unwrap();
+        std::fs::write(root.join("logs/2024/app.log"), "started\n").unwrap();
+        std::fs::write(root.join("README"), "hello").unwrap();
+    }
+
+    #[test]
+    fn test_assert_dir_eq_on_equal_trees() {
+        let (expected, actual) = (temp_dir(), temp_dir());
+        populate(expected.path());
+        populate(actual.path());
+
+        assert_dir_eq(expected.path(), actual.path());
+    }
+
+    #[test]
+    fn test_diff_reports_every_difference() {
+        let (expected, actual) = (temp_dir(), temp_dir());
+        populate(expected.path());
+        populate(actual.path());
+        std::fs::write(actual.path().join("README"), "hallo").unwrap();
+        std::fs::remove_dir_all(actual.path().join("logs")).unwrap();
+        std::fs::create_dir(actual.path().join("logs")).unwrap();
+        std::fs::write(actual.path().join("extra"), "").unwrap();
+
+        let differences = DirComparison::new()
+            .diff(expected.path(), actual.path())
+            .unwrap();
+
+        assert_eq!(
+            differences,
+            vec![
+                Difference::ContentMismatch(PathBuf::from("README")),
+                Difference::Unexpected(PathBuf::from("extra")),
+                Difference::Missing(PathBuf::from("logs/2024")),
+                Difference::Missing(PathBuf::from("logs/2024/app.log")),
+            ]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "size:        README (expected 5 bytes, got 2)")]
+    fn test_assert_dir_eq_panics_with_report() {
+        let (expected, actual) = (temp_dir(), temp_dir());
+        populate(expected.path());
+        populate(actual.path());
+        std::fs::write(actual.path().join("README"), "hi").unwrap();
+
+        assert_dir_eq(expected.path(), actual.path());
+    }
+}
@@ -0,0 +1,51 @@
+use rand::Rng;
+
+/// Length of each line, newline included.
+pub(crate) const LINE_LEN: usize = 64;
+
+const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/// Generate `len` lines of ASCII characters where, past the first line, each character is drawn
+/// at random with a probability of `level` percent, and repeats the character above otherwise.
+///
+/// Level 0 repeats the first line over and over, level 100 is random throughout.
+pub(crate) fn fake_entropic_text<R: Rng + ?Sized>(len: usize, level: u8, rng: &mut R) -> Vec<u8> {
+    let probability = level.min(100) as f64 / 100.0;
+    let mut text = Vec::with_capacity(len * LINE_LEN);
+    for line in 0..len {
+        for _ in 0..LINE_LEN - 1 {
+            let c = if line == 0 || rng.gen_bool(probability) {
+                ALPHABET[rng.gen_range(0..ALPHABET.len())]
+            } else {
+                text[text.len() - LINE_LEN]
+            };
+            text.push(c);
+        }
+        text.push(b'\n');
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    #[test]
+    fn test_fake_entropic_text() {
+        let mut rng = rand::thread_rng();
+        let distinct_lines = |text: &[u8]| {
+            let text = std::str::from_utf8(text).unwrap();
+            text.lines().collect::<HashSet<_>>().len()
+        };
+
+        let repetitive = fake_entropic_text(50, 0, &mut rng);
+        assert_eq!(repetitive.len(), 50 * LINE_LEN);
+        assert_eq!(distinct_lines(&repetitive), 1);
+
+        let random = fake_entropic_text(50, 100, &mut rng);
+        assert_eq!(distinct_lines(&random), 50);
+        assert!(fake_entropic_text(0, 50, &mut rng).is_empty());
+    }
+}
@@ -0,0 +1,141 @@
+use std::ops::Range;
+
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use crate::rng::TestRng;
+
+/// Characters a generated filename may consist of.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Charset {
+    /// `a-z`
+    Lowercase,
+    /// `a-z`, `A-Z` and `0-9`
+    Alphanumeric,
+    /// The POSIX portable filename character set: `a-z`, `A-Z`, `0-9`, `.`, `_` and `-`
+    Portable,
+}
+
+impl Charset {
+    fn chars(&self) -> &'static [u8] {
+        match self {
+            Charset::Lowercase => b"abcdefghijklmnopqrstuvwxyz",
+            Charset::Alphanumeric => {
+                b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789"
+            }
+            Charset::Portable => {
+                b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789._-"
+            }
+        }
+    }
+}
+
+/// Device names which cannot be used as file stems on Windows
+const WINDOWS_RESERVED: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Generate a filename whose stem has a length within `len` and consists of `charset`, with an
+/// extension picked from `extensions`, if any.
+///
+/// The name is safe to use on Linux, macOS and Windows alike: it never starts with `-` or `.`,
+/// never ends with `.`, and never is a reserved device name.
+pub fn fake_filename(extensions: &[&str], len: Range<usize>, charset: Charset) -> String {
+    fake_filename_with_rng(extensions, len, charset, &mut TestRng)
+}
+
+pub fn fake_filename_with_rng<R: Rng + ?Sized>(
+    extensions: &[&str],
+    len: Range<usize>,
+    charset: Charset,
+    rng: &mut R,
+) -> String {
+    let chars = charset.chars();
+    let len = len.start.max(1)..len.end.max(len.start.max(1) + 1);
+    let stem = loop {
+        let n = rng.gen_range(len.clone());
+        let stem = (0..n)
+            .map(|_| *chars.choose(rng).unwrap() as char)
+            .collect::<String>();
+        if is_portable_stem(&stem) {
+            break stem;
+        }
+    };
+
+    match extensions.choose(rng) {
+        Some(ext) => format!("{stem}.{}", ext.trim_start_matches('.')),
+        None => stem,
+    }
+}
+
+/// Generate a filename like `fake_filename`, with an extension drawn from the distribution of
+/// `datasets::EXTENSIONS`, so that common extensions are as common as in real directories.
+#[cfg(feature = "dataset-extensions")]
+pub fn fake_realistic_filename(len: Range<usize>, charset: Charset) -> String {
+    fake_realistic_filename_with_rng(len, charset, &mut TestRng)
+}
+
+#[cfg(feature = "dataset-extensions")]
+pub fn fake_realistic_filename_with_rng<R: Rng + ?Sized>(
+    len: Range<usize>,
+    charset: Charset,
+    rng: &mut R,
+) -> String {
+    let ext = crate::datasets::choose_weighted(crate::datasets::EXTENSIONS, rng);
+    fake_filename_with_rng(&[ext], len, charset, rng)
+}
+
+fn is_portable_stem(stem: &str) -> bool {
+    !stem.starts_with(['-', '.'])
+        && !stem.ends_with('.')
+        && !WINDOWS_RESERVED
+            .iter()
+            .any(|reserved| reserved.eq_ignore_ascii_case(stem))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fake_filename() {
+        for _ in 0..200 {
+            let name = fake_filename(&["txt", ".log"], 3..8, Charset::Portable);
+            let (stem, ext) = name.rsplit_once('.').unwrap();
+
+            assert!(ext == "txt" || ext == "log");
+            assert!((3..8).contains(&stem.len()));
+            assert!(is_portable_stem(stem));
+            assert!(stem.bytes().all(|c| Charset::Portable.chars().contains(&c)));
+        }
+    }
+
+    #[test]
+    fn test_fake_filename_without_extension() {
+        let name = fake_filename(&[], 0..1, Charset::Lowercase);
+
+        assert_eq!(name.len(), 1);
+        assert!(name.bytes().all(|c| c.is_ascii_lowercase()));
+    }
+
+    #[cfg(feature = "dataset-extensions")]
+    #[test]
+    fn test_fake_realistic_filename() {
+        let name = fake_realistic_filename(3..8, Charset::Lowercase);
+        let (_, ext) = name.split_once('.').unwrap();
+
+        assert!(crate::datasets::EXTENSIONS
+            .iter()
+            .any(|(known, _)| *known == ext));
+    }
+
+    #[test]
+    fn test_reserved_names_are_rejected() {
+        assert!(!is_portable_stem("con"));
+        assert!(!is_portable_stem("Lpt1"));
+        assert!(!is_portable_stem("-rf"));
+        assert!(!is_portable_stem("name."));
+        assert!(is_portable_stem("console"));
+    }
+}
@@ -0,0 +1,174 @@
+use std::io::Write;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use fake::{Dummy, Fake, Faker};
+use rand::Rng;
+use tempfile::TempPath;
+
+use super::{temp_file, try_fake_content, Budget, TempFileKind};
+
+/// Faker of temp files which keep growing by fake lines until dropped, for testing code that
+/// tails or follows files.
+pub struct GrowingFileFaker<L = Faker> {
+    kind: TempFileKind,
+    line_len: L,
+    interval: Duration,
+    max_total_bytes: Option<usize>,
+}
+
+impl GrowingFileFaker<Faker> {
+    pub fn new() -> GrowingFileFaker<Faker> {
+        GrowingFileFaker {
+            kind: TempFileKind::Text,
+            line_len: Faker,
+            interval: Duration::from_millis(100),
+            max_total_bytes: None,
+        }
+    }
+}
+
+impl Default for GrowingFileFaker<Faker> {
+    fn default() -> Self {
+        GrowingFileFaker::new()
+    }
+}
+
+impl<L> GrowingFileFaker<L> {
+    pub fn kind(mut self, kind: TempFileKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    /// Time to wait between two appended lines.
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Stop growing, with an error logged, before the file exceeds `max` bytes.
+    pub fn max_total_bytes(mut self, max: usize) -> Self {
+        self.max_total_bytes = Some(max);
+        self
+    }
+
+    /// Length of each line, in the unit of the content kind.
+    pub fn line_len<U>(self, line_len: U) -> GrowingFileFaker<U> {
+        GrowingFileFaker {
+            kind: self.kind,
+            line_len,
+            interval: self.interval,
+            max_total_bytes: self.max_total_bytes,
+        }
+    }
+}
+
+pub struct GrowingFile {
+    pub path: TempPath,
+    lines: Arc<AtomicUsize>,
+    stop: Option<Sender<()>>,
+    writer: Option<JoinHandle<()>>,
+}
+
+impl GrowingFile {
+    /// Number of lines appended so far.
+    pub fn lines_written(&self) -> usize {
+        self.lines.load(Ordering::SeqCst)
+    }
+
+    /// Stop appending to the file, keeping the file until dropped.
+    pub fn stop(&mut self) {
+        self.stop.take();
+        if let Some(writer) = self.writer.take() {
+            writer.join().unwrap();
+        }
+    }
+}
+
+impl Drop for GrowingFile {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+impl<L> Dummy<GrowingFileFaker<L>> for GrowingFile
+where
+    u8: Dummy<L>,
+    L: Clone + Send + 'static,
+{
+    fn dummy_with_rng<R: Rng + ?Sized>(config: &GrowingFileFaker<L>, _rng: &mut R) -> Self {
+        let (mut file, path) = temp_file().into_parts();
+        let (stop, stopped) = mpsc::channel::<()>();
+        let lines = Arc::new(AtomicUsize::new(0));
+
+        let kind = config.kind.clone();
+        let line_len = config.line_len.clone();
+        let interval = config.interval;
+        let mut budget = Budget::new(config.max_total_bytes);
+        let counter = lines.clone();
+        // the writer draws from the randomness of the test, although on a thread of its own
+        let mut rng = crate::rng::fork();
+        let writer = std::thread::spawn(move || {
+            while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(interval) {
+                let len = line_len.fake_with_rng::<u8, _>(&mut rng) as usize;
+                let line =
+                    try_fake_content(&kind, len, &mut budget, &mut rng).and_then(|mut line| {
+                        budget.try_spend(1)?;
+                        line.push(b'\n');
+                        Ok(line)
+                    });
+                let line = match line {
+                    Ok(line) => line,
+                    Err(err) => {
+                        log::error!("stopped growing the file: {err}");
+                        break;
+                    }
+                };
+                file.write_all(&line).unwrap();
+                file.flush().unwrap();
+                counter.fetch_add(1, Ordering::SeqCst);
+            }
+        });
+
+        GrowingFile {
+            path,
+            lines,
+            stop: Some(stop),
+            writer: Some(writer),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fake_growing_file() {
+        let temp_path: std::path::PathBuf;
+        {
+            let faker = GrowingFileFaker::new()
+                .interval(Duration::from_millis(5))
+                .line_len(3..6);
+            let mut growing_file = faker.fake::<GrowingFile>();
+            temp_path = growing_file.path.to_path_buf();
+
+            while growing_file.lines_written() < 3 {
+                std::thread::sleep(Duration::from_millis(5));
+            }
+            growing_file.stop();
+
+            let content = std::fs::read_to_string(&temp_path).unwrap();
+            assert_eq!(content.lines().count(), growing_file.lines_written());
+            assert!(content.ends_with('\n'));
+
+            // nothing is appended once stopped
+            std::thread::sleep(Duration::from_millis(20));
+            assert_eq!(std::fs::read_to_string(&temp_path).unwrap(), content);
+        }
+        assert!(!temp_path.exists());
+    }
+}
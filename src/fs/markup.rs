@@ -0,0 +1,145 @@
+use fake::faker::lorem::en::{Word, Words};
+use fake::Fake;
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+const HTML_CONTAINERS: &[&str] = &["div", "section", "article", "main", "aside"];
+const HTML_LEAVES: &[&str] = &["p", "h2", "span", "em", "strong"];
+
+/// A randomly shaped element tree, stored as the parent of each element.
+struct Shape {
+    parents: Vec<Option<usize>>,
+}
+
+impl Shape {
+    /// Shape `len` elements, with at least one, into a tree no deeper than `depth`.
+    fn fake<R: Rng + ?Sized>(len: usize, depth: usize, rng: &mut R) -> Shape {
+        let depth = depth.max(1);
+        let mut parents = vec![None];
+        let mut depths = vec![1];
+        for _ in 1..len.max(1) {
+            let candidates = (0..parents.len())
+                .filter(|&i| depths[i] < depth)
+                .collect::<Vec<_>>();
+            let parent = candidates.choose(rng).copied().unwrap_or(0);
+            parents.push(Some(parent));
+            depths.push((depths[parent] + 1).min(depth));
+        }
+        Shape { parents }
+    }
+
+    fn children(&self, parent: usize) -> impl Iterator<Item = usize> + '_ {
+        (0..self.parents.len()).filter(move |&i| self.parents[i] == Some(parent))
+    }
+}
+
+/// Generate a well-formed XML document of `len` elements nested at most `depth` levels deep.
+pub(crate) fn fake_xml<R: Rng + ?Sized>(len: usize, depth: usize, rng: &mut R) -> String {
+    let shape = Shape::fake(len, depth, rng);
+    let tags = (0..shape.parents.len())
+        .map(|_| Word().fake_with_rng::<&str, R>(rng).to_string())
+        .collect::<Vec<_>>();
+
+    let mut doc = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    render(&shape, 0, &mut doc, rng, &mut |i, children, doc, rng| {
+        let id = rng.gen_range(0..10000);
+        doc.push_str(&format!("<{} id=\"{id}\">", tags[i]));
+        if !children {
+            doc.push_str(&fake_text(rng));
+        }
+        tags[i].clone()
+    });
+    doc
+}
+
+/// Generate a well-formed HTML page whose body has `len` elements nested at most `depth`
+/// levels deep.
+pub(crate) fn fake_html<R: Rng + ?Sized>(len: usize, depth: usize, rng: &mut R) -> String {
+    let shape = Shape::fake(len, depth, rng);
+
+    let mut doc = format!(
+        "<!DOCTYPE html>\n<html>\n<head><title>{}</title></head>\n",
+        fake_text(rng)
+    );
+    render(&shape, 0, &mut doc, rng, &mut |i, children, doc, rng| {
+        let tag = if i == 0 {
+            "body"
+        } else if children {
+            HTML_CONTAINERS.choose(rng).unwrap()
+        } else {
+            HTML_LEAVES.choose(rng).unwrap()
+        };
+        doc.push_str(&format!("<{tag}>"));
+        if !children {
+            doc.push_str(&fake_text(rng));
+        }
+        tag.to_string()
+    });
+    doc.push_str("\n</html>\n");
+    doc
+}
+
+/// Render element `i` and its descendants, letting `open` write the opening tag and text and
+/// return the tag name to close the element with.
+fn render<R, F>(shape: &Shape, i: usize, doc: &mut String, rng: &mut R, open: &mut F)
+where
+    R: Rng + ?Sized,
+    F: FnMut(usize, bool, &mut String, &mut R) -> String,
+{
+    let children = shape.children(i).collect::<Vec<_>>();
+    let tag = open(i, !children.is_empty(), doc, rng);
+    for child in children {
+        render(shape, child, doc, rng, open);
+    }
+    doc.push_str(&format!("</{tag}>"));
+}
+
+fn fake_text<R: Rng + ?Sized>(rng: &mut R) -> String {
+    Words(1..6).fake_with_rng::<Vec<String>, R>(rng).join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Check the tags are balanced and return the element count and maximal depth.
+    fn measure(doc: &str) -> (usize, usize) {
+        let (mut stack, mut count, mut max_depth) = (Vec::new(), 0, 0);
+        for tag in doc.split('<').skip(1) {
+            let tag = tag.split('>').next().unwrap();
+            if let Some(closing) = tag.strip_prefix('/') {
+                assert_eq!(stack.pop(), Some(closing.to_string()));
+            } else if !tag.starts_with(['?', '!']) {
+                stack.push(tag.split(' ').next().unwrap().to_string());
+                count += 1;
+                max_depth = max_depth.max(stack.len());
+            }
+        }
+        assert!(stack.is_empty());
+        (count, max_depth)
+    }
+
+    #[test]
+    fn test_fake_xml() {
+        let mut rng = rand::thread_rng();
+        let doc = fake_xml(30, 4, &mut rng);
+
+        assert!(doc.starts_with("<?xml"));
+        let (count, depth) = measure(&doc);
+        assert_eq!(count, 30);
+        assert!(depth <= 4);
+    }
+
+    #[test]
+    fn test_fake_html() {
+        let mut rng = rand::thread_rng();
+        let doc = fake_html(12, 3, &mut rng);
+
+        assert!(doc.starts_with("<!DOCTYPE html>"));
+        assert!(doc.contains("<body>"));
+        // html, head and title wrap the generated body
+        let (count, depth) = measure(&doc);
+        assert_eq!(count, 12 + 3);
+        assert!(depth <= 3 + 1);
+    }
+}
@@ -0,0 +1,62 @@
+#[cfg(feature = "fs")]
+use std::io;
+#[cfg(feature = "fs")]
+use std::path::Path;
+
+/// Extended metadata stamped on generated files.
+///
+/// Without the `fs` feature there are no files to stamp it on.
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(not(feature = "fs"), allow(dead_code))]
+pub(crate) struct FileMetadata {
+    /// Extended attributes as name and value pairs
+    pub xattrs: Vec<(String, Vec<u8>)>,
+    /// ACL entries in the syntax of the platform's ACL tool
+    pub acl: Vec<String>,
+}
+
+#[cfg(feature = "fs")]
+impl FileMetadata {
+    pub fn apply(&self, path: &Path) -> io::Result<()> {
+        for (name, value) in &self.xattrs {
+            set_xattr(path, name, value)?;
+        }
+        for entry in &self.acl {
+            add_acl_entry(path, entry)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(feature = "fs", feature = "xattr"))]
+fn set_xattr(path: &Path, name: &str, value: &[u8]) -> io::Result<()> {
+    xattr::set(path, name, value)
+}
+
+#[cfg(all(feature = "fs", not(feature = "xattr")))]
+fn set_xattr(_path: &Path, _name: &str, _value: &[u8]) -> io::Result<()> {
+    unreachable!("extended attributes can only be configured with the `xattr` feature")
+}
+
+#[cfg(feature = "fs")]
+fn add_acl_entry(path: &Path, entry: &str) -> io::Result<()> {
+    let mut command = if cfg!(target_os = "macos") {
+        let mut command = std::process::Command::new("chmod");
+        command.arg("+a").arg(entry);
+        command
+    } else {
+        let mut command = std::process::Command::new("setfacl");
+        command.arg("-m").arg(entry);
+        command
+    };
+
+    let output = command.arg(path).output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "failed to add acl entry `{entry}` to {}: {}",
+            path.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(())
+}
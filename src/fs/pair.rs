@@ -0,0 +1,90 @@
+use fake::{Dummy, Fake, Faker};
+use rand::Rng;
+
+use super::{temp_file, Budget, TempFile, TempFileFaker};
+
+/// Faker of an input temp file paired with the expected output of transforming it, for
+/// generating table-driven transformation tests at runtime.
+pub struct TempFilePairFaker<F, L = Faker> {
+    input: TempFileFaker<L>,
+    transform: F,
+}
+
+impl<F> TempFilePairFaker<F, Faker>
+where
+    F: Fn(&[u8]) -> Vec<u8>,
+{
+    pub fn new(transform: F) -> Self {
+        TempFilePairFaker {
+            input: TempFileFaker::new(),
+            transform,
+        }
+    }
+}
+
+impl<F, L> TempFilePairFaker<F, L> {
+    /// Configure how the input file is generated.
+    pub fn input<U>(self, input: TempFileFaker<U>) -> TempFilePairFaker<F, U> {
+        TempFilePairFaker {
+            input,
+            transform: self.transform,
+        }
+    }
+}
+
+pub struct TempFilePair {
+    pub input: TempFile,
+    pub expected: TempFile,
+}
+
+impl<F, L> Dummy<TempFilePairFaker<F, L>> for TempFilePair
+where
+    F: Fn(&[u8]) -> Vec<u8>,
+    u8: Dummy<L>,
+{
+    fn dummy_with_rng<R: Rng + ?Sized>(config: &TempFilePairFaker<F, L>, rng: &mut R) -> Self {
+        let input = config.input.fake_with_rng::<TempFile, R>(rng);
+        let input_content = match input.content.as_ref() {
+            Some(content) => content.clone(),
+            None => std::fs::read(&input.path).unwrap(),
+        };
+        let expected_content = (config.transform)(&input_content);
+        Budget::default().spend(expected_content.len());
+
+        let path = temp_file().into_temp_path();
+        std::fs::write(&path, &expected_content).unwrap();
+
+        TempFilePair {
+            expected: TempFile {
+                path,
+                content: input.content.as_ref().map(|_| expected_content),
+            },
+            input,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::TempFileKind;
+    use super::*;
+
+    #[test]
+    fn test_fake_temp_file_pair() {
+        let faker = TempFilePairFaker::new(|content: &[u8]| content.to_ascii_uppercase()).input(
+            TempFileFaker::with_len(5..10)
+                .kind(TempFileKind::Text)
+                .include_content(false),
+        );
+
+        for _ in 0..3 {
+            let pair = faker.fake::<TempFilePair>();
+            let input = std::fs::read(&pair.input.path).unwrap();
+            let expected = std::fs::read(&pair.expected.path).unwrap();
+
+            assert!(pair.expected.content.is_none());
+            assert_ne!(input, expected);
+            assert_eq!(input.to_ascii_uppercase(), expected);
+        }
+    }
+}
@@ -0,0 +1,117 @@
+use std::path::Path;
+
+/// Assert that the two paths point to the same location once normalized with
+/// `normalize_for_comparison`, e.g. a path generated by the code under test and the one it is
+/// expected to be.
+#[track_caller]
+pub fn assert_path_eq<P: AsRef<Path>, Q: AsRef<Path>>(expected: P, actual: Q) {
+    let (expected, actual) = (expected.as_ref(), actual.as_ref());
+    assert_eq!(
+        normalize_for_comparison(expected),
+        normalize_for_comparison(actual),
+        "paths differ: expected `{}`, actual `{}`",
+        expected.display(),
+        actual.display()
+    );
+}
+
+/// `path` in a form comparable across platforms: `/`-separated, without verbatim or UNC
+/// prefixes, `.` components or trailing separators, with `..` resolved lexically, and
+/// lowercase where the file systems are case-insensitive by default, i.e. on Windows and macOS.
+///
+/// The file system is not accessed, so symlinks are compared as they are, except for the
+/// `/private` macOS prefixes temp directories to when canonicalized.
+pub fn normalize_for_comparison<P: AsRef<Path>>(path: P) -> String {
+    let case_insensitive = cfg!(any(windows, target_os = "macos"));
+    let normalized = normalize(&path.as_ref().to_string_lossy(), case_insensitive);
+    if cfg!(target_os = "macos") {
+        for aliased in ["/private/var/", "/private/tmp/", "/private/etc/"] {
+            if normalized.starts_with(aliased) || normalized == aliased.trim_end_matches('/') {
+                return normalized["/private".len()..].to_string();
+            }
+        }
+    }
+    normalized
+}
+
+fn normalize(path: &str, case_insensitive: bool) -> String {
+    let path = path.replace('\\', "/");
+    let (prefix, rest) = if let Some(share) = path.strip_prefix("//?/UNC/") {
+        ("//", share)
+    } else if let Some(rest) = path.strip_prefix("//?/") {
+        ("", rest)
+    } else if let Some(share) = path.strip_prefix("//") {
+        ("//", share)
+    } else {
+        ("", path.as_str())
+    };
+
+    let mut root = prefix.to_string();
+    let mut rest = rest;
+    let mut chars = rest.chars();
+    if let (Some(drive), Some(':')) = (chars.next(), chars.next()) {
+        if drive.is_ascii_alphabetic() {
+            root.push(drive.to_ascii_lowercase());
+            root.push(':');
+            rest = chars.as_str();
+        }
+    }
+    if prefix.is_empty() && rest.starts_with('/') {
+        root.push('/');
+    }
+
+    let mut components: Vec<&str> = Vec::new();
+    for component in rest.split('/') {
+        match component {
+            "" | "." => {}
+            ".." if components.last().is_some_and(|last| *last != "..") => {
+                components.pop();
+            }
+            // `..` of the root is the root
+            ".." if root.ends_with('/') => {}
+            component => components.push(component),
+        }
+    }
+
+    let normalized = root + &components.join("/");
+    let normalized = if normalized.is_empty() {
+        ".".to_string()
+    } else {
+        normalized
+    };
+    if case_insensitive {
+        normalized.to_lowercase()
+    } else {
+        normalized
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize("/tmp//a/./b/../c/", false), "/tmp/a/c");
+        assert_eq!(normalize("a/../../b", false), "../b");
+        assert_eq!(normalize("/../a", false), "/a");
+        assert_eq!(normalize("./", false), ".");
+        assert_eq!(normalize("/Tmp/A", false), "/Tmp/A");
+        assert_eq!(normalize("/Tmp/A", true), "/tmp/a");
+        assert_eq!(normalize(r"C:\Users\Me\", true), "c:/users/me");
+        assert_eq!(normalize(r"\\?\C:\Users\me", false), "c:/Users/me");
+        assert_eq!(normalize(r"c:relative\a", false), "c:relative/a");
+        assert_eq!(
+            normalize(r"\\?\UNC\server\share\a", false),
+            normalize(r"\\server\share\a", false)
+        );
+        assert_eq!(normalize(r"\\server\share\a", false), "//server/share/a");
+    }
+
+    #[test]
+    fn test_assert_path_eq() {
+        assert_path_eq("/data/./files/a.txt", "/data/files/b/../a.txt");
+        let result = std::panic::catch_unwind(|| assert_path_eq("/data/a.txt", "/data/b.txt"));
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,181 @@
+use std::fmt;
+use std::io;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::OnceLock;
+
+/// Environment variable capping the bytes generated across the process, taking precedence over
+/// `set_max_total_bytes`
+pub const MAX_TOTAL_BYTES_ENV: &str = "TEST_UTILITIES_MAX_TOTAL_BYTES";
+
+static GLOBAL: OnceLock<Quota> = OnceLock::new();
+
+/// Cap the bytes of content the fakers may generate across the process, or lift the cap with
+/// `None`. Generating past it panics, or fails for the fallible APIs, with a `QuotaExceeded`.
+pub fn set_max_total_bytes(limit: Option<usize>) {
+    if let Some(env) = env_limit() {
+        log::debug!("ignoring byte limit {limit:?} in favor of {MAX_TOTAL_BYTES_ENV}={env}");
+        return;
+    }
+    global().set_limit(limit);
+}
+
+/// Bytes of content generated across the process so far.
+pub fn total_generated_bytes() -> usize {
+    global().used.load(Ordering::SeqCst)
+}
+
+fn global() -> &'static Quota {
+    GLOBAL.get_or_init(|| {
+        let quota = Quota::default();
+        quota.set_limit(env_limit());
+        quota
+    })
+}
+
+fn env_limit() -> Option<usize> {
+    let value = std::env::var(MAX_TOTAL_BYTES_ENV).ok()?;
+    match value.parse() {
+        Ok(limit) => Some(limit),
+        Err(err) => {
+            log::warn!("ignoring invalid {MAX_TOTAL_BYTES_ENV}={value}: {err}");
+            None
+        }
+    }
+}
+
+/// Generating content would exceed a byte budget, usually because of a misconfigured length.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct QuotaExceeded {
+    /// Bytes about to be generated
+    pub requested: usize,
+    /// Bytes generated before under the same budget
+    pub used: usize,
+    pub limit: usize,
+    /// Whether the budget is the process-wide one rather than the one of the faker
+    pub global: bool,
+}
+
+impl fmt::Display for QuotaExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let budget = if self.global {
+            format!("the process-wide budget of {} bytes", self.limit)
+        } else {
+            format!("the budget of {} bytes of the faker", self.limit)
+        };
+        write!(
+            f,
+            "generating {} more bytes after {} would exceed {budget}, check the length ranges",
+            self.requested, self.used
+        )
+    }
+}
+
+impl std::error::Error for QuotaExceeded {}
+
+impl From<QuotaExceeded> for io::Error {
+    fn from(err: QuotaExceeded) -> Self {
+        io::Error::other(err)
+    }
+}
+
+#[derive(Debug)]
+struct Quota {
+    used: AtomicUsize,
+    /// `usize::MAX` when unlimited
+    limit: AtomicUsize,
+}
+
+impl Default for Quota {
+    fn default() -> Self {
+        Quota {
+            used: AtomicUsize::new(0),
+            limit: AtomicUsize::new(usize::MAX),
+        }
+    }
+}
+
+impl Quota {
+    fn set_limit(&self, limit: Option<usize>) {
+        self.limit
+            .store(limit.unwrap_or(usize::MAX), Ordering::SeqCst);
+    }
+
+    fn charge(&self, len: usize) -> Result<(), QuotaExceeded> {
+        let limit = self.limit.load(Ordering::SeqCst);
+        self.used
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |used| {
+                used.checked_add(len).filter(|total| *total <= limit)
+            })
+            .map(|_| ())
+            .map_err(|used| QuotaExceeded {
+                requested: len,
+                used,
+                limit,
+                global: true,
+            })
+    }
+}
+
+/// The bytes generated by a single fake call, under the limit of its faker and the global one.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct Budget {
+    limit: Option<usize>,
+    used: usize,
+}
+
+impl Budget {
+    pub(crate) fn new(limit: Option<usize>) -> Self {
+        Budget { limit, used: 0 }
+    }
+
+    /// Account for `len` bytes about to be generated.
+    pub(crate) fn try_spend(&mut self, len: usize) -> Result<(), QuotaExceeded> {
+        self.try_spend_with(global(), len)
+    }
+
+    /// Like `try_spend`, panicking once over budget, for the `Dummy` impls.
+    #[cfg_attr(not(any(feature = "fs", feature = "gridfs")), allow(dead_code))]
+    pub(crate) fn spend(&mut self, len: usize) {
+        if let Err(err) = self.try_spend(len) {
+            panic!("{err}");
+        }
+    }
+
+    fn try_spend_with(&mut self, global: &Quota, len: usize) -> Result<(), QuotaExceeded> {
+        let total = self.used.saturating_add(len);
+        if let Some(limit) = self.limit.filter(|limit| total > *limit) {
+            return Err(QuotaExceeded {
+                requested: len,
+                used: self.used,
+                limit,
+                global: false,
+            });
+        }
+        global.charge(len)?;
+        self.used = total;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_budget() {
+        let global = Quota::default();
+        let mut budget = Budget::new(Some(10));
+        budget.try_spend_with(&global, 6).unwrap();
+        let err = budget.try_spend_with(&global, 6).unwrap_err();
+        assert!(!err.global);
+        assert_eq!(err.used, 6);
+        assert!(err.to_string().contains("budget of 10 bytes"));
+
+        global.set_limit(Some(8));
+        let err = Budget::new(None)
+            .try_spend_with(&global, usize::MAX)
+            .unwrap_err();
+        assert!(err.global);
+        assert_eq!(global.used.load(Ordering::SeqCst), 6);
+    }
+}
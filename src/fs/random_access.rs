@@ -0,0 +1,158 @@
+use std::collections::BTreeMap;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::ops::Range;
+
+use fake::{Dummy, Fake, Faker};
+use rand::Rng;
+use tempfile::TempPath;
+
+use super::{temp_file, try_fake_content, Budget, TempFileKind};
+
+/// Faker of temp files along with the bytes expected at a few random offsets, for testing code
+/// which seeks and reads parts of files without reading them whole.
+pub struct RandomAccessFileFaker<L = Faker> {
+    kind: TempFileKind,
+    len: L,
+    probes: usize,
+    probe_len: Range<usize>,
+    max_total_bytes: Option<usize>,
+}
+
+impl RandomAccessFileFaker<Faker> {
+    pub fn new() -> RandomAccessFileFaker<Faker> {
+        RandomAccessFileFaker {
+            kind: TempFileKind::Text,
+            len: Faker,
+            probes: 8,
+            probe_len: 1..16,
+            max_total_bytes: None,
+        }
+    }
+}
+
+impl Default for RandomAccessFileFaker<Faker> {
+    fn default() -> Self {
+        RandomAccessFileFaker::new()
+    }
+}
+
+impl<L> RandomAccessFileFaker<L> {
+    pub fn kind(mut self, kind: TempFileKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    /// Number of offsets to record, 8 by default. Fewer are recorded for files too short to
+    /// hold that many distinct offsets.
+    pub fn probes(mut self, probes: usize) -> Self {
+        self.probes = probes;
+        self
+    }
+
+    /// Range of the number of bytes recorded at each offset.
+    pub fn probe_len(mut self, probe_len: Range<usize>) -> Self {
+        let start = probe_len.start.max(1);
+        self.probe_len = start..probe_len.end.max(start + 1);
+        self
+    }
+
+    /// Fail instead of generating files of more than `max` bytes.
+    pub fn max_total_bytes(mut self, max: usize) -> Self {
+        self.max_total_bytes = Some(max);
+        self
+    }
+
+    pub fn len<U>(self, len: U) -> RandomAccessFileFaker<U> {
+        RandomAccessFileFaker {
+            kind: self.kind,
+            len,
+            probes: self.probes,
+            probe_len: self.probe_len,
+            max_total_bytes: self.max_total_bytes,
+        }
+    }
+}
+
+pub struct RandomAccessFile {
+    pub path: TempPath,
+    /// Length of the file in bytes
+    pub len: u64,
+    /// Bytes expected at each recorded offset, always within the file
+    pub probes: BTreeMap<u64, Vec<u8>>,
+}
+
+impl RandomAccessFile {
+    /// Seek to every recorded offset of `reader` and check that it reads the expected bytes,
+    /// failing with `InvalidData` at the first mismatch.
+    pub fn verify<F: Read + Seek>(&self, mut reader: F) -> io::Result<()> {
+        for (offset, expected) in &self.probes {
+            reader.seek(SeekFrom::Start(*offset))?;
+            let mut actual = vec![0; expected.len()];
+            reader.read_exact(&mut actual)?;
+            if &actual != expected {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("read {actual:?} at offset {offset} instead of {expected:?}"),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<L> Dummy<RandomAccessFileFaker<L>> for RandomAccessFile
+where
+    u8: Dummy<L>,
+{
+    fn dummy_with_rng<R: Rng + ?Sized>(config: &RandomAccessFileFaker<L>, mut rng: &mut R) -> Self {
+        let len = config.len.fake_with_rng::<u8, R>(rng) as usize;
+        let mut budget = Budget::new(config.max_total_bytes);
+        let content = try_fake_content(&config.kind, len, &mut budget, &mut rng)
+            .unwrap_or_else(|err| panic!("{err}"));
+
+        let path = temp_file().into_temp_path();
+        std::fs::write(&path, &content).unwrap();
+
+        let mut probes = BTreeMap::new();
+        for _ in 0..config.probes.min(content.len()) {
+            let offset = rng.gen_range(0..content.len());
+            let probe_len = rng
+                .gen_range(config.probe_len.clone())
+                .min(content.len() - offset);
+            probes.insert(offset as u64, content[offset..offset + probe_len].to_vec());
+        }
+
+        RandomAccessFile {
+            path,
+            len: content.len() as u64,
+            probes,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fake_random_access_file() {
+        let file = RandomAccessFileFaker::new()
+            .kind(TempFileKind::Entropy(100))
+            .len(10..20)
+            .probes(5)
+            .probe_len(4..8)
+            .fake::<RandomAccessFile>();
+
+        assert_eq!(std::fs::metadata(&file.path).unwrap().len(), file.len);
+        assert!(!file.probes.is_empty());
+        assert!(file
+            .probes
+            .iter()
+            .all(|(offset, bytes)| offset + bytes.len() as u64 <= file.len));
+        file.verify(std::fs::File::open(&file.path).unwrap())
+            .unwrap();
+
+        let mut other = std::io::Cursor::new(vec![b'x'; file.len as usize]);
+        assert!(file.verify(&mut other).is_err());
+    }
+}
@@ -0,0 +1,171 @@
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use fake::Dummy;
+use rand::distributions::Alphanumeric;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use tempfile::TempDir;
+
+use super::{temp_dir, Budget};
+
+/// Number of files between two progress reports.
+const PROGRESS_STEP: usize = 1000;
+
+type ProgressFn = Arc<dyn Fn(usize, usize) + Send + Sync>;
+
+/// Faker of temp directories holding a large number of tiny files, written by several threads
+/// at once, for building the corpus of directory-scan benchmarks in seconds.
+pub struct SmallFilesFaker {
+    count: usize,
+    files_per_dir: usize,
+    len: Range<usize>,
+    threads: usize,
+    progress: Option<ProgressFn>,
+    max_total_bytes: Option<usize>,
+}
+
+impl SmallFilesFaker {
+    /// Lay out `count` files of 1 to 64 bytes, a thousand per directory.
+    pub fn new(count: usize) -> Self {
+        SmallFilesFaker {
+            count,
+            files_per_dir: 1000,
+            len: 1..65,
+            threads: std::thread::available_parallelism().map_or(4, |n| n.get()),
+            progress: None,
+            max_total_bytes: None,
+        }
+    }
+
+    /// Spread the files over directories of at most `n` files.
+    pub fn files_per_dir(mut self, n: usize) -> Self {
+        self.files_per_dir = n.max(1);
+        self
+    }
+
+    /// Range of the size of each file, in bytes.
+    pub fn len(mut self, len: Range<usize>) -> Self {
+        self.len = len.start..len.end.max(len.start + 1);
+        self
+    }
+
+    /// Number of threads writing the files, the available parallelism by default.
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+
+    /// Call `f` with the number of files written so far and the total, every thousand files
+    /// and once all are written.
+    pub fn on_progress<F: Fn(usize, usize) + Send + Sync + 'static>(mut self, f: F) -> Self {
+        self.progress = Some(Arc::new(f));
+        self
+    }
+
+    /// Fail before writing anything if the files would add up to more than `max` bytes.
+    pub fn max_total_bytes(mut self, max: usize) -> Self {
+        self.max_total_bytes = Some(max);
+        self
+    }
+}
+
+pub struct SmallFiles {
+    pub root: TempDir,
+    /// Paths of the files, relative to the root, in the order they are numbered
+    pub files: Vec<PathBuf>,
+    /// Total size of the files in bytes
+    pub bytes: usize,
+}
+
+impl SmallFiles {
+    pub fn path(&self) -> &Path {
+        self.root.path()
+    }
+}
+
+impl Dummy<SmallFilesFaker> for SmallFiles {
+    fn dummy_with_rng<R: Rng + ?Sized>(config: &SmallFilesFaker, rng: &mut R) -> Self {
+        // sizes are drawn up front, so that the budget is checked before writing anything
+        let lens: Vec<usize> = (0..config.count)
+            .map(|_| rng.gen_range(config.len.clone()))
+            .collect();
+        let bytes = lens.iter().sum();
+        Budget::new(config.max_total_bytes).spend(bytes);
+
+        let root = temp_dir();
+        let files: Vec<PathBuf> = (0..config.count)
+            .map(|i| {
+                let dir = format!("d{:04}", i / config.files_per_dir);
+                Path::new(&dir).join(format!("f{i:06}.txt"))
+            })
+            .collect();
+        for dir in 0..config.count.div_ceil(config.files_per_dir) {
+            std::fs::create_dir(root.path().join(format!("d{dir:04}"))).unwrap();
+        }
+
+        let written = AtomicUsize::new(0);
+        let chunk_len = config.count.div_ceil(config.threads).max(1);
+        std::thread::scope(|scope| {
+            for (files, lens) in files.chunks(chunk_len).zip(lens.chunks(chunk_len)) {
+                let mut rng = StdRng::seed_from_u64(rng.gen());
+                let (root, written) = (root.path(), &written);
+                scope.spawn(move || {
+                    for (file, len) in files.iter().zip(lens) {
+                        let content: Vec<u8> =
+                            (&mut rng).sample_iter(Alphanumeric).take(*len).collect();
+                        std::fs::write(root.join(file), content).unwrap();
+
+                        let done = written.fetch_add(1, Ordering::Relaxed) + 1;
+                        if let Some(progress) = config.progress.as_ref() {
+                            if done % PROGRESS_STEP == 0 && done < config.count {
+                                progress(done, config.count);
+                            }
+                        }
+                    }
+                });
+            }
+        });
+        if let Some(progress) = config.progress.as_ref() {
+            progress(config.count, config.count);
+        }
+
+        SmallFiles { root, files, bytes }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use fake::Fake;
+
+    use super::*;
+
+    #[test]
+    fn test_fake_small_files() {
+        let reports = Arc::new(Mutex::new(Vec::new()));
+        let sink = reports.clone();
+        let small_files = SmallFilesFaker::new(2500)
+            .files_per_dir(1000)
+            .len(1..9)
+            .threads(3)
+            .on_progress(move |done, total| sink.lock().unwrap().push((done, total)))
+            .fake::<SmallFiles>();
+
+        assert_eq!(small_files.files.len(), 2500);
+        assert_eq!(std::fs::read_dir(small_files.path()).unwrap().count(), 3);
+        let bytes: usize = small_files
+            .files
+            .iter()
+            .map(|file| std::fs::read(small_files.path().join(file)).unwrap().len())
+            .sum();
+        assert_eq!(bytes, small_files.bytes);
+
+        let reports = reports.lock().unwrap();
+        assert_eq!(reports.len(), 3);
+        assert_eq!(reports.last(), Some(&(2500, 2500)));
+    }
+}
@@ -0,0 +1,119 @@
+use std::io;
+#[cfg(feature = "fs")]
+use std::path::{Path, PathBuf};
+
+use futures::future::BoxFuture;
+
+#[cfg(feature = "fs")]
+use tempfile::TempDir;
+
+/// A flat store of named blobs, for testing code written against a storage abstraction with
+/// local directories and GridFS buckets interchangeably.
+///
+/// Names are `/`-separated relative paths. The operations are async, so that a remote storage
+/// can be driven by the runtime of the test, whatever its flavor.
+pub trait Storage: Send + Sync {
+    /// The content stored under `name`, failing with `NotFound` if there is none.
+    fn read<'a>(&'a self, name: &'a str) -> BoxFuture<'a, io::Result<Vec<u8>>>;
+
+    /// Store `content` under `name`, replacing what was stored under it.
+    fn write<'a>(&'a self, name: &'a str, content: &'a [u8]) -> BoxFuture<'a, io::Result<()>>;
+
+    /// The names of everything stored, sorted.
+    fn list(&self) -> BoxFuture<'_, io::Result<Vec<String>>>;
+}
+
+/// Storage in a local directory, each name being the path of a file relative to it.
+#[cfg(feature = "fs")]
+pub struct DirStorage {
+    root: PathBuf,
+    /// The temporary directory `root` is, if owned
+    _dir: Option<TempDir>,
+}
+
+#[cfg(feature = "fs")]
+impl DirStorage {
+    pub fn new<P: Into<PathBuf>>(root: P) -> Self {
+        DirStorage {
+            root: root.into(),
+            _dir: None,
+        }
+    }
+
+    /// Storage in a fresh temporary directory, removed when the storage is dropped.
+    pub fn temp() -> Self {
+        let dir = super::temp_dir();
+        DirStorage {
+            root: dir.path().to_path_buf(),
+            _dir: Some(dir),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.root
+    }
+}
+
+#[cfg(feature = "fs")]
+impl Storage for DirStorage {
+    fn read<'a>(&'a self, name: &'a str) -> BoxFuture<'a, io::Result<Vec<u8>>> {
+        Box::pin(async move { std::fs::read(self.root.join(name)) })
+    }
+
+    fn write<'a>(&'a self, name: &'a str, content: &'a [u8]) -> BoxFuture<'a, io::Result<()>> {
+        Box::pin(async move {
+            let path = self.root.join(name);
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::write(path, content)
+        })
+    }
+
+    fn list(&self) -> BoxFuture<'_, io::Result<Vec<String>>> {
+        Box::pin(async move {
+            let mut names = Vec::new();
+            list_files(&self.root, "", &mut names)?;
+            names.sort();
+            Ok(names)
+        })
+    }
+}
+
+#[cfg(feature = "fs")]
+fn list_files(dir: &Path, prefix: &str, names: &mut Vec<String>) -> io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = format!("{prefix}{}", entry.file_name().to_string_lossy());
+        if entry.file_type()?.is_dir() {
+            list_files(&entry.path(), &format!("{name}/"), names)?;
+        } else {
+            names.push(name);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(all(test, feature = "fs"))]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_dir_storage() {
+        let storage = DirStorage::temp();
+        storage.write("b.txt", b"b").await.unwrap();
+        storage.write("a/nested.txt", b"first").await.unwrap();
+        storage.write("a/nested.txt", b"second").await.unwrap();
+
+        assert_eq!(storage.list().await.unwrap(), ["a/nested.txt", "b.txt"]);
+        assert_eq!(storage.read("a/nested.txt").await.unwrap(), b"second");
+        assert_eq!(
+            storage.read("missing.txt").await.unwrap_err().kind(),
+            io::ErrorKind::NotFound
+        );
+
+        let path = storage.path().to_path_buf();
+        drop(storage);
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,102 @@
+use std::io::{self, Read};
+
+use rand::Rng;
+
+use super::{fake_content, TempFileKind};
+
+/// Length, in the unit of the content kind, of each piece generated by `FakeContentReader`
+const PIECE_LEN: usize = 64;
+
+/// A reader of exactly `len` bytes of fake content, generated piece by piece as it is read,
+/// so that large contents never have to be held in memory at once.
+///
+/// Contents of structured kinds are cut at the byte length and may thus be malformed.
+pub(crate) struct FakeContentReader<'a, R: ?Sized> {
+    kind: &'a TempFileKind,
+    remaining: usize,
+    piece: Vec<u8>,
+    pos: usize,
+    rng: &'a mut R,
+    /// Copy of everything read so far, if requested
+    tee: Option<Vec<u8>>,
+}
+
+impl<'a, R: Rng + ?Sized> FakeContentReader<'a, R> {
+    pub fn new(kind: &'a TempFileKind, len: usize, rng: &'a mut R) -> Self {
+        FakeContentReader {
+            kind,
+            remaining: len,
+            piece: Vec::new(),
+            pos: 0,
+            rng,
+            tee: None,
+        }
+    }
+
+    /// Keep a copy of the content read, to be taken by `into_content`.
+    pub fn tee(mut self, tee: bool) -> Self {
+        self.tee = if tee { Some(Vec::new()) } else { None };
+        self
+    }
+
+    pub fn into_content(self) -> Option<Vec<u8>> {
+        self.tee
+    }
+
+    fn next_piece(&mut self) {
+        let mut piece = if self.piece.is_empty() {
+            Vec::new()
+        } else {
+            vec![b' ']
+        };
+        piece.extend(fake_content(self.kind, PIECE_LEN, self.rng));
+        piece.truncate(self.remaining);
+
+        self.remaining -= piece.len();
+        self.piece = piece;
+        self.pos = 0;
+    }
+}
+
+impl<R: Rng + ?Sized> Read for FakeContentReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pos == self.piece.len() {
+            if self.remaining == 0 {
+                return Ok(0);
+            }
+            self.next_piece();
+        }
+
+        let n = buf.len().min(self.piece.len() - self.pos);
+        buf[..n].copy_from_slice(&self.piece[self.pos..self.pos + n]);
+        if let Some(tee) = self.tee.as_mut() {
+            tee.extend_from_slice(&buf[..n]);
+        }
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fake_content_reader() {
+        let mut rng = rand::thread_rng();
+        let mut reader = FakeContentReader::new(&TempFileKind::Text, 10_000, &mut rng).tee(true);
+
+        let mut content = Vec::new();
+        let mut buf = [0u8; 333];
+        loop {
+            match reader.read(&mut buf).unwrap() {
+                0 => break,
+                n => content.extend_from_slice(&buf[..n]),
+            }
+        }
+
+        assert_eq!(content.len(), 10_000);
+        assert!(!content.contains(&0));
+        assert_eq!(reader.into_content(), Some(content));
+    }
+}
@@ -0,0 +1,183 @@
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, Once, OnceLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Environment variable holding the directory to create temp paths under, taking precedence
+/// over `set_temp_root`
+pub const TEMP_ROOT_ENV: &str = "TEST_UTILITIES_TEMP_ROOT";
+
+const SESSION_PREFIX: &str = "test-utilities-session-";
+/// Age past which the session directory of another process is removed even if the process
+/// cannot be told to have exited, e.g. off Linux or once its pid is reused
+const STALE_AFTER: Duration = Duration::from_secs(24 * 60 * 60);
+
+static STATE: Mutex<State> = Mutex::new(State {
+    root: None,
+    session_dir: None,
+});
+static SESSION: OnceLock<String> = OnceLock::new();
+static REMOVE_AT_EXIT: Once = Once::new();
+
+extern "C" {
+    fn atexit(callback: extern "C" fn()) -> std::ffi::c_int;
+}
+
+struct State {
+    /// Root set by `set_temp_root`
+    root: Option<PathBuf>,
+    /// Directory of the process under the current root, once created
+    session_dir: Option<PathBuf>,
+}
+
+/// Create the temp paths of the fakers under `root`, e.g. a RAM disk or a large scratch
+/// volume, rather than the temp directory of the system.
+///
+/// They go to a directory of the process under `root`, which is created on demand and removed
+/// when the process exits. Directories left under `root` by other processes are removed once
+/// those have exited, or once a day old where that cannot be told.
+pub fn set_temp_root<P: Into<PathBuf>>(root: P) {
+    let root = root.into();
+    if let Some(env) = env_root() {
+        log::debug!(
+            "ignoring temp root {} in favor of {TEMP_ROOT_ENV}={}",
+            root.display(),
+            env.display()
+        );
+        return;
+    }
+    let mut state = STATE.lock().unwrap();
+    // removed unless temp paths are still alive in it
+    if let Some(previous) = state.session_dir.take() {
+        let _ = std::fs::remove_dir(previous);
+    }
+    state.root = Some(root);
+}
+
+/// Directory the temp paths of the fakers are created in, by default the temp directory of
+/// the system.
+pub fn temp_root() -> io::Result<PathBuf> {
+    let mut state = STATE.lock().unwrap();
+    let Some(root) = env_root().or_else(|| state.root.clone()) else {
+        return Ok(std::env::temp_dir());
+    };
+    if let Some(dir) = &state.session_dir {
+        if dir.parent() == Some(root.as_path()) {
+            // the cleanup of another process may have removed it while empty
+            std::fs::create_dir_all(dir)?;
+            return Ok(dir.clone());
+        }
+    }
+    let dir = prepare(&root)?;
+    state.session_dir = Some(dir.clone());
+    Ok(dir)
+}
+
+fn env_root() -> Option<PathBuf> {
+    std::env::var_os(TEMP_ROOT_ENV)
+        .filter(|root| !root.is_empty())
+        .map(PathBuf::from)
+}
+
+/// Create the directory of the process under `root`, removing those of earlier processes.
+fn prepare(root: &Path) -> io::Result<PathBuf> {
+    std::fs::create_dir_all(root)?;
+    let session = SESSION.get_or_init(|| {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+        format!("{SESSION_PREFIX}{:x}-{nanos:x}", std::process::id())
+    });
+    let dir = root.join(session);
+    remove_stale(root, &dir);
+    std::fs::create_dir_all(&dir)?;
+    REMOVE_AT_EXIT.call_once(|| {
+        // SAFETY: the callback is a plain function which neither unwinds nor calls `exit`
+        if unsafe { atexit(remove_session_dir) } != 0 {
+            log::warn!("failed to remove {} at exit", dir.display());
+        }
+    });
+    Ok(dir)
+}
+
+/// Remove the directory of the process, with what its leaked temp paths left in it.
+extern "C" fn remove_session_dir() {
+    // the lock may be held by a thread the exit interrupted
+    let Ok(state) = STATE.try_lock() else {
+        return;
+    };
+    if let Some(dir) = state.session_dir.as_ref() {
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
+
+/// Whether the process which created the session directory `name` is known to have exited.
+fn has_exited(name: &str) -> bool {
+    let pid = name
+        .strip_prefix(SESSION_PREFIX)
+        .and_then(|session| session.split_once('-'))
+        .and_then(|(pid, _)| u32::from_str_radix(pid, 16).ok());
+    let Some(pid) = pid else {
+        return false;
+    };
+    let proc = Path::new("/proc");
+    cfg!(target_os = "linux") && proc.join("self").exists() && !proc.join(pid.to_string()).exists()
+}
+
+fn remove_stale(root: &Path, current: &Path) {
+    let Ok(entries) = std::fs::read_dir(root) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if !name.starts_with(SESSION_PREFIX) || path == current {
+            continue;
+        }
+        let age = entry
+            .metadata()
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| modified.elapsed().ok());
+        // the directory of a live process may be empty between two temp paths
+        if has_exited(&name) || age.is_some_and(|age| age > STALE_AFTER) {
+            log::debug!("removing stale temp directory {}", path.display());
+            let _ = std::fs::remove_dir_all(&path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prepare() {
+        let root = crate::fs::temp_dir();
+        let session = |pid: u32| root.path().join(format!("{SESSION_PREFIX}{pid:x}-1"));
+        // of a process which has exited, unless pids are not known
+        let exited = session(u32::MAX);
+        std::fs::create_dir(&exited).unwrap();
+        std::fs::write(exited.join("a.txt"), "a").unwrap();
+        // of a live process, between two temp paths
+        let live = session(std::process::id());
+        std::fs::create_dir(&live).unwrap();
+        let old = session(std::process::id() + 1);
+        std::fs::create_dir(&old).unwrap();
+        std::fs::File::open(&old)
+            .and_then(|dir| dir.set_modified(SystemTime::now() - 2 * STALE_AFTER))
+            .unwrap();
+        let other = root.path().join("other");
+        std::fs::create_dir(&other).unwrap();
+
+        let dir = prepare(root.path()).unwrap();
+        assert!(dir.is_dir());
+        assert_eq!(dir.parent(), Some(root.path()));
+        assert_eq!(exited.exists(), !cfg!(target_os = "linux"));
+        assert!(live.exists());
+        assert!(!old.exists());
+        assert!(other.exists());
+        assert_eq!(prepare(root.path()).unwrap(), dir);
+    }
+}
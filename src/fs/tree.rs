@@ -0,0 +1,371 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use fake::{Dummy, Fake, Faker};
+use rand::seq::SliceRandom;
+use rand::Rng;
+use tempfile::TempDir;
+
+use super::{temp_dir, try_fake_content, Budget, TempFileKind};
+
+const NAME_CHARS: &[u8] = b"abcdefghijklmnopqrstuvwxyz0123456789";
+
+/// Faker of temp directory trees, whose files are laid out to match a glob pattern.
+pub struct TempTreeFaker<L = Faker> {
+    pattern: String,
+    tokens: Vec<Token>,
+    count: usize,
+    kind: TempFileKind,
+    len: L,
+    /// Bytes all the files of a tree may add up to
+    max_total_bytes: Option<usize>,
+}
+
+impl TempTreeFaker<Faker> {
+    /// Lay out `count` distinct files whose paths relative to the root match `pattern`.
+    ///
+    /// The pattern supports `*`, `**`, `?`, character classes like `[0-9]` or `[!a]`, and
+    /// alternatives like `{log,txt}`. It panics if no path can match the pattern, see
+    /// `try_matching`.
+    pub fn matching<S: Into<String>>(pattern: S, count: usize) -> TempTreeFaker<Faker> {
+        let pattern = pattern.into();
+        match TempTreeFaker::try_matching(pattern.as_str(), count) {
+            Ok(faker) => faker,
+            Err(err) => panic!("invalid pattern `{pattern}`: {err}"),
+        }
+    }
+
+    /// Like `matching`, but returning why no path can match the pattern instead.
+    pub fn try_matching<S: Into<String>>(
+        pattern: S,
+        count: usize,
+    ) -> Result<TempTreeFaker<Faker>, PatternError> {
+        let pattern = pattern.into();
+        let tokens = tokenize(pattern.trim_start_matches('/'))?;
+        Ok(TempTreeFaker {
+            pattern,
+            tokens,
+            count,
+            kind: TempFileKind::Text,
+            len: Faker,
+            max_total_bytes: None,
+        })
+    }
+}
+
+impl<L> TempTreeFaker<L> {
+    pub fn kind(mut self, kind: TempFileKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    /// Fail instead of generating trees whose files add up to more than `max` bytes.
+    pub fn max_total_bytes(mut self, max: usize) -> Self {
+        self.max_total_bytes = Some(max);
+        self
+    }
+
+    /// Length of the content of each file, in the unit of the content kind.
+    pub fn len<U>(self, len: U) -> TempTreeFaker<U> {
+        TempTreeFaker {
+            pattern: self.pattern,
+            tokens: self.tokens,
+            count: self.count,
+            kind: self.kind,
+            len,
+            max_total_bytes: self.max_total_bytes,
+        }
+    }
+}
+
+pub struct TempTree {
+    pub root: TempDir,
+    /// Paths of the generated files, relative to the root
+    pub files: Vec<PathBuf>,
+}
+
+impl TempTree {
+    pub fn path(&self) -> &Path {
+        self.root.path()
+    }
+}
+
+impl<L> Dummy<TempTreeFaker<L>> for TempTree
+where
+    u8: Dummy<L>,
+{
+    fn dummy_with_rng<R: Rng + ?Sized>(config: &TempTreeFaker<L>, mut rng: &mut R) -> Self {
+        let root = temp_dir();
+        let files = fake_paths(&config.pattern, &config.tokens, config.count, rng);
+        let mut budget = Budget::new(config.max_total_bytes);
+        for file in &files {
+            let path = root.path().join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+
+            let len = config.len.fake_with_rng::<u8, R>(rng) as usize;
+            let content = try_fake_content(&config.kind, len, &mut budget, &mut rng)
+                .unwrap_or_else(|err| panic!("{err}"));
+            std::fs::write(path, content).unwrap();
+        }
+
+        TempTree { root, files }
+    }
+}
+
+/// A glob pattern no path can match.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PatternError {
+    /// `[]`, a class of no character
+    EmptyClass,
+    /// A range of a class whose end comes before its start, e.g. `[z-a]`
+    ReversedRange { from: char, to: char },
+    /// A negated class of every character generated names are made of
+    ExhaustiveNegation,
+}
+
+impl fmt::Display for PatternError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PatternError::EmptyClass => write!(f, "a character class is empty"),
+            PatternError::ReversedRange { from, to } => {
+                write!(
+                    f,
+                    "the range `{from}-{to}` of a character class is reversed"
+                )
+            }
+            PatternError::ExhaustiveNegation => write!(
+                f,
+                "a negated character class excludes every character of generated names"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PatternError {}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Literal(char),
+    /// `*`, any run of characters within a path segment
+    Star,
+    /// `**`, any number of whole path segments
+    Globstar,
+    /// `?`, any single character
+    Question,
+    /// `[...]`, one of the characters, or any but them when negated
+    Class {
+        chars: Vec<char>,
+        negated: bool,
+    },
+    /// `{a,b}`, one of the alternatives
+    Alternatives(Vec<String>),
+}
+
+fn tokenize(pattern: &str) -> Result<Vec<Token>, PatternError> {
+    let mut tokens = Vec::new();
+    let mut chars = pattern.chars().peekable();
+    while let Some(c) = chars.next() {
+        let token = match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                // swallow the separator, as the globstar generates whole segments
+                if chars.peek() == Some(&'/') {
+                    chars.next();
+                }
+                Token::Globstar
+            }
+            '*' => Token::Star,
+            '?' => Token::Question,
+            '[' => {
+                let negated = matches!(chars.peek(), Some('!') | Some('^'));
+                if negated {
+                    chars.next();
+                }
+                let mut class = Vec::new();
+                while let Some(c) = chars.next() {
+                    match c {
+                        ']' => break,
+                        '-' if !class.is_empty() && chars.peek().is_some_and(|&c| c != ']') => {
+                            let (from, to) = (class.pop().unwrap(), chars.next().unwrap());
+                            if from > to {
+                                return Err(PatternError::ReversedRange { from, to });
+                            }
+                            class.extend(from..=to);
+                        }
+                        c => class.push(c),
+                    }
+                }
+                if class.is_empty() {
+                    return Err(PatternError::EmptyClass);
+                }
+                let excluded = |&c: &u8| class.contains(&(c as char));
+                if negated && NAME_CHARS.iter().all(excluded) {
+                    return Err(PatternError::ExhaustiveNegation);
+                }
+                Token::Class {
+                    chars: class,
+                    negated,
+                }
+            }
+            '{' => {
+                let alternatives = chars.by_ref().take_while(|&c| c != '}').collect::<String>();
+                Token::Alternatives(alternatives.split(',').map(str::to_string).collect())
+            }
+            c => Token::Literal(c),
+        };
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+fn fake_name_char<R: Rng + ?Sized>(rng: &mut R) -> char {
+    *NAME_CHARS.choose(rng).unwrap() as char
+}
+
+fn fake_path<R: Rng + ?Sized>(tokens: &[Token], rng: &mut R) -> String {
+    let mut path = String::new();
+    for token in tokens {
+        match token {
+            Token::Literal(c) => path.push(*c),
+            Token::Star => {
+                let len = rng.gen_range(1..=6);
+                path.extend((0..len).map(|_| fake_name_char(rng)));
+            }
+            Token::Globstar => {
+                for _ in 0..rng.gen_range(0..=2) {
+                    let len = rng.gen_range(1..=6);
+                    path.extend((0..len).map(|_| fake_name_char(rng)));
+                    path.push('/');
+                }
+            }
+            Token::Question => path.push(fake_name_char(rng)),
+            Token::Class {
+                chars,
+                negated: false,
+            } => path.push(*chars.choose(rng).unwrap()),
+            Token::Class {
+                chars,
+                negated: true,
+            } => loop {
+                let c = fake_name_char(rng);
+                if !chars.contains(&c) {
+                    path.push(c);
+                    break;
+                }
+            },
+            Token::Alternatives(alternatives) => path.push_str(alternatives.choose(rng).unwrap()),
+        }
+    }
+    path
+}
+
+/// Generate `count` distinct relative file paths matching `pattern`, tokenized as `tokens`,
+/// none of which is a directory of another.
+fn fake_paths<R: Rng + ?Sized>(
+    pattern: &str,
+    tokens: &[Token],
+    count: usize,
+    rng: &mut R,
+) -> Vec<PathBuf> {
+    let mut files = Vec::with_capacity(count);
+    let mut seen_files = HashSet::new();
+    let mut seen_dirs = HashSet::new();
+
+    let max_attempts = count.saturating_mul(100).max(100);
+    for _ in 0..max_attempts {
+        if files.len() == count {
+            break;
+        }
+
+        let path = PathBuf::from(fake_path(tokens, rng));
+        let dirs = path.ancestors().skip(1).collect::<Vec<_>>();
+        if seen_files.contains(&path)
+            || seen_dirs.contains(&path)
+            || dirs.iter().any(|dir| seen_files.contains(*dir))
+        {
+            continue;
+        }
+
+        seen_dirs.extend(dirs.into_iter().map(Path::to_path_buf));
+        seen_files.insert(path.clone());
+        files.push(path);
+    }
+
+    assert_eq!(
+        files.len(),
+        count,
+        "pattern `{pattern}` cannot produce {count} distinct paths"
+    );
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokenize() {
+        assert_eq!(
+            tokenize("a/**/[!x0-2]?.{md,txt}").unwrap(),
+            vec![
+                Token::Literal('a'),
+                Token::Literal('/'),
+                Token::Globstar,
+                Token::Class {
+                    chars: vec!['x', '0', '1', '2'],
+                    negated: true
+                },
+                Token::Question,
+                Token::Literal('.'),
+                Token::Alternatives(vec!["md".to_string(), "txt".to_string()]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_fake_temp_tree_matching() {
+        let root_path: PathBuf;
+        {
+            let tree = TempTreeFaker::matching("logs/2024-*/app-[0-9]?.{log,txt}", 20)
+                .len(1..5)
+                .fake::<TempTree>();
+            root_path = tree.path().to_path_buf();
+
+            assert_eq!(tree.files.len(), 20);
+            for file in &tree.files {
+                let path = file.to_str().unwrap();
+                let (dir, name) = path.rsplit_once('/').unwrap();
+                let (stem, ext) = name.split_once('.').unwrap();
+
+                assert!(dir.starts_with("logs/2024-") && dir.len() > "logs/2024-".len());
+                assert!(stem.starts_with("app-") && stem.len() == 6);
+                assert!(stem.as_bytes()[4].is_ascii_digit());
+                assert!(ext == "log" || ext == "txt");
+                assert!(tree.path().join(file).is_file());
+            }
+        }
+        assert!(!root_path.exists());
+    }
+
+    #[test]
+    fn test_invalid_patterns() {
+        assert_eq!(tokenize("[]").unwrap_err(), PatternError::EmptyClass);
+        assert_eq!(
+            tokenize("[z-a]").unwrap_err(),
+            PatternError::ReversedRange { from: 'z', to: 'a' }
+        );
+        assert_eq!(
+            tokenize("[!a-z0-9]").unwrap_err(),
+            PatternError::ExhaustiveNegation
+        );
+        assert!(tokenize("[!a-y0-9]").is_ok());
+        assert!(TempTreeFaker::try_matching("logs/[]", 1).is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot produce 3 distinct paths")]
+    fn test_fake_temp_tree_with_exhausted_pattern() {
+        TempTreeFaker::matching("{a,b}.txt", 3).fake::<TempTree>();
+    }
+}
@@ -1,29 +1,69 @@
-use std::cell::RefCell;
+use std::io::{self, Read};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
 
-use fake::faker::filesystem::en::FileName;
 use fake::{Dummy, Fake, Faker};
+use md5::{Digest, Md5};
 use mongodb::bson::oid::ObjectId;
+use mongodb::bson::{doc, DateTime, Document};
+use mongodb::options::IndexOptions;
+use mongodb::{Database, IndexModel};
+use mongodb_gridfs::options::GridFSUploadOptions;
 use mongodb_gridfs::GridFSBucket;
-use rand::Rng;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use sha2::Sha256;
 
-use crate::fs::{fake_content, TempFileKind};
+use crate::fs::{
+    fake_filename, try_fake_content, Budget, Charset, FakeContentReader, TempFileKind,
+};
+
+pub use snapshot::{assert_bucket_matches, BucketManifest, BucketSnapshotFaker, ManifestEntry};
+pub use storage::GridFsStorage;
+
+mod snapshot;
+mod storage;
+
+/// Field of the file metadata holding the expiry time, see `TempFileFaker::expires_at`
+pub const EXPIRES_AT: &str = "expiresAt";
+/// Fields of the file metadata holding the hex-encoded digests of the content, see
+/// `TempFileFaker::store_digests`
+pub const MD5: &str = "md5";
+pub const SHA256: &str = "sha256";
+
+/// Callback given the bytes uploaded so far and the total length of the file
+type ProgressFn = Arc<dyn Fn(usize, usize) + Send + Sync>;
 
 pub struct TempFileFaker<L = Faker> {
     kind: TempFileKind,
     name: String,
     len: L,
+    /// Whether `len` counts bytes rather than units of the content kind
+    len_in_bytes: bool,
     include_content: bool,
-    bucket: RefCell<GridFSBucket>,
+    metadata: Option<Document>,
+    expires_at: Option<SystemTime>,
+    store_digests: bool,
+    max_total_bytes: Option<usize>,
+    progress: Option<ProgressFn>,
+    /// Cloned for every upload, so that the faker can be shared across threads
+    bucket: GridFSBucket,
 }
 
 impl TempFileFaker<Faker> {
     pub fn with_bucket(bucket: GridFSBucket) -> Self {
         TempFileFaker {
             kind: TempFileKind::Text,
-            name: FileName().fake(),
+            name: fake_filename(&["txt"], 4..16, Charset::Alphanumeric),
             len: Faker,
+            len_in_bytes: false,
             include_content: false,
-            bucket: RefCell::new(bucket),
+            metadata: None,
+            expires_at: None,
+            store_digests: false,
+            max_total_bytes: None,
+            progress: None,
+            bucket,
         }
     }
 }
@@ -42,46 +82,421 @@ impl<L> TempFileFaker<L> {
             kind: self.kind,
             name: self.name,
             len,
+            len_in_bytes: false,
             include_content: self.include_content,
+            metadata: self.metadata,
+            expires_at: self.expires_at,
+            store_digests: self.store_digests,
+            max_total_bytes: self.max_total_bytes,
+            progress: self.progress,
             bucket: self.bucket,
         }
     }
 
+    /// Measure the content in bytes instead of units of the content kind.
+    ///
+    /// The content is then generated and uploaded chunk by chunk, so that files of hundreds of
+    /// megabytes never have to fit in memory unless `include_content` is set.
+    pub fn len_bytes<U>(self, len: U) -> TempFileFaker<U> {
+        TempFileFaker {
+            len_in_bytes: true,
+            ..self.len(len)
+        }
+    }
+
     pub fn include_content(self, include_content: bool) -> Self {
         Self {
             include_content,
             ..self
         }
     }
+
+    /// Store `metadata` in the `metadata` field of the uploaded files.
+    pub fn metadata(self, metadata: Document) -> Self {
+        Self {
+            metadata: Some(metadata),
+            ..self
+        }
+    }
+
+    /// Stamp the uploaded files with `time` as the `expiresAt` field of their metadata.
+    ///
+    /// Files only get removed once the TTL index is set up by `create_expiry_index`.
+    pub fn expires_at(self, time: SystemTime) -> Self {
+        Self {
+            expires_at: Some(time),
+            ..self
+        }
+    }
+
+    /// Store the digests of the content in the `md5` and `sha256` fields of the file metadata,
+    /// for code which verifies downloads against them.
+    ///
+    /// Contents measured in bytes are then generated twice, once for the digests and once for
+    /// the upload, to keep them out of memory.
+    pub fn store_digests(self, store_digests: bool) -> Self {
+        Self {
+            store_digests,
+            ..self
+        }
+    }
+
+    /// Fail instead of uploading files of more than `max` bytes, e.g. when the range given to
+    /// `len_bytes` is misconfigured.
+    pub fn max_total_bytes(self, max: usize) -> Self {
+        Self {
+            max_total_bytes: Some(max),
+            ..self
+        }
+    }
+
+    /// Call `progress` with the bytes uploaded so far and the total length of the file every
+    /// time a chunk is read for the upload, e.g. to display the progress of slow fixtures.
+    pub fn progress<F>(self, progress: F) -> Self
+    where
+        F: Fn(usize, usize) + Send + Sync + 'static,
+    {
+        Self {
+            progress: Some(Arc::new(progress)),
+            ..self
+        }
+    }
+
+    /// Upload the content of a fake local file, with the name and metadata of the faker, for
+    /// tests of local to cloud synchronization. Both files are kept together in the result.
+    #[cfg(feature = "fs")]
+    pub fn from_local(&self, local: crate::fs::TempFile) -> LinkedTempFile {
+        let content = match local.content.as_ref() {
+            Some(content) => content.clone(),
+            None => std::fs::read(&local.path).unwrap(),
+        };
+        Budget::new(self.max_total_bytes).spend(content.len());
+        let upload = upload_content(self, base_metadata(self), content, self.include_content);
+        LinkedTempFile {
+            local,
+            remote: upload.into_temp_file(),
+        }
+    }
+}
+
+/// Create a TTL index on the files collection of bucket `bucket_name`, so that files are removed
+/// by the server once their `expiresAt` metadata is past.
+///
+/// The server only checks expiry about once a minute.
+pub async fn create_expiry_index(db: &Database, bucket_name: &str) -> mongodb::error::Result<()> {
+    let index = IndexModel::builder()
+        .keys(doc! { format!("metadata.{EXPIRES_AT}"): 1 })
+        .options(IndexOptions::builder().expire_after(Duration::ZERO).build())
+        .build();
+    db.collection::<Document>(&format!("{bucket_name}.files"))
+        .create_index(index, None)
+        .await?;
+    Ok(())
 }
 
 pub struct TempFile {
     pub id: ObjectId,
     pub filename: Option<String>,
     pub content: Option<Vec<u8>>,
+    /// Hex-encoded md5 digest of the content
+    pub md5: String,
+    /// Hex-encoded sha256 digest of the content
+    pub sha256: String,
+    /// Measured rate of the upload, e.g. to assert a minimum performance of the storage
+    pub throughput: Throughput,
+}
+
+/// Bytes uploaded and the time it took, generating the content streamed included.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Throughput {
+    pub bytes: usize,
+    pub elapsed: Duration,
+}
+
+impl Throughput {
+    pub fn bytes_per_sec(&self) -> f64 {
+        self.bytes as f64 / self.elapsed.as_secs_f64().max(f64::MIN_POSITIVE)
+    }
+}
+
+/// A reference to an uploaded fake file, without its content.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GridFsFileDescriptor {
+    pub id: ObjectId,
+    pub name: String,
+    /// Length of the content in bytes
+    pub len: usize,
+    /// Hex-encoded md5 digest of the content
+    pub md5: String,
+    /// Hex-encoded sha256 digest of the content
+    pub sha256: String,
+}
+
+/// A local fake file along with its upload, see `TempFileFaker::from_local`.
+#[cfg(feature = "fs")]
+pub struct LinkedTempFile {
+    pub local: crate::fs::TempFile,
+    pub remote: TempFile,
+}
+
+#[cfg(feature = "fs")]
+impl LinkedTempFile {
+    /// Whether the local file no longer has the content uploaded.
+    pub fn local_changed(&self) -> io::Result<bool> {
+        let content = std::fs::read(&self.local.path)?;
+        Ok(format!("{:x}", Md5::digest(&content)) != self.remote.md5)
+    }
 }
 
 impl<L> Dummy<TempFileFaker<L>> for TempFile
 where
     usize: Dummy<L>,
 {
-    fn dummy_with_rng<R: Rng + ?Sized>(config: &TempFileFaker<L>, mut rng: &mut R) -> Self {
-        let len = config.len.fake_with_rng::<usize, R>(rng);
-        let content = fake_content(&config.kind, len, &mut rng);
+    fn dummy_with_rng<R: Rng + ?Sized>(config: &TempFileFaker<L>, rng: &mut R) -> Self {
+        upload(config, config.include_content, rng).into_temp_file()
+    }
+}
 
-        let mut bucket = config.bucket.borrow_mut();
-        let oid_fut = bucket.upload_from_stream(&config.name, content.as_slice(), None);
+impl<L> Dummy<TempFileFaker<L>> for GridFsFileDescriptor
+where
+    usize: Dummy<L>,
+{
+    fn dummy_with_rng<R: Rng + ?Sized>(config: &TempFileFaker<L>, rng: &mut R) -> Self {
+        upload(config, false, rng).descriptor
+    }
+}
 
+impl<L> Dummy<TempFileFaker<L>> for ObjectId
+where
+    usize: Dummy<L>,
+{
+    fn dummy_with_rng<R: Rng + ?Sized>(config: &TempFileFaker<L>, rng: &mut R) -> Self {
+        upload(config, false, rng).descriptor.id
+    }
+}
+
+struct Upload {
+    descriptor: GridFsFileDescriptor,
+    content: Option<Vec<u8>>,
+    throughput: Throughput,
+}
+
+impl Upload {
+    fn into_temp_file(self) -> TempFile {
         TempFile {
-            id: futures::executor::block_on(oid_fut).unwrap(),
-            filename: Some(config.name.clone()),
-            content: if config.include_content {
-                Some(content)
-            } else {
-                None
+            id: self.descriptor.id,
+            filename: Some(self.descriptor.name),
+            content: self.content,
+            md5: self.descriptor.md5,
+            sha256: self.descriptor.sha256,
+            throughput: self.throughput,
+        }
+    }
+}
+
+fn upload<L, R: Rng + ?Sized>(
+    config: &TempFileFaker<L>,
+    include_content: bool,
+    mut rng: &mut R,
+) -> Upload
+where
+    usize: Dummy<L>,
+{
+    let len = config.len.fake_with_rng::<usize, R>(rng);
+    let mut metadata = base_metadata(config);
+    let mut budget = Budget::new(config.max_total_bytes);
+    if !config.len_in_bytes {
+        let content = try_fake_content(&config.kind, len, &mut budget, &mut rng)
+            .unwrap_or_else(|err| panic!("{err}"));
+        return upload_content(config, metadata, content, include_content);
+    }
+
+    // checked up front, so that a huge length fails before uploading anything
+    budget.spend(len);
+    // the same seed replays the same content for the digests and the upload
+    let seed = rng.gen();
+    if config.store_digests {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut reader = HashingReader::new(FakeContentReader::new(&config.kind, len, &mut rng));
+        io::copy(&mut reader, &mut io::sink()).unwrap();
+        reader.split().0.insert_into(&mut metadata);
+    }
+
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut reader = HashingReader::new(
+        FakeContentReader::new(&config.kind, len, &mut rng).tee(include_content),
+    );
+    let (id, elapsed) = upload_stream(config, metadata, &mut reader, len);
+    let (digest, inner) = reader.split();
+    digest.into_upload(id, config.name.clone(), inner.into_content(), elapsed)
+}
+
+/// Upload `content`, already generated or read, with the name and metadata of `config`.
+fn upload_content<L>(
+    config: &TempFileFaker<L>,
+    mut metadata: Option<Document>,
+    content: Vec<u8>,
+    include_content: bool,
+) -> Upload {
+    if config.store_digests {
+        let mut digest = ContentDigest::new();
+        digest.update(&content);
+        digest.insert_into(&mut metadata);
+    }
+
+    let mut reader = HashingReader::new(content.as_slice());
+    let (id, elapsed) = upload_stream(config, metadata, &mut reader, content.len());
+    let (digest, _) = reader.split();
+    let content = include_content.then_some(content);
+    digest.into_upload(id, config.name.clone(), content, elapsed)
+}
+
+/// Upload the `total` bytes of `reader`, returning the id of the file and the time it took.
+fn upload_stream<L, I: Read>(
+    config: &TempFileFaker<L>,
+    metadata: Option<Document>,
+    reader: &mut HashingReader<I>,
+    total: usize,
+) -> (ObjectId, Duration) {
+    let mut bucket = config.bucket.clone();
+    let reader = ProgressReader {
+        inner: reader,
+        uploaded: 0,
+        total,
+        progress: config.progress.as_deref(),
+    };
+    let start = Instant::now();
+    let oid_fut = bucket.upload_from_stream(&config.name, reader, options(metadata));
+    let id = futures::executor::block_on(oid_fut).unwrap();
+    (id, start.elapsed())
+}
+
+/// The metadata configured on the faker, expiry time included.
+fn base_metadata<L>(config: &TempFileFaker<L>) -> Option<Document> {
+    let mut metadata = config.metadata.clone();
+    if let Some(time) = config.expires_at {
+        metadata
+            .get_or_insert_with(Document::new)
+            .insert(EXPIRES_AT, DateTime::from_system_time(time));
+    }
+    metadata
+}
+
+fn options(metadata: Option<Document>) -> Option<GridFSUploadOptions> {
+    metadata.map(|metadata| {
+        GridFSUploadOptions::builder()
+            .metadata(Some(metadata))
+            .build()
+    })
+}
+
+/// Digest of the content passed through a `HashingReader`.
+#[derive(Clone)]
+struct ContentDigest {
+    len: usize,
+    md5: Md5,
+    sha256: Sha256,
+}
+
+impl ContentDigest {
+    fn new() -> Self {
+        ContentDigest {
+            len: 0,
+            md5: Md5::new(),
+            sha256: Sha256::new(),
+        }
+    }
+
+    fn update(&mut self, bytes: &[u8]) {
+        self.len += bytes.len();
+        self.md5.update(bytes);
+        self.sha256.update(bytes);
+    }
+
+    /// The hex-encoded md5 and sha256 digests.
+    fn finalize(&self) -> (String, String) {
+        (
+            format!("{:x}", self.md5.clone().finalize()),
+            format!("{:x}", self.sha256.clone().finalize()),
+        )
+    }
+
+    fn into_upload(
+        self,
+        id: ObjectId,
+        name: String,
+        content: Option<Vec<u8>>,
+        elapsed: Duration,
+    ) -> Upload {
+        let (md5, sha256) = self.finalize();
+        Upload {
+            throughput: Throughput {
+                bytes: self.len,
+                elapsed,
             },
+            descriptor: GridFsFileDescriptor {
+                id,
+                name,
+                len: self.len,
+                md5,
+                sha256,
+            },
+            content,
         }
     }
+
+    fn insert_into(&self, metadata: &mut Option<Document>) {
+        let (md5, sha256) = self.finalize();
+        let metadata = metadata.get_or_insert_with(Document::new);
+        metadata.insert(MD5, md5);
+        metadata.insert(SHA256, sha256);
+    }
+}
+
+struct HashingReader<I> {
+    inner: I,
+    digest: ContentDigest,
+}
+
+impl<I: Read> HashingReader<I> {
+    fn new(inner: I) -> Self {
+        HashingReader {
+            inner,
+            digest: ContentDigest::new(),
+        }
+    }
+
+    fn split(self) -> (ContentDigest, I) {
+        (self.digest, self.inner)
+    }
+}
+
+impl<I: Read> Read for HashingReader<I> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.digest.update(&buf[..n]);
+        Ok(n)
+    }
+}
+
+/// Reports the bytes read so far to the progress callback of the faker, if any.
+struct ProgressReader<'a, I> {
+    inner: I,
+    uploaded: usize,
+    total: usize,
+    progress: Option<&'a (dyn Fn(usize, usize) + Send + Sync)>,
+}
+
+impl<I: Read> Read for ProgressReader<'_, I> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.uploaded += n;
+        match self.progress {
+            Some(progress) if n > 0 => progress(self.uploaded, self.total),
+            _ => {}
+        }
+        Ok(n)
+    }
 }
 
 #[cfg(test)]
@@ -89,17 +504,17 @@ mod tests {
     use futures::StreamExt;
     use mongodb::Client;
 
-    use crate::docker::Builder as ContainerBuilder;
+    use crate::docker::{Builder as ContainerBuilder, HostPort};
 
     use super::*;
 
     #[tokio::test]
     async fn test_fake_temp_file() {
         let handler = ContainerBuilder::new("mongo")
-            .bind_port_as_default(Some("0"), "27017")
+            .bind_port_as_default(Some(HostPort::ANY), 27017)
             .build_disposable()
             .await;
-        let db = Client::with_uri_str(handler.url())
+        let db = Client::with_uri_str(handler.url().unwrap())
             .await
             .unwrap()
             .database("testdb");
@@ -120,4 +535,188 @@ mod tests {
         assert_eq!(cloud_filename, temp_file.filename.unwrap());
         assert_eq!(cloud_content, temp_file.content.unwrap());
     }
+
+    #[tokio::test]
+    async fn test_fake_temp_file_len_bytes() {
+        let handler = ContainerBuilder::new("mongo")
+            .bind_port_as_default(Some(HostPort::ANY), 27017)
+            .build_disposable()
+            .await;
+        let db = Client::with_uri_str(handler.url().unwrap())
+            .await
+            .unwrap()
+            .database("testdb");
+        let bucket = GridFSBucket::new(db, None);
+        let reported = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let progress = reported.clone();
+        let temp_file = TempFileFaker::with_bucket(bucket.clone())
+            .len_bytes(600_000..600_001)
+            .include_content(true)
+            .progress(move |uploaded, total| progress.lock().unwrap().push((uploaded, total)))
+            .fake::<TempFile>();
+
+        let (cursor, _) = bucket
+            .open_download_stream_with_filename(temp_file.id)
+            .await
+            .unwrap();
+        let cloud_content: Vec<u8> = cursor.concat().await;
+
+        assert_eq!(cloud_content.len(), 600_000);
+        assert_eq!(cloud_content, temp_file.content.unwrap());
+        assert_eq!(reported.lock().unwrap().last(), Some(&(600_000, 600_000)));
+        assert_eq!(temp_file.throughput.bytes, 600_000);
+        assert!(temp_file.throughput.bytes_per_sec() > 0.0);
+    }
+
+    #[test]
+    fn test_progress_reader() {
+        let reported = std::sync::Mutex::new(Vec::new());
+        let progress = |uploaded, total| reported.lock().unwrap().push((uploaded, total));
+        let mut reader = ProgressReader {
+            inner: [0u8; 10].as_slice(),
+            uploaded: 0,
+            total: 10,
+            progress: Some(&progress),
+        };
+        let mut buf = [0u8; 4];
+        while reader.read(&mut buf).unwrap() > 0 {}
+
+        assert_eq!(*reported.lock().unwrap(), [(4, 10), (8, 10), (10, 10)]);
+    }
+
+    #[tokio::test]
+    async fn test_store_digests() {
+        let handler = ContainerBuilder::new("mongo")
+            .bind_port_as_default(Some(HostPort::ANY), 27017)
+            .build_disposable()
+            .await;
+        let db = Client::with_uri_str(handler.url().unwrap())
+            .await
+            .unwrap()
+            .database("testdb");
+        let bucket = GridFSBucket::new(db.clone(), None);
+        let files = db.collection::<Document>("fs.files");
+
+        for faker in [
+            TempFileFaker::with_bucket(bucket.clone()).len_bytes(100_000..100_001),
+            TempFileFaker::with_bucket(bucket.clone()).len(10..20),
+        ] {
+            let temp_file = faker.store_digests(true).fake::<TempFile>();
+            let (cursor, _) = bucket
+                .open_download_stream_with_filename(temp_file.id)
+                .await
+                .unwrap();
+            let cloud_content: Vec<u8> = cursor.concat().await;
+            assert_eq!(
+                format!("{:x}", Sha256::digest(&cloud_content)),
+                temp_file.sha256
+            );
+
+            let file = files
+                .find_one(doc! { "_id": temp_file.id }, None)
+                .await
+                .unwrap()
+                .unwrap();
+            let metadata = file.get_document("metadata").unwrap();
+            assert_eq!(metadata.get_str(MD5).unwrap(), temp_file.md5);
+            assert_eq!(metadata.get_str(SHA256).unwrap(), temp_file.sha256);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fake_file_descriptor() {
+        let handler = ContainerBuilder::new("mongo")
+            .bind_port_as_default(Some(HostPort::ANY), 27017)
+            .build_disposable()
+            .await;
+        let db = Client::with_uri_str(handler.url().unwrap())
+            .await
+            .unwrap()
+            .database("testdb");
+        let bucket = GridFSBucket::new(db, None);
+        let faker = TempFileFaker::with_bucket(bucket.clone()).len_bytes(1000..2000);
+
+        let descriptor = faker.fake::<GridFsFileDescriptor>();
+        let (cursor, cloud_filename) = bucket
+            .open_download_stream_with_filename(descriptor.id)
+            .await
+            .unwrap();
+        let cloud_content: Vec<u8> = cursor.concat().await;
+
+        assert_eq!(cloud_filename, descriptor.name);
+        assert_eq!(cloud_content.len(), descriptor.len);
+        assert_eq!(format!("{:x}", Md5::digest(&cloud_content)), descriptor.md5);
+
+        let id = faker.fake::<ObjectId>();
+        assert!(bucket.open_download_stream(id).await.is_ok());
+    }
+
+    #[cfg(feature = "fs")]
+    #[tokio::test]
+    async fn test_upload_from_local() {
+        let handler = ContainerBuilder::new("mongo")
+            .bind_port_as_default(Some(HostPort::ANY), 27017)
+            .build_disposable()
+            .await;
+        let db = Client::with_uri_str(handler.url().unwrap())
+            .await
+            .unwrap()
+            .database("testdb");
+        let bucket = GridFSBucket::new(db, None);
+        let local = crate::fs::TempFileFaker::with_len(20..40).fake::<crate::fs::TempFile>();
+
+        let linked = TempFileFaker::with_bucket(bucket.clone()).from_local(local);
+        let (cursor, _) = bucket
+            .open_download_stream_with_filename(linked.remote.id)
+            .await
+            .unwrap();
+        let cloud_content: Vec<u8> = cursor.concat().await;
+        assert_eq!(Some(cloud_content), linked.local.content.clone());
+        assert!(!linked.local_changed().unwrap());
+
+        std::fs::write(&linked.local.path, "edited").unwrap();
+        assert!(linked.local_changed().unwrap());
+    }
+
+    #[test]
+    fn test_faker_is_sync() {
+        fn assert_sync<T: Send + Sync>() {}
+        assert_sync::<TempFileFaker<std::ops::Range<usize>>>();
+    }
+
+    #[tokio::test]
+    async fn test_fake_temp_file_expiring() {
+        let handler = ContainerBuilder::new("mongo")
+            .bind_port_as_default(Some(HostPort::ANY), 27017)
+            .build_disposable()
+            .await;
+        let db = Client::with_uri_str(handler.url().unwrap())
+            .await
+            .unwrap()
+            .database("testdb");
+        create_expiry_index(&db, "fs").await.unwrap();
+        let bucket = GridFSBucket::new(db.clone(), None);
+
+        let expires_at = SystemTime::now() + Duration::from_secs(3600);
+        let descriptor = TempFileFaker::with_bucket(bucket)
+            .len(1..10)
+            .metadata(doc! { "owner": "alice" })
+            .expires_at(expires_at)
+            .fake::<GridFsFileDescriptor>();
+
+        let files = db.collection::<Document>("fs.files");
+        let file = files
+            .find_one(doc! { "_id": descriptor.id }, None)
+            .await
+            .unwrap()
+            .unwrap();
+        let metadata = file.get_document("metadata").unwrap();
+        assert_eq!(metadata.get_str("owner").unwrap(), "alice");
+        assert_eq!(
+            metadata.get_datetime(EXPIRES_AT).unwrap(),
+            &DateTime::from_system_time(expires_at)
+        );
+        let indexes = files.list_index_names().await.unwrap();
+        assert!(indexes.contains(&format!("metadata.{EXPIRES_AT}_1")));
+    }
 }
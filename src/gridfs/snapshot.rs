@@ -0,0 +1,216 @@
+use std::collections::HashMap;
+use std::ops::Range;
+
+use fake::{Dummy, Fake};
+use futures::{StreamExt, TryStreamExt};
+use md5::{Digest, Md5};
+use mongodb::bson::{doc, Bson, Document};
+use mongodb_gridfs::options::GridFSFindOptions;
+use mongodb_gridfs::GridFSBucket;
+use rand::Rng;
+
+use crate::fs::TempFileKind;
+
+use super::{GridFsFileDescriptor, TempFileFaker};
+
+/// Populate a bucket with a described set of fake files, producing a `BucketManifest` of what
+/// was uploaded.
+pub struct BucketSnapshotFaker {
+    bucket: GridFSBucket,
+    kind: TempFileKind,
+    files: Vec<FileSpec>,
+}
+
+struct FileSpec {
+    name: String,
+    /// Length of the content in bytes
+    len: Range<usize>,
+    metadata: Option<Document>,
+}
+
+impl BucketSnapshotFaker {
+    pub fn with_bucket(bucket: GridFSBucket) -> Self {
+        BucketSnapshotFaker {
+            bucket,
+            kind: TempFileKind::Text,
+            files: Vec::new(),
+        }
+    }
+
+    pub fn kind(self, kind: TempFileKind) -> Self {
+        Self { kind, ..self }
+    }
+
+    /// Add a file of `len` bytes.
+    pub fn file<S: Into<String>>(mut self, name: S, len: Range<usize>) -> Self {
+        self.files.push(FileSpec {
+            name: name.into(),
+            len,
+            metadata: None,
+        });
+        self
+    }
+
+    /// Add a file of `len` bytes, with `metadata` stored in its `metadata` field.
+    pub fn file_with_metadata<S: Into<String>>(
+        mut self,
+        name: S,
+        len: Range<usize>,
+        metadata: Document,
+    ) -> Self {
+        self.files.push(FileSpec {
+            name: name.into(),
+            len,
+            metadata: Some(metadata),
+        });
+        self
+    }
+}
+
+/// The files uploaded by a `BucketSnapshotFaker`, in the order they were described.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BucketManifest {
+    pub files: Vec<ManifestEntry>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ManifestEntry {
+    pub file: GridFsFileDescriptor,
+    pub metadata: Option<Document>,
+}
+
+impl Dummy<BucketSnapshotFaker> for BucketManifest {
+    fn dummy_with_rng<R: Rng + ?Sized>(config: &BucketSnapshotFaker, rng: &mut R) -> Self {
+        let files = config
+            .files
+            .iter()
+            .map(|spec| {
+                let mut faker = TempFileFaker::with_bucket(config.bucket.clone())
+                    .kind(config.kind.clone())
+                    .name(spec.name.clone())
+                    .len_bytes(spec.len.clone());
+                if let Some(metadata) = spec.metadata.clone() {
+                    faker = faker.metadata(metadata);
+                }
+
+                ManifestEntry {
+                    file: faker.fake_with_rng(rng),
+                    metadata: spec.metadata.clone(),
+                }
+            })
+            .collect();
+
+        BucketManifest { files }
+    }
+}
+
+/// Assert that `bucket` holds exactly the files of `manifest`, with the same names, contents and
+/// metadata, panicking with a report of every difference otherwise.
+pub async fn assert_bucket_matches(bucket: &GridFSBucket, manifest: &BucketManifest) {
+    let mut expected: HashMap<_, _> = manifest
+        .files
+        .iter()
+        .map(|entry| (entry.file.id, entry))
+        .collect();
+    let actual: Vec<Document> = bucket
+        .find(doc! {}, GridFSFindOptions::default())
+        .await
+        .unwrap()
+        .try_collect()
+        .await
+        .unwrap();
+
+    let mut differences = Vec::new();
+    for file in actual {
+        let id = file.get_object_id("_id").unwrap();
+        let name = file.get_str("filename").unwrap_or_default();
+        let Some(entry) = expected.remove(&id) else {
+            differences.push(format!("unexpected file `{name}` ({id})"));
+            continue;
+        };
+
+        if name != entry.file.name {
+            differences.push(format!(
+                "file {id} is named `{name}` instead of `{}`",
+                entry.file.name
+            ));
+        }
+        let metadata = match file.get("metadata") {
+            Some(Bson::Document(metadata)) => Some(metadata),
+            _ => None,
+        };
+        if metadata != entry.metadata.as_ref() {
+            differences.push(format!(
+                "file `{name}` has metadata {metadata:?} instead of {:?}",
+                entry.metadata
+            ));
+        }
+
+        let content: Vec<u8> = bucket
+            .open_download_stream(id)
+            .await
+            .unwrap()
+            .concat()
+            .await;
+        if content.len() != entry.file.len {
+            differences.push(format!(
+                "file `{name}` has {} bytes instead of {}",
+                content.len(),
+                entry.file.len
+            ));
+        } else if format!("{:x}", Md5::digest(&content)) != entry.file.md5 {
+            differences.push(format!("file `{name}` has different content"));
+        }
+    }
+    for entry in expected.values() {
+        differences.push(format!(
+            "missing file `{}` ({})",
+            entry.file.name, entry.file.id
+        ));
+    }
+
+    assert!(
+        differences.is_empty(),
+        "bucket does not match the manifest:\n  {}",
+        differences.join("\n  ")
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use mongodb::Client;
+
+    use crate::docker::{Builder as ContainerBuilder, HostPort};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_bucket_snapshot() {
+        let handler = ContainerBuilder::new("mongo")
+            .bind_port_as_default(Some(HostPort::ANY), 27017)
+            .build_disposable()
+            .await;
+        let db = Client::with_uri_str(handler.url().unwrap())
+            .await
+            .unwrap()
+            .database("testdb");
+        let bucket = GridFSBucket::new(db, None);
+
+        let manifest = BucketSnapshotFaker::with_bucket(bucket.clone())
+            .file("a.txt", 10..20)
+            .file("b.txt", 0..1)
+            .file_with_metadata("c.txt", 300_000..300_001, doc! { "owner": "alice" })
+            .fake::<BucketManifest>();
+        assert_eq!(manifest.files.len(), 3);
+        assert_eq!(manifest.files[2].file.len, 300_000);
+        assert_bucket_matches(&bucket, &manifest).await;
+
+        let mut incomplete = manifest.clone();
+        incomplete.files.pop();
+        let result = tokio::spawn(async move {
+            assert_bucket_matches(&bucket, &incomplete).await;
+        })
+        .await;
+        assert!(result.is_err());
+    }
+}
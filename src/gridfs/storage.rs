@@ -0,0 +1,144 @@
+use std::collections::BTreeSet;
+use std::io;
+
+use futures::future::BoxFuture;
+use futures::{StreamExt, TryStreamExt};
+use mongodb::bson::oid::ObjectId;
+use mongodb::bson::{doc, Document};
+use mongodb_gridfs::options::GridFSFindOptions;
+use mongodb_gridfs::GridFSBucket;
+
+use crate::fs::Storage;
+
+/// Storage in a GridFS bucket, each name being the filename of a file in it, e.g. of a bucket
+/// populated by `BucketSnapshotFaker`.
+///
+/// GridFS allows several files with the same filename, in which case the latest upload is the
+/// one read, and all of them are replaced on write.
+#[derive(Clone)]
+pub struct GridFsStorage {
+    bucket: GridFSBucket,
+}
+
+impl GridFsStorage {
+    pub fn new(bucket: GridFSBucket) -> Self {
+        GridFsStorage { bucket }
+    }
+
+    pub fn bucket(&self) -> &GridFSBucket {
+        &self.bucket
+    }
+
+    async fn files(&self, filter: Document) -> io::Result<Vec<Document>> {
+        self.bucket
+            .find(filter, GridFSFindOptions::default())
+            .await
+            .map_err(io::Error::other)?
+            .try_collect()
+            .await
+            .map_err(io::Error::other)
+    }
+}
+
+impl Storage for GridFsStorage {
+    fn read<'a>(&'a self, name: &'a str) -> BoxFuture<'a, io::Result<Vec<u8>>> {
+        Box::pin(async move {
+            let files = self.files(doc! { "filename": name }).await?;
+            let latest = files
+                .iter()
+                .max_by_key(|file| file.get_datetime("uploadDate").ok().copied())
+                .and_then(|file| file.get_object_id("_id").ok())
+                .ok_or_else(|| {
+                    io::Error::new(io::ErrorKind::NotFound, format!("no file named `{name}`"))
+                })?;
+            let content: Vec<u8> = self
+                .bucket
+                .open_download_stream(latest)
+                .await
+                .map_err(io::Error::other)?
+                .concat()
+                .await;
+            Ok(content)
+        })
+    }
+
+    fn write<'a>(&'a self, name: &'a str, content: &'a [u8]) -> BoxFuture<'a, io::Result<()>> {
+        Box::pin(async move {
+            let replaced: Vec<ObjectId> = self
+                .files(doc! { "filename": name })
+                .await?
+                .iter()
+                .filter_map(|file| file.get_object_id("_id").ok())
+                .collect();
+            let mut bucket = self.bucket.clone();
+            bucket
+                .upload_from_stream(name, content, None)
+                .await
+                .map_err(io::Error::other)?;
+            for id in replaced {
+                self.bucket.delete(id).await.map_err(io::Error::other)?;
+            }
+            Ok(())
+        })
+    }
+
+    fn list(&self) -> BoxFuture<'_, io::Result<Vec<String>>> {
+        Box::pin(async move {
+            let names: BTreeSet<_> = self
+                .files(doc! {})
+                .await?
+                .iter()
+                .filter_map(|file| file.get_str("filename").ok())
+                .map(ToString::to_string)
+                .collect();
+            Ok(names.into_iter().collect())
+        })
+    }
+}
+
+#[cfg(all(test, feature = "fs"))]
+mod tests {
+    use mongodb::Client;
+
+    use crate::docker::{Builder as ContainerBuilder, HostPort};
+    use crate::fs::DirStorage;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_gridfs_storage() {
+        // code written against the storage abstraction
+        async fn copy_all(storage: &dyn Storage) -> io::Result<()> {
+            for name in storage.list().await? {
+                let content = storage.read(&name).await?;
+                storage.write(&format!("copy/{name}"), &content).await?;
+            }
+            Ok(())
+        }
+
+        let handler = ContainerBuilder::new("mongo")
+            .bind_port_as_default(Some(HostPort::ANY), 27017)
+            .build_disposable()
+            .await;
+        let db = Client::with_uri_str(handler.url().unwrap())
+            .await
+            .unwrap()
+            .database("testdb");
+        let gridfs = GridFsStorage::new(GridFSBucket::new(db, None));
+        let dir = DirStorage::temp();
+
+        for storage in [&gridfs as &dyn Storage, &dir] {
+            storage.write("a.txt", b"first").await.unwrap();
+            storage.write("a.txt", b"second").await.unwrap();
+            assert_eq!(storage.read("a.txt").await.unwrap(), b"second");
+            copy_all(storage).await.unwrap();
+        }
+
+        assert_eq!(gridfs.list().await.unwrap(), ["a.txt", "copy/a.txt"]);
+        assert_eq!(gridfs.list().await.unwrap(), dir.list().await.unwrap());
+        assert_eq!(
+            gridfs.read("missing.txt").await.unwrap_err().kind(),
+            io::ErrorKind::NotFound
+        );
+    }
+}
@@ -0,0 +1,160 @@
+//! Kubernetes clusters run by k3s in a container, for testing controllers and operators
+//! without a pre-provisioned cluster.
+//!
+//! ```no_run
+//! # async fn run() {
+//! use test_utilities::k8s;
+//!
+//! let cluster = k8s::k3s().start().await.unwrap();
+//! std::process::Command::new("kubectl")
+//!     .env("KUBECONFIG", cluster.kubeconfig_path())
+//!     .args(["get", "nodes"])
+//!     .status()
+//!     .unwrap();
+//! # }
+//! ```
+
+use std::io::{self, Write};
+use std::path::Path;
+use std::time::Duration;
+
+use tempfile::TempPath;
+
+use crate::docker::{Builder, ContainerHandle, Error, HostPort, Stage, WaitStrategy};
+
+const DEFAULT_IMAGE: &str = "rancher/k3s:v1.29.4-k3s1";
+const API_PORT: u16 = 6443;
+const KUBECONFIG_PATH: &str = "/etc/rancher/k3s/k3s.yaml";
+/// Logged once the API server serves requests
+const READY_LOG: &str = "k3s is up and running";
+const START_TIMEOUT: Duration = Duration::from_secs(180);
+const KUBECONFIG_ATTEMPTS: usize = 50;
+const KUBECONFIG_RETRY_DELAY: Duration = Duration::from_millis(200);
+
+/// A single node k3s cluster, without the bundled ingress controller.
+pub fn k3s() -> K3s {
+    K3s {
+        image: DEFAULT_IMAGE.to_string(),
+    }
+}
+
+pub struct K3s {
+    image: String,
+}
+
+impl K3s {
+    /// Image of the cluster, `rancher/k3s:v1.29.4-k3s1` by default.
+    pub fn image<S: Into<String>>(mut self, image: S) -> Self {
+        self.image = image.into();
+        self
+    }
+
+    /// The builder of the cluster, which runs privileged, as k3s manages cgroups and mounts.
+    pub fn builder(self) -> Builder {
+        Builder::new(self.image)
+            .protocol("https")
+            .bind_port_as_default(Some(HostPort::ANY), API_PORT)
+            .cmd(vec!["server", "--disable=traefik"])
+            .configure(|config| {
+                let host_config = config.host_config.get_or_insert_with(Default::default);
+                host_config.privileged = Some(true);
+                host_config.tmpfs = Some(
+                    [("/run", ""), ("/var/run", "")]
+                        .into_iter()
+                        .map(|(path, options)| (path.to_string(), options.to_string()))
+                        .collect(),
+                );
+            })
+            .wait_for(WaitStrategy::LogLine(READY_LOG.to_string()))
+            .wait_timeout(START_TIMEOUT)
+    }
+
+    /// Start the cluster and write its kubeconfig, pointing to the published API port, to a
+    /// temporary file.
+    pub async fn start(self) -> Result<Cluster, Error> {
+        Cluster::new(self.builder().try_build_disposable().await?).await
+    }
+}
+
+/// A running cluster, removed along with its kubeconfig when dropped.
+pub struct Cluster {
+    pub handle: ContainerHandle,
+    kubeconfig: String,
+    kubeconfig_path: TempPath,
+}
+
+impl Cluster {
+    /// Read the kubeconfig of the cluster in `handle`, which k3s writes once the API server is
+    /// up.
+    pub async fn new(handle: ContainerHandle) -> Result<Self, Error> {
+        let mut attempt = 1;
+        let kubeconfig = loop {
+            match handle.copy_out(KUBECONFIG_PATH).await {
+                Ok(kubeconfig) => break kubeconfig,
+                Err(_) if attempt < KUBECONFIG_ATTEMPTS => {
+                    attempt += 1;
+                    tokio::time::sleep(KUBECONFIG_RETRY_DELAY).await;
+                }
+                Err(err) => return Err(err),
+            }
+        };
+        let server = handle.url()?.trim_end_matches('/').to_string();
+        let kubeconfig = String::from_utf8_lossy(&kubeconfig)
+            .replace(&format!("https://127.0.0.1:{API_PORT}"), &server);
+        let kubeconfig_path =
+            write_kubeconfig(&kubeconfig).map_err(|err| Error::new(Stage::Copy, err))?;
+        Ok(Cluster {
+            handle,
+            kubeconfig,
+            kubeconfig_path,
+        })
+    }
+
+    /// The kubeconfig of the cluster admin.
+    pub fn kubeconfig(&self) -> &str {
+        &self.kubeconfig
+    }
+
+    /// Path of the kubeconfig, to point `KUBECONFIG` to.
+    pub fn kubeconfig_path(&self) -> &Path {
+        &self.kubeconfig_path
+    }
+}
+
+fn write_kubeconfig(kubeconfig: &str) -> io::Result<TempPath> {
+    let mut file = tempfile::Builder::new()
+        .prefix("test-utilities-kubeconfig-")
+        .suffix(".yaml")
+        .tempfile()?;
+    file.write_all(kubeconfig.as_bytes())?;
+    Ok(file.into_temp_path())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::docker::mock::MockDocker;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_kubeconfig() {
+        let docker = MockDocker::new().startup_log(DEFAULT_IMAGE, READY_LOG);
+        let handle = k3s().builder().backend(docker).build_disposable().await;
+        let dir = crate::fs::temp_dir();
+        let local_path = dir.path().join("k3s.yaml");
+        std::fs::write(
+            &local_path,
+            format!("clusters:\n- cluster:\n    server: https://127.0.0.1:{API_PORT}\n"),
+        )
+        .unwrap();
+        handle.copy_in(&local_path, KUBECONFIG_PATH).await.unwrap();
+        let server = handle.url().unwrap();
+
+        let cluster = Cluster::new(handle).await.unwrap();
+        assert!(cluster.kubeconfig().contains(server.trim_end_matches('/')));
+        assert_eq!(
+            std::fs::read_to_string(cluster.kubeconfig_path()).unwrap(),
+            cluster.kubeconfig()
+        );
+    }
+}
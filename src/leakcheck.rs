@@ -0,0 +1,255 @@
+//! Checks that a test left nothing behind, e.g. a handle kept in a static or a temp file
+//! persisted by mistake.
+//!
+//! Open a scope before the fixtures of the test and check it once they are dropped:
+//!
+//! ```no_run
+//! # async fn run() {
+//! use test_utilities::docker::Builder;
+//! use test_utilities::leakcheck;
+//!
+//! let scope = leakcheck::scope();
+//! {
+//!     let _mongo = Builder::new("mongo").build_disposable().await;
+//! }
+//! scope.assert_clean();
+//! # }
+//! ```
+//!
+//! Only the resources the thread of the scope created while it was open are checked, so that
+//! tests running in parallel do not report each other's: the containers and networks labeled
+//! with the session of the process, and the temp paths created by the fakers. Resources created
+//! by tasks spawned on other threads, e.g. of a multi-thread runtime, escape the check. Reused
+//! containers are meant to outlive the process and are not leaks.
+
+use std::cell::RefCell;
+#[cfg(feature = "docker")]
+use std::collections::HashSet;
+use std::fmt;
+use std::marker::PhantomData;
+#[cfg(feature = "fs")]
+use std::path::PathBuf;
+
+#[cfg(feature = "docker")]
+use crate::docker::{connect, reaper, Backend, BackendResult, SESSION_LABEL};
+
+thread_local! {
+    /// Resources created by the thread, for each of its open scopes from the outermost
+    static SCOPES: RefCell<Vec<Vec<Leak>>> = const { RefCell::new(Vec::new()) };
+}
+
+/// A resource left behind.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Leak {
+    #[cfg(feature = "docker")]
+    Container { id: String, name: String },
+    #[cfg(feature = "docker")]
+    Network(String),
+    #[cfg(feature = "fs")]
+    TempPath(PathBuf),
+}
+
+impl fmt::Display for Leak {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            #[cfg(feature = "docker")]
+            Leak::Container { id, name } => write!(f, "container {name} ({id})"),
+            #[cfg(feature = "docker")]
+            Leak::Network(name) => write!(f, "network {name}"),
+            #[cfg(feature = "fs")]
+            Leak::TempPath(path) => write!(f, "temp path {}", path.display()),
+        }
+    }
+}
+
+#[cfg(feature = "docker")]
+impl Leak {
+    fn is_docker(&self) -> bool {
+        match self {
+            Leak::Container { .. } | Leak::Network(_) => true,
+            #[cfg(feature = "fs")]
+            Leak::TempPath(_) => false,
+        }
+    }
+}
+
+/// Start recording the resources the current thread creates, until the returned scope is
+/// dropped. Scopes may be nested, each recording what is created while it is open.
+pub fn scope() -> Scope {
+    SCOPES.with(|scopes| {
+        let mut scopes = scopes.borrow_mut();
+        scopes.push(Vec::new());
+        Scope {
+            depth: scopes.len() - 1,
+            _thread: PhantomData,
+        }
+    })
+}
+
+/// Record `resource` as created in the open scopes of the current thread, if any.
+pub(crate) fn record(resource: Leak) {
+    SCOPES.with(|scopes| {
+        for created in scopes.borrow_mut().iter_mut() {
+            created.push(resource.clone());
+        }
+    });
+}
+
+/// Resources created by a thread while open, see `scope`.
+pub struct Scope {
+    depth: usize,
+    /// Bound to the thread whose resources it records
+    _thread: PhantomData<*const ()>,
+}
+
+impl Scope {
+    /// Panic listing the resources of the scope left behind, if any.
+    pub fn assert_clean(&self) {
+        let leaks = self.leaks();
+        if !leaks.is_empty() {
+            let leaks: Vec<_> = leaks.iter().map(|leak| format!("  {leak}")).collect();
+            panic!("resources were left behind:\n{}", leaks.join("\n"));
+        }
+    }
+
+    /// The resources of the scope left behind, on the local docker daemon and on disk. Failures
+    /// to reach the daemon are logged and skip the check of docker resources.
+    pub fn leaks(&self) -> Vec<Leak> {
+        let created = self.created();
+        let mut leaks = Vec::new();
+        #[cfg(feature = "docker")]
+        if created.iter().any(Leak::is_docker) {
+            let created = created.clone();
+            let listed = crate::docker::block_on(async move {
+                let docker = connect()?;
+                docker_leaks_with(&docker, &created).await
+            });
+            match listed {
+                Ok(Ok(docker_leaks)) => leaks.extend(docker_leaks),
+                Ok(Err(err)) => {
+                    log::warn!("failed to list the docker resources of the session: {err}")
+                }
+                Err(err) => log::warn!("failed to list the docker resources of the session: {err}"),
+            }
+        }
+        #[cfg(feature = "fs")]
+        leaks.extend(created.into_iter().filter(
+            |leak| matches!(leak, Leak::TempPath(path) if path.symlink_metadata().is_ok()),
+        ));
+        leaks
+    }
+
+    /// The containers and networks of the scope left behind on `backend`.
+    #[cfg(feature = "docker")]
+    pub async fn docker_leaks_with<B: Backend + ?Sized>(
+        &self,
+        backend: &B,
+    ) -> BackendResult<Vec<Leak>> {
+        docker_leaks_with(backend, &self.created()).await
+    }
+
+    fn created(&self) -> Vec<Leak> {
+        SCOPES.with(|scopes| scopes.borrow().get(self.depth).cloned().unwrap_or_default())
+    }
+}
+
+impl Drop for Scope {
+    fn drop(&mut self) {
+        SCOPES.with(|scopes| scopes.borrow_mut().truncate(self.depth));
+    }
+}
+
+/// The containers and networks of `created` still on `backend`.
+#[cfg(feature = "docker")]
+async fn docker_leaks_with<B: Backend + ?Sized>(
+    backend: &B,
+    created: &[Leak],
+) -> BackendResult<Vec<Leak>> {
+    let label = format!("{SESSION_LABEL}={}", reaper::session_id());
+    let containers: HashSet<_> = backend
+        .list_containers(&label)
+        .await?
+        .into_iter()
+        .filter_map(|summary| summary.id)
+        .collect();
+    let networks: HashSet<_> = backend.list_networks(&label).await?.into_iter().collect();
+    Ok(created
+        .iter()
+        .filter(|leak| match leak {
+            Leak::Container { id, .. } => containers.contains(id),
+            Leak::Network(name) => networks.contains(name),
+            #[cfg(feature = "fs")]
+            Leak::TempPath(_) => false,
+        })
+        .cloned()
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "docker")]
+    #[tokio::test]
+    async fn test_docker_leaks() {
+        use crate::docker::mock::MockDocker;
+        use crate::docker::{Builder, NetworkHandle};
+
+        let docker = MockDocker::new();
+        // created before the scope
+        let _other = Builder::new("redis")
+            .backend(docker.clone())
+            .build_disposable()
+            .await;
+        let scope = scope();
+        let network = NetworkHandle::create_with(docker.clone(), "leaky-net")
+            .await
+            .unwrap();
+        let handle = network
+            .builder("redis")
+            .name("leaky-redis")
+            .build_disposable()
+            .await;
+
+        let leaks = scope.docker_leaks_with(&docker).await.unwrap();
+        assert_eq!(
+            leaks,
+            [
+                Leak::Network("leaky-net".to_string()),
+                Leak::Container {
+                    id: handle.container_id.clone(),
+                    name: "leaky-redis".to_string()
+                },
+            ]
+        );
+
+        drop(handle);
+        drop(network);
+        assert_eq!(scope.docker_leaks_with(&docker).await.unwrap(), []);
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_temp_path_leaks() {
+        let outer = scope();
+        let inner = scope();
+        let path = crate::fs::temp_file().into_temp_path().keep().unwrap();
+        drop(inner);
+        let after = crate::fs::temp_file().into_temp_path().keep().unwrap();
+        assert_eq!(
+            outer.leaks(),
+            [Leak::TempPath(path.clone()), Leak::TempPath(after.clone())]
+        );
+        // created by another thread, e.g. a test running in parallel
+        let elsewhere = std::thread::spawn(|| crate::fs::temp_file().into_temp_path().keep())
+            .join()
+            .unwrap()
+            .unwrap();
+        assert!(!outer.leaks().contains(&Leak::TempPath(elsewhere.clone())));
+
+        for path in [&path, &after, &elsewhere] {
+            std::fs::remove_file(path).unwrap();
+        }
+        assert_eq!(outer.leaks(), []);
+    }
+}
@@ -1,8 +1,36 @@
+pub mod artifacts;
+
+#[cfg(any(
+    feature = "dataset-usernames",
+    feature = "dataset-domains",
+    feature = "dataset-extensions"
+))]
+pub mod datasets;
+
 #[cfg(feature = "docker")]
 pub mod docker;
 
-#[cfg(feature = "fs")]
+pub mod env;
+
+pub mod fixture;
+
+pub use error::{Error, Result};
+
+mod error;
+
+#[cfg(any(feature = "fs", feature = "no-fs-write"))]
 pub mod fs;
 
 #[cfg(feature = "gridfs")]
 pub mod gridfs;
+
+#[cfg(feature = "k8s")]
+pub mod k8s;
+
+#[cfg(any(feature = "docker", feature = "fs"))]
+pub mod leakcheck;
+
+#[cfg(feature = "mongodb")]
+pub mod mongo;
+
+pub mod rng;
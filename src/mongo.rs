@@ -0,0 +1,243 @@
+use std::time::Duration;
+
+use fake::faker::lorem::en::Word;
+use fake::{Dummy, Fake, Faker};
+use mongodb::bson::{doc, Document};
+use mongodb::Collection;
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+pub use bootstrap::{bootstrap, CollectionSpec, DatabaseSpec, IndexSpec, SeededDatabase};
+
+mod bootstrap;
+
+/// A single write performed by an `EventScript`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ChangeOp {
+    Insert(Document),
+    Update { filter: Document, update: Document },
+    Delete(Document),
+}
+
+/// A sequence of writes replayed against a collection at a steady pace, to feed change-stream
+/// consumers with a realistic flow of events.
+///
+/// Change streams need a replica set, a standalone server will only apply the writes.
+#[derive(Clone, Debug)]
+pub struct EventScript {
+    ops: Vec<ChangeOp>,
+    interval: Duration,
+}
+
+impl Default for EventScript {
+    fn default() -> Self {
+        EventScript {
+            ops: Vec::new(),
+            interval: Duration::from_millis(100),
+        }
+    }
+}
+
+impl EventScript {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn insert(mut self, document: Document) -> Self {
+        self.ops.push(ChangeOp::Insert(document));
+        self
+    }
+
+    /// Update the first document matching `filter`.
+    pub fn update(mut self, filter: Document, update: Document) -> Self {
+        self.ops.push(ChangeOp::Update { filter, update });
+        self
+    }
+
+    /// Delete the first document matching `filter`.
+    pub fn delete(mut self, filter: Document) -> Self {
+        self.ops.push(ChangeOp::Delete(filter));
+        self
+    }
+
+    /// Pause between two writes, 100ms by default.
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    pub fn ops(&self) -> &[ChangeOp] {
+        &self.ops
+    }
+
+    /// Perform the writes one after another against `collection`.
+    pub async fn run(&self, collection: &Collection<Document>) -> mongodb::error::Result<()> {
+        for (i, op) in self.ops.iter().enumerate() {
+            if i > 0 {
+                tokio::time::sleep(self.interval).await;
+            }
+            match op {
+                ChangeOp::Insert(document) => {
+                    collection.insert_one(document, None).await?;
+                }
+                ChangeOp::Update { filter, update } => {
+                    collection
+                        .update_one(filter.clone(), update.clone(), None)
+                        .await?;
+                }
+                ChangeOp::Delete(filter) => {
+                    collection.delete_one(filter.clone(), None).await?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Run the script in the background, so that the consumer can be driven meanwhile.
+    pub fn spawn(
+        self,
+        collection: Collection<Document>,
+    ) -> tokio::task::JoinHandle<mongodb::error::Result<()>> {
+        tokio::spawn(async move { self.run(&collection).await })
+    }
+}
+
+/// Fake a script of `len` writes over documents `{ _id: <n>, value: <word> }`, where updates and
+/// deletes only target documents inserted and not yet deleted by the script.
+pub struct EventScriptFaker<L = Faker> {
+    len: L,
+}
+
+impl EventScriptFaker<Faker> {
+    pub fn new() -> Self {
+        EventScriptFaker { len: Faker }
+    }
+}
+
+impl Default for EventScriptFaker<Faker> {
+    fn default() -> Self {
+        EventScriptFaker::new()
+    }
+}
+
+impl<L> EventScriptFaker<L> {
+    pub fn len<U>(self, len: U) -> EventScriptFaker<U> {
+        EventScriptFaker { len }
+    }
+}
+
+impl<L> Dummy<EventScriptFaker<L>> for EventScript
+where
+    u8: Dummy<L>,
+{
+    fn dummy_with_rng<R: Rng + ?Sized>(config: &EventScriptFaker<L>, rng: &mut R) -> Self {
+        let len = config.len.fake_with_rng::<u8, R>(rng);
+        let mut script = EventScript::new();
+        let mut alive = Vec::new();
+        let mut next_id = 0i64;
+
+        for _ in 0..len {
+            let value = Word().fake_with_rng::<String, R>(rng);
+            let target = alive.choose(rng).copied();
+            script = match (rng.gen_range(0..4), target) {
+                (2, Some(id)) => {
+                    script.update(doc! { "_id": id }, doc! { "$set": { "value": value } })
+                }
+                (3, Some(id)) => {
+                    alive.retain(|alive| *alive != id);
+                    script.delete(doc! { "_id": id })
+                }
+                _ => {
+                    alive.push(next_id);
+                    next_id += 1;
+                    script.insert(doc! { "_id": next_id - 1, "value": value })
+                }
+            };
+        }
+        script
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "docker")]
+    use mongodb::Client;
+
+    #[cfg(feature = "docker")]
+    use crate::docker::{Builder as ContainerBuilder, HostPort};
+
+    use super::*;
+
+    #[test]
+    fn test_fake_event_script() {
+        let script = EventScriptFaker::new().len(50..51).fake::<EventScript>();
+        assert_eq!(script.ops().len(), 50);
+        assert!(matches!(script.ops()[0], ChangeOp::Insert(_)));
+    }
+
+    #[cfg(feature = "docker")]
+    #[tokio::test]
+    async fn test_run_event_script() {
+        let handler = ContainerBuilder::new("mongo")
+            .bind_port_as_default(Some(HostPort::ANY), 27017)
+            .build_disposable()
+            .await;
+        let collection = Client::with_uri_str(handler.url().unwrap())
+            .await
+            .unwrap()
+            .database("testdb")
+            .collection::<Document>("events");
+
+        EventScript::new()
+            .insert(doc! { "_id": 1, "value": "a" })
+            .insert(doc! { "_id": 2, "value": "b" })
+            .update(doc! { "_id": 1 }, doc! { "$set": { "value": "c" } })
+            .delete(doc! { "_id": 2 })
+            .interval(Duration::from_millis(10))
+            .spawn(collection.clone())
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(collection.count_documents(None, None).await.unwrap(), 1);
+        let document = collection.find_one(None, None).await.unwrap().unwrap();
+        assert_eq!(document.get_str("value").unwrap(), "c");
+    }
+
+    #[cfg(feature = "docker")]
+    #[tokio::test]
+    async fn test_bootstrap() {
+        let handler = ContainerBuilder::new("mongo")
+            .bind_port_as_default(Some(HostPort::ANY), 27017)
+            .build_disposable()
+            .await;
+        let db = Client::with_uri_str(handler.url().unwrap())
+            .await
+            .unwrap()
+            .database("testdb");
+
+        let spec = DatabaseSpec::new().collection(
+            CollectionSpec::new("users")
+                .validator(doc! { "email": { "$type": "string" } })
+                .index(IndexSpec::new(doc! { "email": 1 }).unique(true))
+                .index(IndexSpec::ttl("seen_at", Duration::from_secs(3600)).name("seen_ttl")),
+        );
+        bootstrap(&db, &spec).await.unwrap();
+        bootstrap(&db, &spec).await.unwrap();
+
+        let users = db.collection::<Document>("users");
+        let indexes = users.list_index_names().await.unwrap();
+        assert!(indexes.contains(&"email_1".to_string()));
+        assert!(indexes.contains(&"seen_ttl".to_string()));
+
+        users
+            .insert_one(doc! { "email": "a@example.com" }, None)
+            .await
+            .unwrap();
+        assert!(users
+            .insert_one(doc! { "email": "a@example.com" }, None)
+            .await
+            .is_err());
+        assert!(users.insert_one(doc! { "email": 1 }, None).await.is_err());
+    }
+}
@@ -0,0 +1,175 @@
+use std::time::Duration;
+
+use futures::future::BoxFuture;
+use mongodb::bson::{doc, Document};
+use mongodb::error::ErrorKind;
+use mongodb::options::{CreateCollectionOptions, IndexOptions};
+use mongodb::{Database, IndexModel};
+
+use crate::fixture::Fixture;
+
+/// Code of the server error raised when creating a collection which already exists
+const NAMESPACE_EXISTS: i32 = 48;
+
+/// Declarative layout of a database, applied by `bootstrap` before tests run.
+#[derive(Clone, Debug, Default)]
+pub struct DatabaseSpec {
+    collections: Vec<CollectionSpec>,
+}
+
+impl DatabaseSpec {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn collection(mut self, collection: CollectionSpec) -> Self {
+        self.collections.push(collection);
+        self
+    }
+
+    pub fn collections(&self) -> &[CollectionSpec] {
+        &self.collections
+    }
+}
+
+/// A collection with its validator and indexes.
+#[derive(Clone, Debug)]
+pub struct CollectionSpec {
+    name: String,
+    validator: Option<Document>,
+    indexes: Vec<IndexSpec>,
+}
+
+impl CollectionSpec {
+    pub fn new(name: impl Into<String>) -> Self {
+        CollectionSpec {
+            name: name.into(),
+            validator: None,
+            indexes: Vec::new(),
+        }
+    }
+
+    /// Reject writes of documents not matching `validator`, e.g. `{ "$jsonSchema": ... }`.
+    pub fn validator(mut self, validator: Document) -> Self {
+        self.validator = Some(validator);
+        self
+    }
+
+    pub fn index(mut self, index: IndexSpec) -> Self {
+        self.indexes.push(index);
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+/// An index over the fields of `keys`, e.g. `{ "email": 1 }`.
+#[derive(Clone, Debug)]
+pub struct IndexSpec {
+    keys: Document,
+    name: Option<String>,
+    unique: bool,
+    expire_after: Option<Duration>,
+}
+
+impl IndexSpec {
+    pub fn new(keys: Document) -> Self {
+        IndexSpec {
+            keys,
+            name: None,
+            unique: false,
+            expire_after: None,
+        }
+    }
+
+    /// TTL index removing documents once `field`, a date, is older than `expire_after`.
+    pub fn ttl(field: &str, expire_after: Duration) -> Self {
+        IndexSpec::new(doc! { field: 1 }).expire_after(expire_after)
+    }
+
+    /// Name of the index, derived from the keys by the server by default.
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    pub fn unique(mut self, unique: bool) -> Self {
+        self.unique = unique;
+        self
+    }
+
+    pub fn expire_after(mut self, expire_after: Duration) -> Self {
+        self.expire_after = Some(expire_after);
+        self
+    }
+
+    fn model(&self) -> IndexModel {
+        let options = IndexOptions::builder()
+            .name(self.name.clone())
+            .unique(self.unique.then_some(true))
+            .expire_after(self.expire_after)
+            .build();
+        IndexModel::builder()
+            .keys(self.keys.clone())
+            .options(options)
+            .build()
+    }
+}
+
+/// Create the collections, validators and indexes of `spec` in `db`.
+///
+/// Existing collections are kept, with their validator replaced, so that bootstrapping twice is
+/// harmless.
+pub async fn bootstrap(db: &Database, spec: &DatabaseSpec) -> mongodb::error::Result<()> {
+    for collection in &spec.collections {
+        let options = CreateCollectionOptions::builder()
+            .validator(collection.validator.clone())
+            .build();
+        match db.create_collection(&collection.name, options).await {
+            Ok(()) => {}
+            Err(err) if is_namespace_exists(&err) => {
+                if let Some(validator) = &collection.validator {
+                    let command = doc! { "collMod": &collection.name, "validator": validator };
+                    db.run_command(command, None).await?;
+                }
+            }
+            Err(err) => return Err(err),
+        }
+
+        if !collection.indexes.is_empty() {
+            let models = collection.indexes.iter().map(IndexSpec::model);
+            db.collection::<Document>(&collection.name)
+                .create_indexes(models, None)
+                .await?;
+        }
+    }
+    Ok(())
+}
+
+fn is_namespace_exists(err: &mongodb::error::Error) -> bool {
+    matches!(err.kind.as_ref(), ErrorKind::Command(err) if err.code == NAMESPACE_EXISTS)
+}
+
+/// A database bootstrapped from a spec when set up, and dropped when torn down.
+pub struct SeededDatabase {
+    pub db: Database,
+    spec: DatabaseSpec,
+}
+
+impl SeededDatabase {
+    pub fn new(db: Database, spec: DatabaseSpec) -> Self {
+        SeededDatabase { db, spec }
+    }
+}
+
+impl Fixture for SeededDatabase {
+    fn setup(&mut self) -> BoxFuture<'_, crate::Result<()>> {
+        Box::pin(async move { Ok(bootstrap(&self.db, &self.spec).await?) })
+    }
+
+    fn teardown(self: Box<Self>) -> BoxFuture<'static, crate::Result<()>> {
+        Box::pin(async move { Ok(self.db.drop(None).await?) })
+    }
+}
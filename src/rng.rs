@@ -0,0 +1,154 @@
+//! Randomness of a test, replayable from the seed printed when the test fails.
+//!
+//! The fakers, credentials and names of the crate draw from `TestRng`, which each thread seeds
+//! from `TEST_UTILITIES_SEED` if set, or randomly. Tests running on a multi-threaded runtime
+//! only replay what their own thread draws.
+//!
+//! ```no_run
+//! use test_utilities::fs::{TempFile, TempFileFaker};
+//! use test_utilities::rng::{self, TestRng};
+//! use fake::Fake;
+//!
+//! // prints `TEST_UTILITIES_SEED=...` if the test panics
+//! let _seed = rng::init();
+//! let file: TempFile = TempFileFaker::with_len(10..20).fake_with_rng(&mut TestRng);
+//! ```
+
+use std::cell::RefCell;
+
+use rand::rngs::StdRng;
+use rand::{RngCore, SeedableRng};
+
+/// Environment variable holding the seed to replay, in decimal
+pub const SEED_ENV: &str = "TEST_UTILITIES_SEED";
+
+thread_local! {
+    static CURRENT: RefCell<Option<Seeded>> = const { RefCell::new(None) };
+}
+
+struct Seeded {
+    seed: u64,
+    rng: StdRng,
+}
+
+impl Seeded {
+    fn new(seed: u64) -> Self {
+        Seeded {
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+}
+
+/// The random number generator of the current thread, to pass to `fake_with_rng` and the
+/// other `*_with_rng` functions.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TestRng;
+
+impl TestRng {
+    fn with<T>(f: impl FnOnce(&mut StdRng) -> T) -> T {
+        CURRENT.with(|current| {
+            let mut current = current.borrow_mut();
+            let seeded = current.get_or_insert_with(|| Seeded::new(initial_seed()));
+            f(&mut seeded.rng)
+        })
+    }
+}
+
+impl RngCore for TestRng {
+    fn next_u32(&mut self) -> u32 {
+        TestRng::with(|rng| rng.next_u32())
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        TestRng::with(|rng| rng.next_u64())
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        TestRng::with(|rng| rng.fill_bytes(dest))
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        TestRng::with(|rng| rng.try_fill_bytes(dest))
+    }
+}
+
+/// Seed the current thread from `TEST_UTILITIES_SEED`, or randomly, returning a guard which
+/// prints the seed if the test panics before it is dropped.
+#[must_use = "the seed is only printed if the test panics while the guard is alive"]
+pub fn init() -> SeedGuard {
+    let seed = initial_seed();
+    reseed(seed);
+    SeedGuard { seed }
+}
+
+/// Seed the current thread with `seed`, restarting its sequence.
+pub fn reseed(seed: u64) {
+    CURRENT.with(|current| *current.borrow_mut() = Some(Seeded::new(seed)));
+}
+
+/// The seed of the current thread.
+pub fn seed() -> u64 {
+    CURRENT.with(|current| {
+        current
+            .borrow_mut()
+            .get_or_insert_with(|| Seeded::new(initial_seed()))
+            .seed
+    })
+}
+
+/// A generator seeded from the current thread, for work moved to other threads.
+pub fn fork() -> StdRng {
+    StdRng::seed_from_u64(TestRng.next_u64())
+}
+
+#[derive(Debug)]
+pub struct SeedGuard {
+    seed: u64,
+}
+
+impl SeedGuard {
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+}
+
+impl Drop for SeedGuard {
+    fn drop(&mut self) {
+        if std::thread::panicking() {
+            eprintln!(
+                "replay the randomness of the test with {SEED_ENV}={}",
+                self.seed
+            );
+        }
+    }
+}
+
+fn initial_seed() -> u64 {
+    match std::env::var(SEED_ENV) {
+        Ok(seed) => seed.trim().parse().unwrap_or_else(|err| {
+            panic!("{SEED_ENV} is not a valid seed: {err}");
+        }),
+        Err(_) => rand::thread_rng().next_u64(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng;
+
+    use super::*;
+
+    #[test]
+    fn test_reseed_replays() {
+        reseed(42);
+        let first: Vec<u64> = (0..4).map(|_| TestRng.gen()).collect();
+        let forked = fork().gen::<u64>();
+        reseed(42);
+        let second: Vec<u64> = (0..4).map(|_| TestRng.gen()).collect();
+
+        assert_eq!(first, second);
+        assert_eq!(fork().gen::<u64>(), forked);
+        assert_eq!(seed(), 42);
+    }
+}